@@ -1,6 +1,8 @@
 #version 330 core
 out vec4 colour;
 
+uniform vec4 tint;
+
 void main() {
-    colour = vec4(0.5f, 1.0f, 0.25f, 1.0f);
+    colour = tint;
 }
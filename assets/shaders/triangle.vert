@@ -1,8 +1,10 @@
 #version 330 core
 layout (location = 0) in vec3 pos;
 
-uniform float timeSinceLastFrame;
-uniform float elapsedTime;
+layout (std140) uniform Frame {
+    float elapsedTime;
+    float timeSinceLastFrame;
+};
 
 mat2 rotate2D(float angle) {
     return mat2(cos(angle), -sin(angle), sin(angle), cos(angle));
@@ -6,7 +6,13 @@ use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::ptr;
+use std::sync::Arc;
 
+use crate::assets::preprocessor::{
+    normalize_shader_defines, preprocess_shader_source, PreprocessedSource, ShaderDefine,
+};
+use crate::assets::shader_diagnostic::{parse_shader_info_log, ShaderDiagnostic};
+use crate::assets::{AssetKey, AssetRegistry};
 use crate::c_bridge;
 
 // Errors based on the implementation of std::io::Error and
@@ -57,6 +63,9 @@ impl Error for AssetError {
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum ShaderErrorKind {
     MalformedSource,
+    IncludeError,
+    UnsupportedStage,
+    CreationFailed,
     CompilationError,
 }
 
@@ -65,6 +74,8 @@ pub struct ShaderError {
     source: Option<Box<dyn Error + 'static>>,
     message: String,
     kind: ShaderErrorKind,
+    source_files: Vec<PathBuf>,
+    diagnostics: Vec<ShaderDiagnostic>,
 }
 
 impl ShaderError {
@@ -77,8 +88,35 @@ impl ShaderError {
             source,
             message: message.as_ref().into(),
             kind,
+            source_files: vec![],
+            diagnostics: vec![],
         }
     }
+
+    // Attaches what the compiler reported about the sources of a stage that failed to compile.
+    pub fn with_diagnostics(
+        mut self,
+        source_files: &[PathBuf],
+        diagnostics: Vec<ShaderDiagnostic>,
+    ) -> ShaderError {
+        self.source_files = source_files.to_vec();
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    pub fn kind(&self) -> ShaderErrorKind {
+        self.kind
+    }
+
+    // The files that the stage was compiled from, with the preprocessed file first and the
+    // files it included after it.
+    pub fn source_files(&self) -> &[PathBuf] {
+        self.source_files.as_slice()
+    }
+
+    pub fn diagnostics(&self) -> &[ShaderDiagnostic] {
+        self.diagnostics.as_slice()
+    }
 }
 
 impl fmt::Display for ShaderError {
@@ -93,48 +131,301 @@ impl Error for ShaderError {
     }
 }
 
+// The first shader error with diagnostics in the chain of errors that caused the error, e.g. to
+// find out why a program failed to load.
+pub fn find_shader_error<'a>(error: &'a (dyn Error + 'static)) -> Option<&'a ShaderError> {
+    let mut current_error = Some(error);
+    while let Some(error) = current_error {
+        if let Some(shader_error) = error.downcast_ref::<ShaderError>() {
+            if !shader_error.diagnostics.is_empty() {
+                return Some(shader_error);
+            }
+        }
+        current_error = error.source();
+    }
+
+    None
+}
+
+// A small RGBA8 preview of an asset, top row first, e.g. for the asset browser. The pixels are
+// shared, so thumbnails are cheap to clone.
+#[derive(Clone, Debug)]
+pub struct AssetThumbnail {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Arc<Vec<u8>>,
+}
+
 pub trait Asset {
     fn new<S: AsRef<str>>(id: S, file_path: &Path) -> Result<Self, AssetError>
+    where
+        Self: Sized;
+    // Extensions, without the leading dot, of the files the asset type can be loaded from.
+    fn supported_file_extensions() -> &'static [&'static str]
     where
         Self: Sized;
     fn reload(&mut self) -> Result<(), AssetError>;
     fn destroy(&mut self) -> Result<(), AssetError>;
     fn is_loaded(&self) -> bool;
+    fn is_stale(&self) -> bool;
+    fn mark_stale(&mut self);
     fn get_src_file_path(&self) -> &Path;
+
+    // Other files the asset is built from (e.g. files included by a shader). Changes to them
+    // should reload the asset too.
+    fn get_dependency_file_paths(&self) -> &[PathBuf] {
+        &[]
+    }
+
+    // Other assets the asset is built from (e.g. the shaders of a program). The asset registry
+    // rebuilds the asset whenever one of them is reloaded.
+    fn get_asset_dependencies(&self) -> Vec<AssetKey> {
+        vec![]
+    }
+
+    // Loads, or looks up, the other assets the asset refers to (e.g. the program and textures
+    // of a material). The asset registry calls it whenever the asset is loaded or reloaded
+    // through it. The manager of the asset is unavailable in the meantime, so an asset cannot
+    // refer to assets of its own type.
+    fn load_asset_dependencies(&mut self, _assets: &mut AssetRegistry) -> Result<(), AssetError> {
+        Ok(())
+    }
+
+    // Updates the asset after its asset dependencies have been reloaded.
+    fn rebuild(&mut self, _assets: &AssetRegistry) -> Result<(), AssetError> {
+        Ok(())
+    }
+
+    // A rough estimate, in bytes, of the memory the asset takes up on the CPU and the GPU.
+    // None if the asset cannot tell.
+    fn get_memory_usage(&self) -> Option<usize> {
+        None
+    }
+
+    fn get_thumbnail(&self) -> Option<AssetThumbnail> {
+        None
+    }
+}
+
+// Assets whose loading can be split in two: decoding, which only does file I/O and CPU work
+// and can run on a worker thread, and creating the asset from the decoded data, which uploads
+// it to the GPU and has to run on the thread that owns the GL context.
+pub trait AsyncAsset: Asset + Sized {
+    type Decoded: Send + 'static;
+
+    fn decode_file(file_path: &Path) -> Result<Self::Decoded, AssetError>;
+    fn from_decoded<S: AsRef<str>>(
+        id: S,
+        file_path: &Path,
+        decoded: Self::Decoded,
+    ) -> Result<Self, AssetError>;
+
+    // Reloading is split the same way. The returned decoder reads the file of the asset again
+    // the way the asset was loaded (e.g. with the defines of a shader permutation), and
+    // reload_decoded() swaps what it decoded into the asset. A failed reload leaves the asset
+    // untouched.
+    fn reload_decoder(&self) -> AssetDecoder<Self::Decoded>;
+    fn reload_decoded(&mut self, decoded: Self::Decoded) -> Result<(), AssetError>;
+}
+
+// Decodes a file on a worker thread (see AsyncAsset::reload_decoder()).
+pub type AssetDecoder<D> = Box<dyn FnOnce() -> Result<D, AssetError> + Send>;
+
+// A single compiled stage of a shader asset.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ShaderStage {
+    kind: gl::types::GLenum,
+    shader_id: gl::types::GLuint,
+}
+
+impl ShaderStage {
+    pub fn get_shader_id(&self) -> gl::types::GLuint {
+        self.shader_id
+    }
+
+    pub fn get_shader_kind(&self) -> gl::types::GLenum {
+        self.kind
+    }
+}
+
+// Lines of a single stage in a shader file, paired with their line numbers.
+type StageLines<'a> = (gl::types::GLenum, Vec<(usize, &'a str)>);
+
+// Preprocessed sources of the stages of a shader, ready to be compiled.
+pub struct DecodedShader {
+    defines: Vec<ShaderDefine>,
+    stages: Vec<(gl::types::GLenum, PreprocessedSource)>,
 }
 
 pub struct Shader {
     id: String,
-    shader_id: gl::types::GLuint,
-    kind: gl::types::GLenum,
+    stages: Vec<ShaderStage>,
     src_file_path: PathBuf,
+    included_files: Vec<PathBuf>,
+    defines: Vec<ShaderDefine>,
     is_loaded: bool,
     is_stale: bool,
 }
 
 impl Asset for Shader {
     fn new<S: AsRef<str>>(id: S, file_path: &Path) -> Result<Self, AssetError> {
-        let file_ext: &OsStr = match file_path.extension() {
-            Some(extension) => extension,
-            None => {
+        Self::with_defines(id, file_path, &[])
+    }
+
+    fn supported_file_extensions() -> &'static [&'static str] {
+        &["vert", "frag", "geom", "tesc", "tese", "comp", "glsl"]
+    }
+
+    fn reload(&mut self) -> Result<(), AssetError> {
+        let decoded_shader = (self.reload_decoder())()?;
+        self.reload_decoded(decoded_shader)
+    }
+
+    fn destroy(&mut self) -> Result<(), AssetError> {
+        Self::delete_stages(&self.stages);
+        self.stages.clear();
+        self.included_files.clear();
+
+        self.id.clear();
+        self.src_file_path.clear();
+        self.is_loaded = false;
+
+        Ok(())
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.is_loaded
+    }
+
+    fn is_stale(&self) -> bool {
+        self.is_stale
+    }
+
+    fn mark_stale(&mut self) {
+        self.is_stale = true;
+    }
+
+    fn get_src_file_path(&self) -> &Path {
+        self.src_file_path.as_path()
+    }
+
+    fn get_dependency_file_paths(&self) -> &[PathBuf] {
+        self.included_files.as_slice()
+    }
+}
+
+impl AsyncAsset for Shader {
+    type Decoded = DecodedShader;
+
+    fn decode_file(file_path: &Path) -> Result<DecodedShader, AssetError> {
+        Self::decode_with_defines(file_path, &[])
+    }
+
+    fn from_decoded<S: AsRef<str>>(
+        id: S,
+        file_path: &Path,
+        decoded: DecodedShader,
+    ) -> Result<Self, AssetError> {
+        let (stages, included_files) = match Self::compile_stages(&decoded.stages) {
+            Ok(compiled) => compiled,
+            Err(error) => {
                 return Err(AssetError::new(
                     format!(
-                        "shader source file from {} does not have a valid file extension",
+                        "unable to compile shader from {}",
                         file_path.to_string_lossy()
                     ),
-                    AssetErrorKind::InvalidFileExtension,
-                    None,
-                ));
+                    AssetErrorKind::LoadingFailed,
+                    Some(Box::new(error)),
+                ))
             }
         };
 
-        let kind: gl::types::GLenum = match file_ext.to_str() {
-            Some("vert") => gl::VERTEX_SHADER,
-            Some("frag") => gl::FRAGMENT_SHADER,
-            _ => {
+        Ok(Self {
+            id: id.as_ref().into(),
+            stages,
+            src_file_path: file_path.to_path_buf(),
+            included_files,
+            defines: decoded.defines,
+            is_loaded: true,
+            is_stale: false,
+        })
+    }
+
+    fn reload_decoder(&self) -> AssetDecoder<DecodedShader> {
+        let id = self.id.clone();
+        let src_file_path = self.src_file_path.clone();
+        let defines = self.defines.clone();
+        Box::new(
+            move || match Self::decode_with_defines(src_file_path.as_path(), &defines) {
+                Ok(decoded_shader) => Ok(decoded_shader),
+                Err(error) => Err(AssetError::new(
+                    format!("unable to reload asset, '{}'", id.as_str()),
+                    AssetErrorKind::LoadingFailed,
+                    Some(Box::new(error)),
+                )),
+            },
+        )
+    }
+
+    // The new stages are compiled before the current ones are deleted, so a broken file leaves
+    // the current shader untouched.
+    fn reload_decoded(&mut self, decoded_shader: DecodedShader) -> Result<(), AssetError> {
+        if !self.is_loaded {
+            return Err(AssetError::new(
+                format!("asset, '{}', not yet loaded", self.id.as_str()),
+                AssetErrorKind::NotLoaded,
+                None,
+            ));
+        }
+
+        let (new_stages, included_files) = match Self::compile_stages(&decoded_shader.stages) {
+            Ok(compiled) => compiled,
+            Err(error) => {
                 return Err(AssetError::new(
                     format!(
-                        "shader source file extension of {} is neither \".vert\" or \".frag\".",
+                        "unable to hot-reload shader from {}",
+                        self.src_file_path.to_string_lossy()
+                    ),
+                    AssetErrorKind::ReloadingFailed,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        Self::delete_stages(&self.stages);
+
+        self.stages = new_stages;
+        self.included_files = included_files;
+        self.is_stale = false;
+        Ok(())
+    }
+}
+
+impl Shader {
+    // Compiles a permutation of the shader, with the given defines injected after its #version
+    // line. The defines are kept so that reloads produce the same permutation.
+    pub fn with_defines<S: AsRef<str>>(
+        id: S,
+        file_path: &Path,
+        defines: &[ShaderDefine],
+    ) -> Result<Self, AssetError> {
+        let decoded_shader = Self::decode_with_defines(file_path, defines)?;
+        Self::from_decoded(id, file_path, decoded_shader)
+    }
+
+    // Reads and preprocesses a permutation of the shader. This does not touch the GPU.
+    pub fn decode_with_defines(
+        file_path: &Path,
+        defines: &[ShaderDefine],
+    ) -> Result<DecodedShader, AssetError> {
+        let defines = normalize_shader_defines(defines);
+
+        let file_ext: &OsStr = match file_path.extension() {
+            Some(extension) => extension,
+            None => {
+                return Err(AssetError::new(
+                    format!(
+                        "shader source file from {} does not have a valid file extension",
                         file_path.to_string_lossy()
                     ),
                     AssetErrorKind::InvalidFileExtension,
@@ -143,111 +434,258 @@ impl Asset for Shader {
             }
         };
 
-        match fs::read_to_string(file_path) {
-            Ok(contents) => {
-                let shader_id: gl::types::GLuint = match Self::compile(contents.as_str(), kind) {
-                    Ok(id) => id,
-                    Err(error) => {
-                        return Err(AssetError::new(
-                            format!(
-                                "unable to compile shader from {}",
-                                file_path.to_string_lossy()
-                            ),
-                            AssetErrorKind::LoadingFailed,
-                            Some(Box::new(error)),
-                        ))
-                    }
-                };
-
-                let shader: Self = Self {
-                    id: id.as_ref().into(),
-                    shader_id: shader_id,
-                    kind: kind,
-                    src_file_path: file_path.to_path_buf(),
-                    is_loaded: true,
-                    is_stale: false,
-                };
-
-                Ok(shader)
+        if file_ext.to_str() != Some("glsl") && Self::file_ext_to_kind(file_ext).is_none() {
+            return Err(AssetError::new(
+                format!(
+                    "shader source file extension of {} is not one of \".vert\", \".frag\", \
+                    \".geom\", \".tesc\", \".tese\", \".comp\", or \".glsl\".",
+                    file_path.to_string_lossy()
+                ),
+                AssetErrorKind::InvalidFileExtension,
+                None,
+            ));
+        }
+
+        let contents = match fs::read_to_string(file_path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                return Err(AssetError::new(
+                    format!("unable to load asset from {}", file_path.to_string_lossy()),
+                    AssetErrorKind::LoadingFailed,
+                    Some(Box::new(error)),
+                ))
             }
+        };
+
+        match Self::preprocess_stages(contents.as_str(), file_path, &defines) {
+            Ok(stages) => Ok(DecodedShader { defines, stages }),
             Err(error) => Err(AssetError::new(
-                format!("unable to load asset from {}", file_path.to_string_lossy()),
+                format!(
+                    "unable to preprocess shader from {}",
+                    file_path.to_string_lossy()
+                ),
                 AssetErrorKind::LoadingFailed,
                 Some(Box::new(error)),
             )),
         }
     }
 
-    fn reload(&mut self) -> Result<(), AssetError> {
-        if !self.is_loaded {
-            return Err(AssetError::new(
-                format!("asset, '{}', not yet loaded", self.id.as_str()),
-                AssetErrorKind::NotLoaded,
-                None,
-            ));
+    pub fn get_defines(&self) -> &[ShaderDefine] {
+        self.defines.as_slice()
+    }
+
+    pub fn get_stages(&self) -> &[ShaderStage] {
+        self.stages.as_slice()
+    }
+
+    fn file_ext_to_kind(file_ext: &OsStr) -> Option<gl::types::GLenum> {
+        match file_ext.to_str() {
+            Some("vert") => Some(gl::VERTEX_SHADER),
+            Some("frag") => Some(gl::FRAGMENT_SHADER),
+            Some("geom") => Some(gl::GEOMETRY_SHADER),
+            Some("tesc") => Some(gl::TESS_CONTROL_SHADER),
+            Some("tese") => Some(gl::TESS_EVALUATION_SHADER),
+            Some("comp") => Some(gl::COMPUTE_SHADER),
+            _ => None,
         }
+    }
 
-        match fs::read_to_string(self.src_file_path.as_path()) {
-            Ok(contents) => {
-                let new_shader_id: gl::types::GLuint =
-                    match Self::compile(contents.as_str(), self.kind) {
-                        Ok(id) => id,
-                        Err(error) => {
-                            return Err(AssetError::new(
+    fn stage_name_to_kind(stage_name: &str) -> Option<gl::types::GLenum> {
+        match stage_name {
+            "vertex" => Some(gl::VERTEX_SHADER),
+            "fragment" => Some(gl::FRAGMENT_SHADER),
+            "geometry" => Some(gl::GEOMETRY_SHADER),
+            "tess_control" => Some(gl::TESS_CONTROL_SHADER),
+            "tess_evaluation" => Some(gl::TESS_EVALUATION_SHADER),
+            "compute" => Some(gl::COMPUTE_SHADER),
+            _ => None,
+        }
+    }
+
+    pub fn stage_kind_name(kind: gl::types::GLenum) -> &'static str {
+        match kind {
+            gl::VERTEX_SHADER => "vertex",
+            gl::FRAGMENT_SHADER => "fragment",
+            gl::GEOMETRY_SHADER => "geometry",
+            gl::TESS_CONTROL_SHADER => "tess_control",
+            gl::TESS_EVALUATION_SHADER => "tess_evaluation",
+            gl::COMPUTE_SHADER => "compute",
+            _ => "unknown",
+        }
+    }
+
+    // The OpenGL version that a stage needs. Tessellation stages became core in 4.0, and
+    // compute shaders in 4.3.
+    fn required_gl_version(kind: gl::types::GLenum) -> (gl::types::GLint, gl::types::GLint) {
+        match kind {
+            gl::TESS_CONTROL_SHADER | gl::TESS_EVALUATION_SHADER => (4, 0),
+            gl::COMPUTE_SHADER => (4, 3),
+            _ => (3, 3),
+        }
+    }
+
+    fn context_gl_version() -> (gl::types::GLint, gl::types::GLint) {
+        let mut major_version: gl::types::GLint = 0;
+        let mut minor_version: gl::types::GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAJOR_VERSION, &mut major_version);
+            gl::GetIntegerv(gl::MINOR_VERSION, &mut minor_version);
+        };
+
+        (major_version, minor_version)
+    }
+
+    // Splits the source of a shader file into the lines of its stages, each paired with its
+    // line number in the file. Single-stage files get their stage from their file extension.
+    // Combined ".glsl" files are split at each "#pragma stage <name>" line, with everything
+    // before the first one (e.g. the #version line and shared code) prepended to every stage.
+    fn split_stages<'a>(
+        src: &'a str,
+        file_path: &Path,
+    ) -> Result<Vec<StageLines<'a>>, ShaderError> {
+        let numbered_lines = src
+            .lines()
+            .enumerate()
+            .map(|(line_index, line)| (line_index + 1, line));
+
+        if let Some(kind) = file_path.extension().and_then(Self::file_ext_to_kind) {
+            return Ok(vec![(kind, numbered_lines.collect())]);
+        }
+
+        let mut prelude: Vec<(usize, &str)> = vec![];
+        let mut stages: Vec<StageLines> = vec![];
+        for (line_number, line) in numbered_lines {
+            let stage_name = line
+                .trim()
+                .strip_prefix("#pragma")
+                .map(str::trim_start)
+                .and_then(|directive| directive.strip_prefix("stage"))
+                .map(str::trim);
+
+            match stage_name {
+                Some(stage_name) => {
+                    let kind = match Self::stage_name_to_kind(stage_name) {
+                        Some(kind) => kind,
+                        None => {
+                            return Err(ShaderError::new(
                                 format!(
-                                    "unable to hot-reload shader from {}",
-                                    self.src_file_path.to_string_lossy()
+                                    "unknown shader stage, \"{}\", at line {}",
+                                    stage_name, line_number
                                 ),
-                                AssetErrorKind::ReloadingFailed,
-                                Some(Box::new(error)),
+                                ShaderErrorKind::MalformedSource,
+                                None,
                             ))
                         }
                     };
 
-                unsafe {
-                    gl::DeleteShader(self.shader_id);
-                }
+                    if stages.iter().any(|(stage_kind, _)| *stage_kind == kind) {
+                        return Err(ShaderError::new(
+                            format!(
+                                "shader stage, \"{}\", is declared more than once",
+                                stage_name
+                            ),
+                            ShaderErrorKind::MalformedSource,
+                            None,
+                        ));
+                    }
 
-                self.shader_id = new_shader_id;
-                self.is_stale = false;
-                Ok(())
+                    stages.push((kind, prelude.clone()));
+                }
+                None => {
+                    let section = match stages.last_mut() {
+                        Some((_, stage_lines)) => stage_lines,
+                        None => &mut prelude,
+                    };
+                    section.push((line_number, line));
+                }
             }
-            Err(error) => Err(AssetError::new(
-                format!("unable to reload asset, '{}'", self.id.as_str()),
-                AssetErrorKind::LoadingFailed,
-                Some(Box::new(error)),
-            )),
         }
-    }
 
-    fn destroy(&mut self) -> Result<(), AssetError> {
-        unsafe {
-            gl::DeleteShader(self.shader_id);
-        };
-
-        self.id.clear();
-        self.src_file_path.clear();
-        self.is_loaded = false;
+        if stages.is_empty() {
+            return Err(ShaderError::new(
+                "combined shader source does not declare any \"#pragma stage\" sections",
+                ShaderErrorKind::MalformedSource,
+                None,
+            ));
+        }
 
-        Ok(())
+        Ok(stages)
     }
 
-    fn is_loaded(&self) -> bool {
-        self.is_loaded
-    }
+    // Splits a shader file into its stages and expands their #include directives. This does
+    // not touch the GPU.
+    fn preprocess_stages(
+        src: &str,
+        file_path: &Path,
+        defines: &[ShaderDefine],
+    ) -> Result<Vec<(gl::types::GLenum, PreprocessedSource)>, ShaderError> {
+        let mut preprocessed_stages: Vec<(gl::types::GLenum, PreprocessedSource)> = vec![];
+        for (kind, stage_lines) in Self::split_stages(src, file_path)? {
+            let preprocessed_source = preprocess_shader_source(&stage_lines, file_path, defines)?;
+            preprocessed_stages.push((kind, preprocessed_source));
+        }
 
-    fn get_src_file_path(&self) -> &Path {
-        self.src_file_path.as_path()
+        Ok(preprocessed_stages)
     }
-}
 
-impl Shader {
-    pub fn get_shader_id(&self) -> gl::types::GLuint {
-        self.shader_id
+    // Either every stage compiles, or none of them are kept. Also returns the files included
+    // by any of the stages.
+    fn compile_stages(
+        preprocessed_stages: &[(gl::types::GLenum, PreprocessedSource)],
+    ) -> Result<(Vec<ShaderStage>, Vec<PathBuf>), ShaderError> {
+        let mut stages: Vec<ShaderStage> = vec![];
+        let mut included_files: Vec<PathBuf> = vec![];
+        for (kind, preprocessed_source) in preprocessed_stages {
+            let kind = *kind;
+            for included_file in preprocessed_source.included_files() {
+                if !included_files.contains(included_file) {
+                    included_files.push(included_file.clone());
+                }
+            }
+
+            match Self::compile(preprocessed_source.source.as_str(), kind) {
+                Ok(shader_id) => stages.push(ShaderStage { kind, shader_id }),
+                Err(error) => {
+                    Self::delete_stages(&stages);
+
+                    // Compiler errors refer to files by the source string number set in the
+                    // #line directives emitted by the preprocessor.
+                    let mut message =
+                        format!("unable to compile {} stage", Self::stage_kind_name(kind));
+                    if preprocessed_source.source_files.len() > 1 {
+                        for (index, source_file) in
+                            preprocessed_source.source_files.iter().enumerate()
+                        {
+                            message.push_str(
+                                format!("\n  source {}: {}", index, source_file.to_string_lossy())
+                                    .as_str(),
+                            );
+                        }
+                    }
+
+                    let diagnostics = match error.kind {
+                        ShaderErrorKind::CompilationError => parse_shader_info_log(
+                            error.message.as_str(),
+                            &preprocessed_source.source_files,
+                        ),
+                        _ => vec![],
+                    };
+
+                    return Err(ShaderError::new(message, error.kind, Some(Box::new(error)))
+                        .with_diagnostics(&preprocessed_source.source_files, diagnostics));
+                }
+            }
+        }
+
+        Ok((stages, included_files))
     }
 
-    pub fn get_shader_kind(&self) -> gl::types::GLenum {
-        self.kind
+    fn delete_stages(stages: &[ShaderStage]) {
+        for stage in stages {
+            unsafe {
+                gl::DeleteShader(stage.shader_id);
+            };
+        }
     }
 
     // Based on:
@@ -265,7 +703,37 @@ impl Shader {
             }
         };
 
+        // Creating a shader of a stage that the context does not support fails, and the stage
+        // would otherwise silently drop out of the programs it is linked into.
+        let required_version = Self::required_gl_version(kind);
+        let context_version = Self::context_gl_version();
+        if context_version < required_version {
+            return Err(ShaderError::new(
+                format!(
+                    "{} stage requires OpenGL {}.{}, but the context is OpenGL {}.{}",
+                    Self::stage_kind_name(kind),
+                    required_version.0,
+                    required_version.1,
+                    context_version.0,
+                    context_version.1
+                ),
+                ShaderErrorKind::UnsupportedStage,
+                None,
+            ));
+        }
+
         let shader_id: gl::types::GLuint = unsafe { gl::CreateShader(kind) };
+        if shader_id == 0 {
+            return Err(ShaderError::new(
+                format!(
+                    "unable to create a {} shader object",
+                    Self::stage_kind_name(kind)
+                ),
+                ShaderErrorKind::CreationFailed,
+                None,
+            ));
+        }
+
         unsafe {
             gl::ShaderSource(
                 shader_id,
@@ -295,10 +763,11 @@ impl Shader {
                     ptr::null_mut(),
                     error_msg.as_ptr() as *mut gl::types::GLchar,
                 );
+                gl::DeleteShader(shader_id);
             };
 
             return Err(ShaderError::new(
-                error_msg.to_string_lossy().into_owned(),
+                error_msg.to_string_lossy().trim_end_matches('\0'),
                 ShaderErrorKind::CompilationError,
                 None,
             ));
@@ -0,0 +1,142 @@
+use crate::assets::AssetRegistry;
+use crate::console::{ArgKind, Command, CommandArg, CommandError, CommandErrorKind};
+use crate::console::{CommandArgs, CommandRegistry};
+
+// Registers "reload <asset-id>", "list-assets [filter]", and "collect-garbage". The commands
+// reach the assets through their context.
+pub fn register_asset_commands<C>(commands: &mut CommandRegistry<C>)
+where
+    C: AsRef<AssetRegistry> + AsMut<AssetRegistry> + 'static,
+{
+    commands.register(
+        Command::new(
+            "reload",
+            "Reloads an asset from its file, and rebuilds the assets built from it.",
+            reload_asset,
+        )
+        .arg(CommandArg::new("asset-id", ArgKind::String).completer(get_asset_ids)),
+    );
+    commands.register(
+        Command::new(
+            "list-assets",
+            "Lists the loaded assets, or the ones whose type or ID contains the filter.",
+            list_assets,
+        )
+        .arg(CommandArg::new("filter", ArgKind::String).optional()),
+    );
+    commands.register(Command::new(
+        "collect-garbage",
+        "Unloads the assets that are no longer used, regardless of the unload policy.",
+        collect_garbage,
+    ));
+}
+
+fn reload_asset<C: AsMut<AssetRegistry>>(
+    context: &mut C,
+    args: &CommandArgs,
+) -> Result<Vec<String>, CommandError> {
+    let asset_id = args.get_str("asset-id").unwrap_or_default();
+    let assets = context.as_mut();
+
+    let keys = assets.find_assets(asset_id);
+    if keys.is_empty() {
+        return Err(CommandError::new(
+            format!("there is no asset with ID, \"{}\"", asset_id),
+            CommandErrorKind::InvalidArgument,
+            None,
+        ));
+    }
+
+    // Reloading can fail halfway through, e.g. when an asset reloads but one of the assets
+    // built from it fails to rebuild. The first error is the one that gets reported in full.
+    let mut errors = assets.reload_assets(&keys).into_iter();
+    match errors.next() {
+        None => Ok(vec![format!("Reloaded asset, \"{}\"", asset_id)]),
+        Some(error) => {
+            let num_other_errors = errors.count();
+            let message = match num_other_errors {
+                0 => format!("failed to reload asset, \"{}\"", asset_id),
+                _ => format!(
+                    "failed to reload asset, \"{}\", with {} other error(s)",
+                    asset_id, num_other_errors
+                ),
+            };
+
+            Err(CommandError::new(
+                message,
+                CommandErrorKind::ExecutionFailed,
+                Some(Box::new(error)),
+            ))
+        }
+    }
+}
+
+fn list_assets<C: AsRef<AssetRegistry>>(
+    context: &mut C,
+    args: &CommandArgs,
+) -> Result<Vec<String>, CommandError> {
+    let filter = args.get_str("filter").unwrap_or_default().to_lowercase();
+
+    let mut lines: Vec<String> = vec![];
+    for info in context.as_ref().asset_infos() {
+        let type_name = info.key().short_type_name();
+        if !type_name.to_lowercase().contains(filter.as_str())
+            && !info.id().to_lowercase().contains(filter.as_str())
+        {
+            continue;
+        }
+
+        let mut line = format!("{:<10} {}", type_name, info.id());
+        if !info.src_file_path().as_os_str().is_empty() {
+            line.push_str(format!(" ({})", info.src_file_path().to_string_lossy()).as_str());
+        }
+        if info.is_missing() {
+            line.push_str(" [missing]");
+        } else if info.is_stale() {
+            line.push_str(" [stale]");
+        }
+        if !info.is_loaded() {
+            line.push_str(" [not loaded]");
+        }
+        lines.push(line);
+    }
+
+    match lines.is_empty() {
+        true => Ok(vec![String::from("No assets found.")]),
+        false => Ok(lines),
+    }
+}
+
+fn collect_garbage<C: AsMut<AssetRegistry>>(
+    context: &mut C,
+    _args: &CommandArgs,
+) -> Result<Vec<String>, CommandError> {
+    let assets = context.as_mut();
+    let num_assets = assets.asset_infos().len();
+
+    let mut errors = assets.collect_garbage().into_iter();
+    let num_unloaded_assets = num_assets.saturating_sub(assets.asset_infos().len());
+    match errors.next() {
+        None => Ok(vec![format!(
+            "Unloaded {} unused asset(s)",
+            num_unloaded_assets
+        )]),
+        Some(error) => Err(CommandError::new(
+            format!(
+                "failed to unload an unused asset, with {} other error(s)",
+                errors.count()
+            ),
+            CommandErrorKind::ExecutionFailed,
+            Some(Box::new(error)),
+        )),
+    }
+}
+
+fn get_asset_ids<C: AsRef<AssetRegistry>>(context: &C) -> Vec<String> {
+    context
+        .as_ref()
+        .asset_infos()
+        .into_iter()
+        .map(|info| String::from(info.id()))
+        .collect()
+}
@@ -0,0 +1,313 @@
+use std::any::{self, TypeId};
+use std::collections::{HashMap, HashSet};
+
+use crate::assets::{AssetManagerError, AssetManagerErrorKind, Handle, WeakHandle};
+
+// Identifies an asset of any type, so that assets of different types can depend on each
+// other, e.g. a program on its shaders.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct AssetKey {
+    type_id: TypeId,
+    type_name: &'static str,
+    index: u32,
+    generation: u32,
+}
+
+impl AssetKey {
+    pub fn of<A: 'static>(handle: &Handle<A>) -> Self {
+        Self::from_weak(handle.downgrade())
+    }
+
+    pub fn from_weak<A: 'static>(handle: WeakHandle<A>) -> Self {
+        Self {
+            type_id: TypeId::of::<A>(),
+            type_name: any::type_name::<A>(),
+            index: handle.index(),
+            generation: handle.generation(),
+        }
+    }
+
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    // The type name without its module path, e.g. "Shader".
+    pub fn short_type_name(&self) -> &'static str {
+        match self.type_name.rsplit_once("::") {
+            Some((_, short_type_name)) => short_type_name,
+            None => self.type_name,
+        }
+    }
+
+    // Returns None if the key belongs to an asset of another type.
+    pub fn downcast<A: 'static>(&self) -> Option<WeakHandle<A>> {
+        match self.type_id == TypeId::of::<A>() {
+            true => Some(WeakHandle::new(self.index, self.generation)),
+            false => None,
+        }
+    }
+}
+
+// Tracks which assets are built from which other assets. File dependencies, such as the files
+// included by a shader, are tracked by the asset managers instead.
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    dependencies: HashMap<AssetKey, Vec<AssetKey>>,
+    dependents: HashMap<AssetKey, Vec<AssetKey>>,
+}
+
+impl DependencyGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replaces the dependencies of an asset.
+    pub fn set_dependencies(&mut self, dependent: AssetKey, dependencies: &[AssetKey]) {
+        self.remove_dependencies(dependent);
+
+        let mut unique_dependencies: Vec<AssetKey> = vec![];
+        for dependency in dependencies {
+            if !unique_dependencies.contains(dependency) {
+                unique_dependencies.push(*dependency);
+                self.dependents
+                    .entry(*dependency)
+                    .or_default()
+                    .push(dependent);
+            }
+        }
+        if !unique_dependencies.is_empty() {
+            self.dependencies.insert(dependent, unique_dependencies);
+        }
+    }
+
+    // Forgets the asset, along with the edges from and to it.
+    pub fn remove_asset(&mut self, key: AssetKey) {
+        self.remove_dependencies(key);
+        if let Some(dependents) = self.dependents.remove(&key) {
+            for dependent in dependents {
+                if let Some(dependencies) = self.dependencies.get_mut(&dependent) {
+                    dependencies.retain(|dependency| *dependency != key);
+                }
+            }
+        }
+    }
+
+    pub fn get_dependencies(&self, key: AssetKey) -> &[AssetKey] {
+        match self.dependencies.get(&key) {
+            Some(dependencies) => dependencies.as_slice(),
+            None => &[],
+        }
+    }
+
+    pub fn get_dependents(&self, key: AssetKey) -> &[AssetKey] {
+        match self.dependents.get(&key) {
+            Some(dependents) => dependents.as_slice(),
+            None => &[],
+        }
+    }
+
+    pub fn keys(&self) -> Vec<AssetKey> {
+        let mut keys: HashSet<AssetKey> = HashSet::new();
+        keys.extend(self.dependencies.keys());
+        keys.extend(self.dependents.keys());
+
+        keys.into_iter().collect()
+    }
+
+    // Returns every asset that depends, directly or not, on the changed assets, ordered so
+    // that each asset comes after all of its dependencies. Changed assets are only included
+    // if they depend on other changed assets, in which case they get rebuilt after them.
+    pub fn get_dependents_in_reload_order(
+        &self,
+        changed: &[AssetKey],
+    ) -> Result<Vec<AssetKey>, AssetManagerError> {
+        let mut affected: HashSet<AssetKey> = HashSet::new();
+        let mut unvisited: Vec<AssetKey> = changed.to_vec();
+        while let Some(key) = unvisited.pop() {
+            for dependent in self.get_dependents(key) {
+                if affected.insert(*dependent) {
+                    unvisited.push(*dependent);
+                }
+            }
+        }
+
+        // Kahn's algorithm over the affected assets. Edges from unaffected assets are ignored,
+        // since those assets are not reloaded.
+        let mut num_pending_dependencies: HashMap<AssetKey, usize> = affected
+            .iter()
+            .map(|key| {
+                let num_dependencies = self
+                    .get_dependencies(*key)
+                    .iter()
+                    .filter(|dependency| affected.contains(*dependency))
+                    .count();
+                (*key, num_dependencies)
+            })
+            .collect();
+
+        let mut ready: Vec<AssetKey> = num_pending_dependencies
+            .iter()
+            .filter(|(_, num_dependencies)| **num_dependencies == 0)
+            .map(|(key, _)| *key)
+            .collect();
+        let mut ordered: Vec<AssetKey> = vec![];
+        while let Some(key) = ready.pop() {
+            ordered.push(key);
+            for dependent in self.get_dependents(key) {
+                if let Some(num_dependencies) = num_pending_dependencies.get_mut(dependent) {
+                    *num_dependencies -= 1;
+                    if *num_dependencies == 0 {
+                        ready.push(*dependent);
+                    }
+                }
+            }
+        }
+
+        if ordered.len() != affected.len() {
+            let cycle_type_names: Vec<&str> = affected
+                .iter()
+                .filter(|key| !ordered.contains(key))
+                .map(|key| key.type_name())
+                .collect();
+
+            return Err(AssetManagerError::new(
+                format!(
+                    "assets depend on each other in a cycle, between assets of type(s): {}",
+                    cycle_type_names.join(", ")
+                ),
+                AssetManagerErrorKind::DependencyCycle,
+                None,
+            ));
+        }
+
+        Ok(ordered)
+    }
+
+    fn remove_dependencies(&mut self, dependent: AssetKey) {
+        if let Some(dependencies) = self.dependencies.remove(&dependent) {
+            for dependency in dependencies {
+                if let Some(dependents) = self.dependents.get_mut(&dependency) {
+                    dependents.retain(|key| *key != dependent);
+                    if dependents.is_empty() {
+                        self.dependents.remove(&dependency);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Shader;
+    struct Program;
+
+    fn shader_key(index: u32) -> AssetKey {
+        AssetKey::from_weak(WeakHandle::<Shader>::new(index, 0))
+    }
+
+    fn program_key(index: u32) -> AssetKey {
+        AssetKey::from_weak(WeakHandle::<Program>::new(index, 0))
+    }
+
+    fn position(keys: &[AssetKey], key: AssetKey) -> usize {
+        keys.iter().position(|other_key| *other_key == key).unwrap()
+    }
+
+    #[test]
+    fn keeps_keys_of_different_types_apart() {
+        assert_ne!(shader_key(0), program_key(0));
+        assert!(shader_key(0).type_name().ends_with("::Shader"));
+        assert!(shader_key(0).downcast::<Shader>().is_some());
+        assert!(shader_key(0).downcast::<Program>().is_none());
+    }
+
+    #[test]
+    fn replaces_dependencies() {
+        let mut graph = DependencyGraph::new();
+        graph.set_dependencies(program_key(0), &[shader_key(0), shader_key(1)]);
+        graph.set_dependencies(program_key(0), &[shader_key(1), shader_key(1)]);
+
+        assert_eq!(graph.get_dependencies(program_key(0)), &[shader_key(1)]);
+        assert_eq!(graph.get_dependents(shader_key(0)), &[]);
+        assert_eq!(graph.get_dependents(shader_key(1)), &[program_key(0)]);
+    }
+
+    #[test]
+    fn removes_edges_of_removed_assets() {
+        let mut graph = DependencyGraph::new();
+        graph.set_dependencies(program_key(0), &[shader_key(0)]);
+        graph.set_dependencies(program_key(1), &[program_key(0)]);
+        graph.remove_asset(program_key(0));
+
+        assert_eq!(graph.get_dependents(shader_key(0)), &[]);
+        assert_eq!(graph.get_dependencies(program_key(1)), &[]);
+    }
+
+    #[test]
+    fn orders_dependents_after_their_dependencies() {
+        // shader 0 <- program 0 <- program 2
+        //          <- program 1 <-
+        // shader 1 <- program 2
+        let mut graph = DependencyGraph::new();
+        graph.set_dependencies(program_key(0), &[shader_key(0)]);
+        graph.set_dependencies(program_key(1), &[shader_key(0)]);
+        graph.set_dependencies(
+            program_key(2),
+            &[program_key(0), program_key(1), shader_key(1)],
+        );
+
+        let ordered = graph
+            .get_dependents_in_reload_order(&[shader_key(0)])
+            .unwrap();
+        assert_eq!(ordered.len(), 3);
+        assert!(position(&ordered, program_key(0)) < position(&ordered, program_key(2)));
+        assert!(position(&ordered, program_key(1)) < position(&ordered, program_key(2)));
+
+        let ordered = graph
+            .get_dependents_in_reload_order(&[shader_key(1)])
+            .unwrap();
+        assert_eq!(ordered, vec![program_key(2)]);
+    }
+
+    #[test]
+    fn reloads_nothing_for_assets_without_dependents() {
+        let mut graph = DependencyGraph::new();
+        graph.set_dependencies(program_key(0), &[shader_key(0)]);
+
+        let ordered = graph
+            .get_dependents_in_reload_order(&[program_key(0), shader_key(1)])
+            .unwrap();
+        assert!(ordered.is_empty());
+    }
+
+    #[test]
+    fn rebuilds_changed_assets_that_depend_on_other_changed_assets() {
+        let mut graph = DependencyGraph::new();
+        graph.set_dependencies(program_key(0), &[shader_key(0)]);
+
+        let ordered = graph
+            .get_dependents_in_reload_order(&[shader_key(0), program_key(0)])
+            .unwrap();
+        assert_eq!(ordered, vec![program_key(0)]);
+    }
+
+    #[test]
+    fn detects_dependency_cycles() {
+        let mut graph = DependencyGraph::new();
+        graph.set_dependencies(program_key(0), &[shader_key(0), program_key(1)]);
+        graph.set_dependencies(program_key(1), &[program_key(0)]);
+
+        let error = graph
+            .get_dependents_in_reload_order(&[shader_key(0)])
+            .unwrap_err();
+        assert!(error.to_string().contains("cycle"));
+        assert!(error.to_string().contains(program_key(0).type_name()));
+    }
+}
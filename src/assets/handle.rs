@@ -0,0 +1,128 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+// A reference to an asset stored in an AssetManager. The index points at a slot in the manager,
+// and the generation tells whether the slot still holds the asset the handle was created for.
+// Destroying an asset bumps the generation of its slot, so handles to it become stale instead
+// of silently pointing at whatever gets loaded into the slot next.
+//
+// Handles keep their asset alive. Once the last one is dropped, the manager unloads the asset
+// according to its unload policy.
+pub struct Handle<A> {
+    weak_handle: WeakHandle<A>,
+    // Shared with the slot of the asset, which counts as one reference.
+    ref_count: Arc<()>,
+}
+
+impl<A> Handle<A> {
+    pub(crate) fn new(weak_handle: WeakHandle<A>, ref_count: Arc<()>) -> Self {
+        Self {
+            weak_handle,
+            ref_count,
+        }
+    }
+
+    pub fn index(&self) -> u32 {
+        self.weak_handle.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.weak_handle.generation
+    }
+
+    pub fn downgrade(&self) -> WeakHandle<A> {
+        self.weak_handle
+    }
+}
+
+impl<A> Clone for Handle<A> {
+    fn clone(&self) -> Self {
+        Self {
+            weak_handle: self.weak_handle,
+            ref_count: Arc::clone(&self.ref_count),
+        }
+    }
+}
+
+impl<A> PartialEq for Handle<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.weak_handle == other.weak_handle
+    }
+}
+
+impl<A> Eq for Handle<A> {}
+
+impl<A> Hash for Handle<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.weak_handle.hash(state);
+    }
+}
+
+impl<A> fmt::Debug for Handle<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Handle({}v{})",
+            self.weak_handle.index, self.weak_handle.generation
+        )
+    }
+}
+
+// A handle that does not keep its asset alive. AssetManager::upgrade() turns it back into a
+// handle while the asset is still loaded.
+pub struct WeakHandle<A> {
+    index: u32,
+    generation: u32,
+    // fn() -> A keeps the handle Send, Sync and Copy regardless of A.
+    _marker: PhantomData<fn() -> A>,
+}
+
+impl<A> WeakHandle<A> {
+    pub(crate) fn new(index: u32, generation: u32) -> Self {
+        Self {
+            index,
+            generation,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+// Implemented by hand, since deriving them would require A to implement them as well.
+impl<A> Clone for WeakHandle<A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A> Copy for WeakHandle<A> {}
+
+impl<A> PartialEq for WeakHandle<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<A> Eq for WeakHandle<A> {}
+
+impl<A> Hash for WeakHandle<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<A> fmt::Debug for WeakHandle<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WeakHandle({}v{})", self.index, self.generation)
+    }
+}
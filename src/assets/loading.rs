@@ -0,0 +1,125 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::assets::{Asset, AssetError, Handle};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum AssetLoadStatus {
+    Pending,
+    Loaded,
+    Failed,
+}
+
+pub(crate) enum AssetLoadState<A: Asset> {
+    Pending,
+    Loaded(Handle<A>),
+    Failed(String),
+}
+
+// Tracks an asset that is being loaded in the background. The asset becomes available once
+// AssetManager::pump() has uploaded it.
+pub struct AssetLoadHandle<A: Asset> {
+    asset_id: String,
+    state: Arc<Mutex<AssetLoadState<A>>>,
+}
+
+impl<A: Asset> Clone for AssetLoadHandle<A> {
+    fn clone(&self) -> Self {
+        Self {
+            asset_id: self.asset_id.clone(),
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<A: Asset> AssetLoadHandle<A> {
+    pub(crate) fn new(asset_id: String, state: Arc<Mutex<AssetLoadState<A>>>) -> Self {
+        Self { asset_id, state }
+    }
+
+    pub fn asset_id(&self) -> &str {
+        self.asset_id.as_str()
+    }
+
+    pub fn status(&self) -> AssetLoadStatus {
+        match self.state.lock() {
+            Ok(state) => match *state {
+                AssetLoadState::Pending => AssetLoadStatus::Pending,
+                AssetLoadState::Loaded(_) => AssetLoadStatus::Loaded,
+                AssetLoadState::Failed(_) => AssetLoadStatus::Failed,
+            },
+            Err(_) => AssetLoadStatus::Failed,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.status() != AssetLoadStatus::Pending
+    }
+
+    // Returns the handle to the asset once it has been loaded.
+    pub fn get(&self) -> Option<Handle<A>> {
+        match self.state.lock() {
+            Ok(state) => match &*state {
+                AssetLoadState::Loaded(handle) => Some(handle.clone()),
+                _ => None,
+            },
+            Err(_) => None,
+        }
+    }
+
+    pub fn error_message(&self) -> Option<String> {
+        match self.state.lock() {
+            Ok(state) => match &*state {
+                AssetLoadState::Failed(message) => Some(message.clone()),
+                _ => None,
+            },
+            Err(_) => Some(format!(
+                "load state of asset, \"{}\", is poisoned",
+                self.asset_id
+            )),
+        }
+    }
+}
+
+pub(crate) type AssetCreator<A> = Box<dyn FnOnce(&str, &Path) -> Result<A, AssetError> + Send>;
+pub(crate) type AssetUpdater<A> = Box<dyn FnOnce(&mut A) -> Result<(), AssetError> + Send>;
+
+// Result of decoding an asset on a worker thread, waiting to be created, or swapped into the
+// asset it is a new version of, on the GL thread. Errors are not necessarily sendable across
+// threads, so decoding errors are sent as their messages instead.
+pub(crate) enum PendingUpload<A> {
+    Load {
+        asset_id: String,
+        abs_file_path: PathBuf,
+        create_asset: Result<AssetCreator<A>, String>,
+    },
+    // The manager keeps track of which asset each reload is for, so that reloads of assets
+    // that got destroyed in the meantime can be told apart.
+    Reload {
+        reload_id: u64,
+        update_asset: Result<AssetUpdater<A>, String>,
+    },
+}
+
+// What AssetManager::pump() finished in the background, for the asset registry to follow up on.
+pub(crate) enum FinishedUpload<A> {
+    // Loaded with AssetManager::load_asset_async().
+    Loaded(Handle<A>),
+    // Loaded from a file that appeared in a watched directory (see
+    // AssetManager::load_new_asset_file()).
+    NewFile(Handle<A>),
+    Reloaded(Handle<A>),
+}
+
+// Joins the messages of an error and its sources, e.g. "unable to load texture: file not found".
+pub(crate) fn describe_error_chain(error: &dyn Error) -> String {
+    let mut description = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        description.push_str(format!(": {}", error).as_str());
+        source = error.source();
+    }
+
+    description
+}
@@ -1,17 +1,34 @@
-use crate::assets::{Asset, AssetError, AssetErrorKind};
+use crate::assets::{
+    normalize_path, normalize_shader_defines, Asset, AssetError, Shader, ShaderDefine,
+};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::env;
 use std::error::Error;
+use std::ffi::OsStr;
 use std::fmt;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
+use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::assets::loading::{
+    describe_error_chain, AssetCreator, AssetLoadState, AssetUpdater, FinishedUpload, PendingUpload,
+};
+use crate::assets::{AssetLoadHandle, AssetRegistry, AsyncAsset, Handle, WeakHandle, WorkerPool};
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum AssetManagerErrorKind {
     AssetLoadError,
-    AssetLockPoisoned,
     AssetReloadError,
+    AssetReloadCallbackError,
+    AssetMissing,
+    StaleHandle,
+    DependencyCycle,
+    UnregisteredAssetType,
+    WorkerPoolError,
     AssetDestructionError,
     CurrentWorkingDirectoryError,
 }
@@ -49,184 +66,674 @@ impl Error for AssetManagerError {
     }
 }
 
+// Decides when assets that no longer have any handles to them get destroyed.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum UnloadPolicy {
+    // Destroyed by the next call to pump().
+    Immediate,
+    // Destroyed by pump() once they have been unused for the given duration, so that assets
+    // that are dropped and loaded again shortly after (e.g. when switching scenes) are kept.
+    GracePeriod(Duration),
+    // Only destroyed by collect_garbage().
+    Manual,
+}
+
+impl UnloadPolicy {
+    // Parses a policy as typed in the console or a config file: "immediate", "manual", or the
+    // grace period in seconds, e.g. "2.5".
+    pub fn parse(text: &str) -> Option<UnloadPolicy> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "immediate" => Some(UnloadPolicy::Immediate),
+            "manual" => Some(UnloadPolicy::Manual),
+            text => match text.parse::<f64>() {
+                Ok(seconds) if seconds.is_finite() && seconds >= 0.0 => {
+                    Some(UnloadPolicy::GracePeriod(Duration::from_secs_f64(seconds)))
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+type ReloadCallback<A> = Box<dyn FnMut(&AssetManager<A>) -> Result<(), Box<dyn Error + 'static>>>;
+
+// Decodes the file of an asset again on a worker thread, into a function that swaps the new
+// version into the asset.
+type ReloadJob<A> = Box<dyn FnOnce() -> Result<AssetUpdater<A>, String> + Send>;
+
+// The parts of AsyncAsset that the manager uses without requiring it of its asset type (see
+// AssetManager::enable_async_loading()).
+struct AsyncLoader<A: Asset> {
+    load_asset: LoadAssetAsync<A>,
+    create_reload_job: fn(&A) -> ReloadJob<A>,
+}
+
+type LoadAssetAsync<A> =
+    fn(&mut AssetManager<A>, &str, &str) -> Result<AssetLoadHandle<A>, AssetManagerError>;
+
+// The asset of a slot is None while the slot is free. Its generation is bumped every time its
+// asset is destroyed, which makes the handles to that asset stale.
+struct AssetSlot<A> {
+    generation: u32,
+    asset_id: String,
+    asset: Option<A>,
+    // Cloned into every handle to the asset. The slot holds the last reference once all the
+    // handles are dropped.
+    ref_count: Arc<()>,
+    unused_since: Option<Instant>,
+    // When the asset was last reloaded or rebuilt, and what went wrong the last time it failed
+    // to be, if it has not succeeded since.
+    last_reload_time: Option<Instant>,
+    last_error: Option<String>,
+}
+
+impl<A> AssetSlot<A> {
+    fn is_unused(&self) -> bool {
+        self.asset.is_some() && Arc::strong_count(&self.ref_count) == 1
+    }
+}
+
 pub struct AssetManager<A: Asset> {
-    assets: HashMap<String, Arc<Mutex<A>>>,
-    callbacks: HashMap<String, Vec<Box<dyn FnMut()>>>,
-    file_path_to_asset_id_map: HashMap<PathBuf, String>,
+    slots: Vec<AssetSlot<A>>,
+    free_slot_indices: Vec<u32>,
+    unload_policy: UnloadPolicy,
+    // String IDs are only a convenience for finding handles. Everything else uses handles.
+    // Weak handles are used internally, so that the manager does not keep its assets alive.
+    asset_id_to_handle_map: HashMap<String, WeakHandle<A>>,
+    callbacks: HashMap<WeakHandle<A>, Vec<ReloadCallback<A>>>,
+    // A file can back more than one asset, e.g. the permutations of a shader.
+    file_path_to_handles_map: HashMap<PathBuf, Vec<WeakHandle<A>>>,
+    // Maps files that assets depend on (e.g. shader includes) to the assets.
+    dependency_path_to_handles_map: HashMap<PathBuf, Vec<WeakHandle<A>>>,
+    // Assets whose source files have been removed. They keep their last loaded version until
+    // their files reappear.
+    missing_assets: HashSet<WeakHandle<A>>,
+    // Background loading. Assets decoded by the worker pool wait in the upload channel until
+    // pump() creates them on the GL thread.
+    worker_pool: Option<WorkerPool>,
+    upload_sender: mpsc::Sender<PendingUpload<A>>,
+    upload_receiver: mpsc::Receiver<PendingUpload<A>>,
+    pending_loads: HashMap<String, Arc<Mutex<AssetLoadState<A>>>>,
+    // Only set for asset types that get loaded and reloaded in the background when their files
+    // change.
+    async_loader: Option<AsyncLoader<A>>,
+    // The IDs of the pending loads of files that appeared in watched directories.
+    new_file_loads: HashSet<String>,
+    // The latest background reload of each asset. Earlier ones that finish after it are
+    // dropped.
+    pending_reloads: HashMap<WeakHandle<A>, u64>,
+    next_reload_id: u64,
+    // Loads of new files that wait for the pending reloads (see pump()).
+    deferred_uploads: VecDeque<PendingUpload<A>>,
+    finished_uploads: Vec<FinishedUpload<A>>,
 }
 
 impl<A: Asset> Drop for AssetManager<A> {
     fn drop(&mut self) {
-        // TODO: Implement drop for asset managers.
+        // Handles that outlive the manager simply become stale. Errors cannot be reported from
+        // here, so the remaining assets are destroyed on a best-effort basis.
+        for slot in self.slots.iter_mut() {
+            if let Some(asset) = slot.asset.as_mut() {
+                let _ = asset.destroy();
+            }
+        }
     }
 }
 
 impl<A: Asset> AssetManager<A> {
     pub fn new() -> Result<Self, AssetManagerError> {
+        let (upload_sender, upload_receiver) = mpsc::channel();
+
         Ok(Self {
-            assets: HashMap::new(),
+            slots: vec![],
+            free_slot_indices: vec![],
+            unload_policy: UnloadPolicy::Immediate,
+            asset_id_to_handle_map: HashMap::new(),
             callbacks: HashMap::new(),
-            file_path_to_asset_id_map: HashMap::new(),
+            file_path_to_handles_map: HashMap::new(),
+            dependency_path_to_handles_map: HashMap::new(),
+            missing_assets: HashSet::new(),
+            worker_pool: None,
+            upload_sender,
+            upload_receiver,
+            pending_loads: HashMap::new(),
+            async_loader: None,
+            new_file_loads: HashSet::new(),
+            pending_reloads: HashMap::new(),
+            next_reload_id: 0,
+            deferred_uploads: VecDeque::new(),
+            finished_uploads: vec![],
         })
     }
 
+    // Loading an asset under an ID that is already in use replaces the previous asset, and
+    // makes the handles to it stale. The asset is unloaded once the returned handle, and every
+    // clone of it, is dropped.
     pub fn load_asset<S: AsRef<str>>(
         &mut self,
         id: S,
         file_path: S,
-    ) -> Result<Arc<Mutex<A>>, AssetManagerError> {
+    ) -> Result<Handle<A>, AssetManagerError> {
         let asset_id = String::from(id.as_ref());
+        let abs_file_path = Self::to_absolute_file_path(file_path.as_ref())?;
 
-        let mut abs_file_path = match env::current_dir() {
-            Ok(path) => path,
-            Err(error) => {
-                return Err(AssetManagerError::new(
-                    "current working directory cannot be used",
-                    AssetManagerErrorKind::CurrentWorkingDirectoryError,
-                    Some(Box::new(error)),
-                ))
-            }
+        match A::new(asset_id.clone(), &abs_file_path) {
+            Ok(asset) => self.insert_asset(asset_id, Some(abs_file_path), asset),
+            Err(error) => Err(AssetManagerError::new(
+                format!("failed to load asset from \"{}\"", file_path.as_ref()),
+                AssetManagerErrorKind::AssetLoadError,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+
+    // Adds an asset that was built in code rather than loaded from a file, e.g. a program
+    // linked from shader assets. Such assets are not reloaded when files change, but they are
+    // rebuilt when the assets they depend on are reloaded (see AssetRegistry).
+    pub fn add_asset<S: AsRef<str>>(
+        &mut self,
+        id: S,
+        asset: A,
+    ) -> Result<Handle<A>, AssetManagerError> {
+        self.insert_asset(String::from(id.as_ref()), None, asset)
+    }
+
+    // Loads a file that appeared in a watched directory, if the asset type supports its file
+    // extension and no asset has been loaded from it yet. Files that assets depend on (e.g.
+    // files included by shaders, which share the extension of combined shader files) are not
+    // assets of their own, so they are skipped too. The asset ID is the path of the file
+    // relative to the current working directory. Asset types that support it are loaded in the
+    // background instead (see enable_async_loading()), in which case None is returned, and
+    // pump() finishes the load.
+    pub fn load_new_asset_file(
+        &mut self,
+        abs_file_path: &Path,
+    ) -> Result<Option<Handle<A>>, AssetManagerError> {
+        let is_supported = match abs_file_path.extension().and_then(OsStr::to_str) {
+            Some(file_ext) => A::supported_file_extensions().contains(&file_ext),
+            None => false,
         };
-        abs_file_path.push(file_path.as_ref());
+        if !is_supported
+            || self.file_path_to_handles_map.contains_key(abs_file_path)
+            || self
+                .dependency_path_to_handles_map
+                .contains_key(abs_file_path)
+        {
+            return Ok(None);
+        }
 
-        match A::new(asset_id.clone(), &abs_file_path) {
-            Ok(asset) => {
-                self.assets
-                    .insert(asset_id.clone(), Arc::new(Mutex::new(asset)));
-                self.file_path_to_asset_id_map
-                    .insert(abs_file_path, asset_id.clone());
+        let asset_id = Self::file_path_to_asset_id(abs_file_path)?;
+        if self.asset_id_to_handle_map.contains_key(&asset_id)
+            || self.pending_loads.contains_key(&asset_id)
+        {
+            return Ok(None);
+        }
 
-                Ok(Arc::clone(self.assets.get(&asset_id.clone()).unwrap()))
-            }
+        if let Some(async_loader) = &self.async_loader {
+            let load_asset = async_loader.load_asset;
+            load_asset(self, &asset_id, &abs_file_path.to_string_lossy())?;
+            self.new_file_loads.insert(asset_id);
+
+            return Ok(None);
+        }
+
+        self.load_asset_file(abs_file_path).map(Some)
+    }
+
+    // Loads an asset that is identified by its file, e.g. a texture referenced by a material.
+    // The asset ID is the path of the file relative to the current working directory.
+    pub fn load_asset_file(
+        &mut self,
+        abs_file_path: &Path,
+    ) -> Result<Handle<A>, AssetManagerError> {
+        let abs_file_path = normalize_path(abs_file_path);
+        let asset_id = Self::file_path_to_asset_id(&abs_file_path)?;
+
+        match A::new(asset_id.clone(), &abs_file_path) {
+            Ok(asset) => self.insert_asset(asset_id, Some(abs_file_path), asset),
             Err(error) => Err(AssetManagerError::new(
-                format!("failed to load asset from \"{}\"", file_path.as_ref()),
+                format!(
+                    "failed to load asset from \"{}\"",
+                    abs_file_path.to_string_lossy()
+                ),
                 AssetManagerErrorKind::AssetLoadError,
                 Some(Box::new(error)),
             )),
         }
     }
 
-    pub fn get_asset<S: AsRef<str>>(&self, id: S) -> Option<Arc<Mutex<A>>> {
-        match self.assets.get(id.as_ref().into()) {
-            Some(asset_ptr) => Some(Arc::clone(asset_ptr)),
+    // Returns a handle to an asset loaded from the file, if there is one.
+    pub fn get_handle_by_file_path(&self, abs_file_path: &Path) -> Option<Handle<A>> {
+        self.file_path_to_handles_map
+            .get(&normalize_path(abs_file_path))?
+            .iter()
+            .find_map(|handle| self.upgrade(*handle))
+    }
+
+    // Shares a worker pool between asset managers. Without one, a pool is created on the first
+    // background load.
+    pub fn set_worker_pool(&mut self, worker_pool: WorkerPool) {
+        self.worker_pool = Some(worker_pool);
+    }
+
+    pub fn set_unload_policy(&mut self, unload_policy: UnloadPolicy) {
+        self.unload_policy = unload_policy;
+    }
+
+    pub fn unload_policy(&self) -> UnloadPolicy {
+        self.unload_policy
+    }
+
+    pub fn is_loading(&self) -> bool {
+        !self.pending_loads.is_empty() || !self.pending_reloads.is_empty()
+    }
+
+    // Creates the assets that finished decoding in the background, uploading them to the GPU,
+    // swaps in the new versions of the assets reloaded in the background, and unloads the
+    // assets that are no longer used according to the unload policy. Uploading stops once the
+    // time budget is used up and leaves the rest for the next call, although at least one asset
+    // is uploaded per call so that loading always progresses. Returns the errors of the loads,
+    // reloads and unloads that failed.
+    pub fn pump(&mut self, time_budget: Duration) -> Vec<AssetManagerError> {
+        self.finished_uploads.clear();
+
+        let start_time = Instant::now();
+        let mut errors: Vec<AssetManagerError> = vec![];
+        let mut has_created_asset = false;
+        while !has_created_asset || start_time.elapsed() < time_budget {
+            let pending_upload = match self.deferred_uploads.front() {
+                Some(_) if self.pending_reloads.is_empty() => self.deferred_uploads.pop_front(),
+                _ => self.upload_receiver.try_recv().ok(),
+            };
+            let pending_upload = match pending_upload {
+                Some(pending_upload) => pending_upload,
+                None => break,
+            };
+
+            // New files wait for the pending reloads, so that a new file that a reloaded shader
+            // now includes is known to be an include rather than a shader of its own.
+            if let PendingUpload::Load { asset_id, .. } = &pending_upload {
+                if self.new_file_loads.contains(asset_id) && !self.pending_reloads.is_empty() {
+                    self.deferred_uploads.push_back(pending_upload);
+                    continue;
+                }
+            }
+
+            let result = match pending_upload {
+                PendingUpload::Load {
+                    asset_id,
+                    abs_file_path,
+                    create_asset,
+                } => self.finish_load(asset_id, abs_file_path, create_asset),
+                PendingUpload::Reload {
+                    reload_id,
+                    update_asset,
+                } => self.finish_reload(reload_id, update_asset),
+            };
+            has_created_asset = true;
+
+            if let Err(error) = result {
+                errors.push(error);
+            }
+        }
+
+        let now = Instant::now();
+        let mut unused_handles: Vec<WeakHandle<A>> = vec![];
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if !slot.is_unused() {
+                slot.unused_since = None;
+                continue;
+            }
+
+            let unused_since = *slot.unused_since.get_or_insert(now);
+            let is_expired = match self.unload_policy {
+                UnloadPolicy::Immediate => true,
+                UnloadPolicy::GracePeriod(grace_period) => now - unused_since >= grace_period,
+                UnloadPolicy::Manual => false,
+            };
+            if is_expired {
+                unused_handles.push(WeakHandle::new(index as u32, slot.generation));
+            }
+        }
+        for handle in unused_handles {
+            if let Err(error) = self.destroy_slot_asset(handle) {
+                errors.push(error);
+            }
+        }
+
+        errors
+    }
+
+    // Takes what the last call to pump() loaded and reloaded in the background, so that the
+    // asset registry can load what the assets refer to, and rebuild the assets that depend on
+    // them.
+    pub(crate) fn take_finished_uploads(&mut self) -> Vec<FinishedUpload<A>> {
+        mem::take(&mut self.finished_uploads)
+    }
+
+    // Destroys every asset that no longer has any handles to it, regardless of the unload
+    // policy. Returns the errors of the assets that could not be destroyed.
+    pub fn collect_garbage(&mut self) -> Vec<AssetManagerError> {
+        let unused_handles: Vec<WeakHandle<A>> = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.is_unused())
+            .map(|(index, slot)| WeakHandle::new(index as u32, slot.generation))
+            .collect();
+
+        let mut errors: Vec<AssetManagerError> = vec![];
+        for handle in unused_handles {
+            if let Err(error) = self.destroy_slot_asset(handle) {
+                errors.push(error);
+            }
+        }
+
+        errors
+    }
+
+    // Returns None if the handle is stale.
+    pub fn get(&self, handle: &Handle<A>) -> Option<&A> {
+        match self.slots.get(handle.index() as usize) {
+            Some(slot) if slot.generation == handle.generation() => slot.asset.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, handle: &Handle<A>) -> Option<&mut A> {
+        match self.slots.get_mut(handle.index() as usize) {
+            Some(slot) if slot.generation == handle.generation() => slot.asset.as_mut(),
+            _ => None,
+        }
+    }
+
+    // Handles to every loaded asset.
+    pub fn handles(&self) -> Vec<Handle<A>> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.asset.is_some())
+            .map(|(index, slot)| {
+                let handle = WeakHandle::new(index as u32, slot.generation);
+                Handle::new(handle, Arc::clone(&slot.ref_count))
+            })
+            .collect()
+    }
+
+    pub fn is_handle_valid(&self, handle: &Handle<A>) -> bool {
+        self.get(handle).is_some()
+    }
+
+    // Returns None once the asset has been destroyed.
+    pub fn upgrade(&self, handle: WeakHandle<A>) -> Option<Handle<A>> {
+        match self.slots.get(handle.index() as usize) {
+            Some(slot) if slot.generation == handle.generation() && slot.asset.is_some() => {
+                Some(Handle::new(handle, Arc::clone(&slot.ref_count)))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn get_handle<S: AsRef<str>>(&self, id: S) -> Option<Handle<A>> {
+        match self.asset_id_to_handle_map.get(id.as_ref()) {
+            Some(handle) => self.upgrade(*handle),
             None => None,
         }
     }
 
-    pub fn reload_asset<S: AsRef<str>>(&mut self, id: S) -> Result<Option<()>, AssetManagerError> {
-        match self.assets.get_mut(id.as_ref().into()) {
-            Some(ptr) => match ptr.lock() {
-                Ok(mut asset) => match asset.reload() {
-                    Ok(_) => {}
-                    Err(error) => {
-                        return Err(AssetManagerError::new(
-                            format!("failed to load asset, \"{}\"", id.as_ref()),
-                            AssetManagerErrorKind::AssetReloadError,
-                            Some(Box::new(error)),
-                        ))
-                    }
-                },
-                Err(_) => {
-                    return Err(AssetManagerError::new(
-                        format!("asset, \"{}\", lock poisoned", id.as_ref()),
-                        AssetManagerErrorKind::AssetLockPoisoned,
-                        None,
-                    ))
-                }
-            },
-            None => return Ok(None),
+    pub fn get_asset_id(&self, handle: &Handle<A>) -> Option<&str> {
+        self.get_weak_asset_id(handle.downgrade())
+    }
+
+    // Assets whose source files no longer exist are marked as missing instead of being
+    // reloaded, so that they keep their last loaded version.
+    pub fn reload_asset(&mut self, handle: &Handle<A>) -> Result<(), AssetManagerError> {
+        let result = self.reload_slot_asset(handle.downgrade());
+        self.record_reload_result(handle.downgrade(), &result);
+
+        result
+    }
+
+    // Starts reloading the asset in the background, if its asset type supports it (see
+    // enable_async_loading()), and returns true. The new version is swapped in by pump() once
+    // it has been decoded, which is when the reload callbacks of the asset are called. Returns
+    // false, without doing anything, if the asset type does not support it.
+    pub fn reload_asset_async(&mut self, handle: &Handle<A>) -> Result<bool, AssetManagerError>
+    where
+        A: 'static,
+    {
+        let create_reload_job = match &self.async_loader {
+            Some(async_loader) => async_loader.create_reload_job,
+            None => return Ok(false),
+        };
+
+        let result = self.start_reload(handle.downgrade(), create_reload_job);
+        if let Err(error) = &result {
+            self.set_last_error(handle.downgrade(), Some(describe_error_chain(error)));
+        }
+
+        result.map(|_| true)
+    }
+
+    // Rebuilds the asset from its asset dependencies, once they have been reloaded.
+    pub fn rebuild_asset(
+        &mut self,
+        handle: &Handle<A>,
+        assets: &AssetRegistry,
+    ) -> Result<(), AssetManagerError> {
+        let result = self.rebuild_slot_asset(handle.downgrade(), assets);
+        self.record_reload_result(handle.downgrade(), &result);
+
+        result
+    }
+
+    // Loads, or looks up, the assets the asset refers to (see Asset::load_asset_dependencies()).
+    pub fn load_asset_dependencies(
+        &mut self,
+        handle: &Handle<A>,
+        assets: &mut AssetRegistry,
+    ) -> Result<(), AssetManagerError> {
+        let (asset_id, asset) = self.get_live_slot_mut(handle.downgrade())?;
+        let result = match asset.load_asset_dependencies(assets) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(AssetManagerError::new(
+                format!(
+                    "failed to load the assets that asset, \"{}\", refers to",
+                    asset_id
+                ),
+                AssetManagerErrorKind::AssetLoadError,
+                Some(Box::new(error)),
+            )),
+        };
+        if let Err(error) = &result {
+            self.set_last_error(handle.downgrade(), Some(describe_error_chain(error)));
         }
 
-        match self.run_asset_reload_callbacks(&String::from(id.as_ref())) {
-            Ok(_) => return Ok(Some(())),
+        result
+    }
+
+    // Returns None if the handle is stale, or if the asset has not been reloaded since it was
+    // loaded.
+    pub fn get_last_reload_time(&self, handle: &Handle<A>) -> Option<Instant> {
+        self.get_live_slot_entry(handle.downgrade())?
+            .last_reload_time
+    }
+
+    // The error, along with its causes, of the last reload, rebuild, or dependency load of the
+    // asset that failed, unless the asset has been reloaded successfully since.
+    pub fn get_last_error(&self, handle: &Handle<A>) -> Option<&str> {
+        self.get_live_slot_entry(handle.downgrade())?
+            .last_error
+            .as_deref()
+    }
+
+    fn reload_slot_asset(&mut self, handle: WeakHandle<A>) -> Result<(), AssetManagerError> {
+        self.check_src_file_exists(handle)?;
+        self.apply_reload(handle, |asset| asset.reload())
+    }
+
+    fn start_reload(
+        &mut self,
+        handle: WeakHandle<A>,
+        create_reload_job: fn(&A) -> ReloadJob<A>,
+    ) -> Result<(), AssetManagerError>
+    where
+        A: 'static,
+    {
+        self.check_src_file_exists(handle)?;
+
+        let (_, asset) = self.get_live_slot_mut(handle)?;
+        let reload_job = create_reload_job(asset);
+        let worker_pool = self.get_worker_pool()?;
+
+        let reload_id = self.next_reload_id;
+        let upload_sender = self.upload_sender.clone();
+        worker_pool.execute(move || {
+            let update_asset = match panic::catch_unwind(AssertUnwindSafe(reload_job)) {
+                Ok(update_asset) => update_asset,
+                Err(_) => Err(String::from("decoder panicked")),
+            };
+
+            // Nothing is waiting for the asset anymore if the manager has been dropped.
+            let _ = upload_sender.send(PendingUpload::Reload {
+                reload_id,
+                update_asset,
+            });
+        })?;
+
+        self.next_reload_id += 1;
+        self.pending_reloads.insert(handle, reload_id);
+
+        Ok(())
+    }
+
+    // Assets whose source files no longer exist are marked as missing.
+    fn check_src_file_exists(&mut self, handle: WeakHandle<A>) -> Result<(), AssetManagerError> {
+        let (asset_id, asset) = self.get_live_slot_mut(handle)?;
+        if asset.get_src_file_path().exists() {
+            return Ok(());
+        }
+
+        let error = AssetManagerError::new(
+            format!(
+                "asset, \"{}\", is missing its source file, {}",
+                asset_id,
+                asset.get_src_file_path().to_string_lossy()
+            ),
+            AssetManagerErrorKind::AssetMissing,
+            None,
+        );
+        self.missing_assets.insert(handle);
+
+        Err(error)
+    }
+
+    // Reloads the asset with the given function, then updates what the manager knows about it,
+    // and calls its reload callbacks.
+    fn apply_reload(
+        &mut self,
+        handle: WeakHandle<A>,
+        reload: impl FnOnce(&mut A) -> Result<(), AssetError>,
+    ) -> Result<(), AssetManagerError> {
+        let (asset_id, asset) = self.get_live_slot_mut(handle)?;
+        let asset_id = String::from(asset_id);
+        let dependency_file_paths: Vec<PathBuf> = match reload(asset) {
+            Ok(_) => asset.get_dependency_file_paths().to_vec(),
             Err(error) => {
                 return Err(AssetManagerError::new(
-                    format!(
-                        "unable to call reload callbacks for asset with id, \"{}\"",
-                        id.as_ref()
-                    ),
+                    format!("failed to load asset, \"{}\"", asset_id),
                     AssetManagerErrorKind::AssetReloadError,
                     Some(Box::new(error)),
                 ))
             }
         };
-    }
-
-    pub fn destroy_asset<S: AsRef<str>>(&mut self, id: S) -> Result<Option<()>, AssetManagerError> {
-        match self.assets.get_mut(id.as_ref().into()) {
-            Some(ptr) => match ptr.lock() {
-                Ok(mut asset) => match asset.destroy() {
-                    Ok(_) => {
-                        self.file_path_to_asset_id_map
-                            .remove(asset.get_src_file_path());
-                    }
-                    Err(error) => {
-                        return Err(AssetManagerError::new(
-                            format!("failed to destroy asset, \"{}\"", id.as_ref()),
-                            AssetManagerErrorKind::AssetReloadError,
-                            Some(Box::new(error)),
-                        ))
-                    }
-                },
-                Err(err) => {
-                    return Err(AssetManagerError::new(
-                        format!("asset, \"{}\", lock poisoned", id.as_ref()),
-                        AssetManagerErrorKind::AssetLockPoisoned,
-                        None,
-                    ))
-                }
-            },
-            None => return Ok(None),
-        };
 
-        let asset_id = String::from(id.as_ref());
-        self.assets.remove(&asset_id);
-        self.callbacks.remove(&asset_id);
+        // Reloading can add or remove dependencies, e.g. when an #include is edited.
+        self.update_asset_dependencies(handle, &dependency_file_paths);
+        self.missing_assets.remove(&handle);
 
-        Ok(Some(()))
+        match self.run_asset_reload_callbacks(handle, &asset_id) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(AssetManagerError::new(
+                format!(
+                    "unable to call reload callbacks for asset with id, \"{}\"",
+                    asset_id
+                ),
+                AssetManagerErrorKind::AssetReloadError,
+                Some(Box::new(error)),
+            )),
+        }
     }
 
-    pub fn is_asset_loaded<S: AsRef<str>>(
+    fn rebuild_slot_asset(
         &mut self,
-        id: S,
-    ) -> Result<Option<bool>, AssetManagerError> {
-        match self.assets.get_mut(id.as_ref().into()) {
-            Some(ptr) => match ptr.lock() {
-                Ok(asset) => Ok(Some(asset.is_loaded())),
-                Err(err) => Err(AssetManagerError::new(
-                    format!("asset, \"{}\", lock poisoned", id.as_ref()),
-                    AssetManagerErrorKind::AssetLockPoisoned,
-                    None,
-                )),
-            },
-            None => Ok(None),
+        handle: WeakHandle<A>,
+        assets: &AssetRegistry,
+    ) -> Result<(), AssetManagerError> {
+        let (asset_id, asset) = self.get_live_slot_mut(handle)?;
+        match asset.rebuild(assets) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(AssetManagerError::new(
+                format!(
+                    "failed to rebuild asset, \"{}\", from its reloaded dependencies",
+                    asset_id
+                ),
+                AssetManagerErrorKind::AssetReloadError,
+                Some(Box::new(error)),
+            )),
         }
     }
 
-    pub fn register_asset_reload_callback<S: AsRef<str>, F: FnMut() + Send + 'static>(
+    // Destroys the asset right away, even if there are other handles to it. Every handle to
+    // the asset becomes stale.
+    pub fn destroy_asset(&mut self, handle: &Handle<A>) -> Result<(), AssetManagerError> {
+        self.destroy_slot_asset(handle.downgrade())
+    }
+
+    // Returns None if the handle is stale.
+    pub fn is_asset_loaded(&self, handle: &Handle<A>) -> Option<bool> {
+        self.get(handle).map(|asset| asset.is_loaded())
+    }
+
+    // Callbacks get the manager so that they can look up the reloaded asset, or other assets
+    // of the same type (e.g. the other shaders of a program).
+    pub fn register_asset_reload_callback<
+        F: FnMut(&AssetManager<A>) -> Result<(), Box<dyn Error + 'static>> + Send + 'static,
+    >(
         &mut self,
-        target_asset_id: S,
+        target_handle: &Handle<A>,
         callback: F,
     ) {
-        match self.callbacks.get_mut(target_asset_id.as_ref().into()) {
+        match self.callbacks.get_mut(&target_handle.downgrade()) {
             Some(callbacks) => callbacks.push(Box::new(callback)),
             None => {
                 self.callbacks
-                    .insert(target_asset_id.as_ref().into(), vec![Box::new(callback)]);
+                    .insert(target_handle.downgrade(), vec![Box::new(callback)]);
             }
         };
     }
 
-    pub fn reload_assets_by_id<S: AsRef<str>>(
-        &mut self,
-        ids: &Vec<S>,
-    ) -> Result<(), AssetManagerError> {
-        for id in ids {
-            match self.reload_asset(id) {
+    // Assets whose asset type supports it are reloaded in the background (see
+    // reload_asset_async()).
+    pub fn reload_assets(&mut self, handles: &[Handle<A>]) -> Result<(), AssetManagerError>
+    where
+        A: 'static,
+    {
+        for handle in handles {
+            let asset_id = match self.get_asset_id(handle) {
+                Some(asset_id) => String::from(asset_id),
+                None => return Err(Self::stale_handle_error(handle.downgrade())),
+            };
+
+            let result = match self.reload_asset_async(handle) {
+                Ok(false) => self.reload_asset(handle),
+                result => result.map(|_| ()),
+            };
+            match result {
                 Ok(_) => {}
                 Err(error) => {
                     return Err(AssetManagerError::new(
-                        format!("failed to reload asset with id, \"{}\"", id.as_ref()),
+                        format!("failed to reload asset with id, \"{}\"", asset_id),
                         AssetManagerErrorKind::AssetReloadError,
                         Some(Box::new(error)),
                     ))
@@ -237,31 +744,554 @@ impl<A: Asset> AssetManager<A> {
         Ok(())
     }
 
-    pub fn file_paths_to_asset_ids(&self, paths: &Vec<PathBuf>) -> Vec<String> {
-        let mut ids = vec![];
+    // Includes the assets that depend on the given paths, e.g. every shader that includes a
+    // modified file.
+    pub fn file_paths_to_handles(&self, paths: &[PathBuf]) -> Vec<Handle<A>> {
+        let mut handles: Vec<WeakHandle<A>> = vec![];
+        for path in paths {
+            let path_handles = self
+                .file_path_to_handles_map
+                .get(path)
+                .into_iter()
+                .chain(self.dependency_path_to_handles_map.get(path))
+                .flatten();
+            for handle in path_handles {
+                if !handles.contains(handle) {
+                    handles.push(*handle);
+                }
+            }
+        }
+
+        handles
+            .into_iter()
+            .filter_map(|handle| self.upgrade(handle))
+            .collect()
+    }
+
+    // Marks the assets loaded from the given (removed) paths as missing, and returns the ones
+    // that were not missing yet. Missing assets are not destroyed, so their GPU objects stay in
+    // use, and they get reloaded as soon as their files are modified or created again.
+    pub fn mark_assets_missing_by_file_paths(&mut self, paths: &[PathBuf]) -> Vec<Handle<A>> {
+        let mut handles: Vec<Handle<A>> = vec![];
         for path in paths {
-            let asset_id = match self.file_path_to_asset_id_map.get(path) {
-                Some(id) => id,
+            if let Some(path_handles) = self.file_path_to_handles_map.get(path) {
+                for handle in path_handles {
+                    if self.missing_assets.insert(*handle) {
+                        handles.extend(self.upgrade(*handle));
+                    }
+                }
+            }
+        }
+
+        handles
+    }
+
+    pub fn is_asset_missing(&self, handle: &Handle<A>) -> bool {
+        self.missing_assets.contains(&handle.downgrade())
+    }
+
+    // Sorted by asset ID.
+    pub fn get_missing_assets(&self) -> Vec<Handle<A>> {
+        let mut handles: Vec<Handle<A>> = self
+            .missing_assets
+            .iter()
+            .filter_map(|handle| self.upgrade(*handle))
+            .collect();
+        handles.sort_by(|handle, other_handle| {
+            self.get_asset_id(handle)
+                .cmp(&self.get_asset_id(other_handle))
+        });
+
+        handles
+    }
+
+    // Marks the assets built from the given paths as stale, and returns them so that they can
+    // be reloaded.
+    pub fn mark_assets_stale_by_file_paths(&mut self, paths: &[PathBuf]) -> Vec<Handle<A>> {
+        let handles = self.file_paths_to_handles(paths);
+        for handle in &handles {
+            if let Some(asset) = self.get_mut(handle) {
+                asset.mark_stale();
+            }
+        }
+
+        handles
+    }
+
+    fn to_absolute_file_path(file_path: &str) -> Result<PathBuf, AssetManagerError> {
+        let mut abs_file_path = match env::current_dir() {
+            Ok(path) => path,
+            Err(error) => {
+                return Err(AssetManagerError::new(
+                    "current working directory cannot be used",
+                    AssetManagerErrorKind::CurrentWorkingDirectoryError,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+        abs_file_path.push(file_path);
+
+        Ok(normalize_path(&abs_file_path))
+    }
+
+    fn file_path_to_asset_id(abs_file_path: &Path) -> Result<String, AssetManagerError> {
+        match env::current_dir() {
+            Ok(current_dir) => match abs_file_path.strip_prefix(current_dir) {
+                Ok(relative_path) => Ok(relative_path.to_string_lossy().replace('\\', "/")),
+                Err(_) => Ok(abs_file_path.to_string_lossy().into_owned()),
+            },
+            Err(error) => Err(AssetManagerError::new(
+                "current working directory cannot be used",
+                AssetManagerErrorKind::CurrentWorkingDirectoryError,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+
+    fn stale_handle_error(handle: WeakHandle<A>) -> AssetManagerError {
+        AssetManagerError::new(
+            format!(
+                "asset handle, {}v{}, is stale, since its asset has been destroyed",
+                handle.index(),
+                handle.generation()
+            ),
+            AssetManagerErrorKind::StaleHandle,
+            None,
+        )
+    }
+
+    fn get_weak_asset_id(&self, handle: WeakHandle<A>) -> Option<&str> {
+        match self.slots.get(handle.index() as usize) {
+            Some(slot) if slot.generation == handle.generation() && slot.asset.is_some() => {
+                Some(slot.asset_id.as_str())
+            }
+            _ => None,
+        }
+    }
+
+    fn get_live_slot_entry(&self, handle: WeakHandle<A>) -> Option<&AssetSlot<A>> {
+        match self.slots.get(handle.index() as usize) {
+            Some(slot) if slot.generation == handle.generation() && slot.asset.is_some() => {
+                Some(slot)
+            }
+            _ => None,
+        }
+    }
+
+    fn get_live_slot_entry_mut(&mut self, handle: WeakHandle<A>) -> Option<&mut AssetSlot<A>> {
+        match self.slots.get_mut(handle.index() as usize) {
+            Some(slot) if slot.generation == handle.generation() && slot.asset.is_some() => {
+                Some(slot)
+            }
+            _ => None,
+        }
+    }
+
+    fn get_live_slot_mut(
+        &mut self,
+        handle: WeakHandle<A>,
+    ) -> Result<(&str, &mut A), AssetManagerError> {
+        match self.slots.get_mut(handle.index() as usize) {
+            Some(AssetSlot {
+                generation,
+                asset_id,
+                asset: Some(asset),
+                ..
+            }) if *generation == handle.generation() => Ok((asset_id.as_str(), asset)),
+            _ => Err(Self::stale_handle_error(handle)),
+        }
+    }
+
+    // Frees the slot of the asset, so every handle to it becomes stale.
+    fn destroy_slot_asset(&mut self, handle: WeakHandle<A>) -> Result<(), AssetManagerError> {
+        let (asset_id, asset) = self.get_live_slot_mut(handle)?;
+        match asset.destroy() {
+            Ok(_) => {}
+            Err(error) => {
+                return Err(AssetManagerError::new(
+                    format!("failed to destroy asset, \"{}\"", asset_id),
+                    AssetManagerErrorKind::AssetDestructionError,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        self.file_path_to_handles_map.retain(|_, handles| {
+            handles.retain(|other_handle| *other_handle != handle);
+            !handles.is_empty()
+        });
+        self.update_asset_dependencies(handle, &[]);
+        self.missing_assets.remove(&handle);
+        self.callbacks.remove(&handle);
+        self.pending_reloads.remove(&handle);
+
+        let slot = &mut self.slots[handle.index() as usize];
+        if self.asset_id_to_handle_map.get(&slot.asset_id) == Some(&handle) {
+            self.asset_id_to_handle_map.remove(&slot.asset_id);
+        }
+        slot.asset = None;
+        slot.asset_id.clear();
+        slot.generation = slot.generation.wrapping_add(1);
+        slot.unused_since = None;
+        self.free_slot_indices.push(handle.index());
+
+        Ok(())
+    }
+
+    fn insert_asset(
+        &mut self,
+        asset_id: String,
+        abs_file_path: Option<PathBuf>,
+        asset: A,
+    ) -> Result<Handle<A>, AssetManagerError> {
+        if let Some(previous_handle) = self.asset_id_to_handle_map.get(&asset_id) {
+            self.destroy_slot_asset(*previous_handle)?;
+        }
+
+        let dependency_file_paths = asset.get_dependency_file_paths().to_vec();
+        // Slots get a new reference count, since handles from before the slot was freed may
+        // still be around.
+        let ref_count = Arc::new(());
+        let handle = match self.free_slot_indices.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.asset_id = asset_id.clone();
+                slot.asset = Some(asset);
+                slot.ref_count = Arc::clone(&ref_count);
+                slot.last_reload_time = None;
+                slot.last_error = None;
+
+                WeakHandle::new(index, slot.generation)
+            }
+            None => {
+                let index = self.slots.len() as u32;
+                self.slots.push(AssetSlot {
+                    generation: 0,
+                    asset_id: asset_id.clone(),
+                    asset: Some(asset),
+                    ref_count: Arc::clone(&ref_count),
+                    unused_since: None,
+                    last_reload_time: None,
+                    last_error: None,
+                });
+
+                WeakHandle::new(index, 0)
+            }
+        };
+
+        self.asset_id_to_handle_map.insert(asset_id, handle);
+        self.update_asset_dependencies(handle, &dependency_file_paths);
+        if let Some(abs_file_path) = abs_file_path {
+            match self.file_path_to_handles_map.get_mut(&abs_file_path) {
+                Some(handles) => handles.push(handle),
                 None => {
-                    continue;
+                    self.file_path_to_handles_map
+                        .insert(abs_file_path, vec![handle]);
                 }
+            }
+        }
+
+        Ok(Handle::new(handle, ref_count))
+    }
+
+    // Creates an asset that was decoded in the background, and resolves its load state.
+    fn finish_load(
+        &mut self,
+        asset_id: String,
+        abs_file_path: PathBuf,
+        create_asset: Result<AssetCreator<A>, String>,
+    ) -> Result<(), AssetManagerError> {
+        let is_new_file = self.new_file_loads.remove(&asset_id);
+
+        // The asset may have been loaded otherwise in the meantime, e.g. a new shader file that
+        // a program now uses as a stage, and a new file may have turned out to be an include.
+        let result = if let Some(handle) = self.get_handle(&asset_id) {
+            Ok(handle)
+        } else if is_new_file
+            && self
+                .dependency_path_to_handles_map
+                .contains_key(&abs_file_path)
+        {
+            self.pending_loads.remove(&asset_id);
+            return Ok(());
+        } else {
+            let created_asset = match create_asset {
+                Ok(create_asset) => create_asset(asset_id.as_str(), &abs_file_path)
+                    .map_err(|error| -> Box<dyn Error> { Box::new(error) }),
+                Err(message) => Err(message.into()),
             };
-            ids.push(asset_id.clone());
+            match created_asset {
+                Ok(asset) => self.insert_asset(asset_id.clone(), Some(abs_file_path), asset),
+                Err(error) => Err(AssetManagerError::new(
+                    format!(
+                        "failed to load asset from \"{}\"",
+                        abs_file_path.to_string_lossy()
+                    ),
+                    AssetManagerErrorKind::AssetLoadError,
+                    Some(error),
+                )),
+            }
+        };
+
+        let new_state = match &result {
+            Ok(handle) => AssetLoadState::Loaded(handle.clone()),
+            Err(error) => AssetLoadState::Failed(describe_error_chain(error)),
+        };
+        if let Some(state) = self.pending_loads.remove(&asset_id) {
+            if let Ok(mut state) = state.lock() {
+                *state = new_state;
+            }
+        }
+
+        let handle = result?;
+        self.finished_uploads.push(match is_new_file {
+            true => FinishedUpload::NewFile(handle),
+            false => FinishedUpload::Loaded(handle),
+        });
+
+        Ok(())
+    }
+
+    // Swaps the new version of an asset, decoded in the background, into it. Reloads that have
+    // been superseded, or whose asset has been destroyed, are dropped.
+    fn finish_reload(
+        &mut self,
+        reload_id: u64,
+        update_asset: Result<AssetUpdater<A>, String>,
+    ) -> Result<(), AssetManagerError> {
+        let handle = match self
+            .pending_reloads
+            .iter()
+            .find(|(_, pending_reload_id)| **pending_reload_id == reload_id)
+        {
+            Some((handle, _)) => *handle,
+            None => return Ok(()),
+        };
+        self.pending_reloads.remove(&handle);
+
+        let result = match update_asset {
+            Ok(update_asset) => self.apply_reload(handle, update_asset),
+            Err(message) => Err(AssetManagerError::new(
+                format!(
+                    "failed to load asset, \"{}\"",
+                    self.get_weak_asset_id(handle).unwrap_or_default()
+                ),
+                AssetManagerErrorKind::AssetReloadError,
+                Some(message.into()),
+            )),
+        };
+        self.record_reload_result(handle, &result);
+        result?;
+
+        if let Some(handle) = self.upgrade(handle) {
+            self.finished_uploads.push(FinishedUpload::Reloaded(handle));
+        }
+
+        Ok(())
+    }
+
+    // Shared by every background load of the manager. A pool is created on the first one, unless
+    // one has been set with set_worker_pool().
+    fn get_worker_pool(&mut self) -> Result<WorkerPool, AssetManagerError> {
+        match &self.worker_pool {
+            Some(worker_pool) => Ok(worker_pool.clone()),
+            None => {
+                let worker_pool = WorkerPool::new(WorkerPool::default_num_workers())?;
+                self.worker_pool = Some(worker_pool.clone());
+                Ok(worker_pool)
+            }
         }
+    }
+
+    fn update_asset_dependencies(
+        &mut self,
+        handle: WeakHandle<A>,
+        dependency_file_paths: &[PathBuf],
+    ) {
+        self.dependency_path_to_handles_map.retain(|_, handles| {
+            handles.retain(|other_handle| *other_handle != handle);
+            !handles.is_empty()
+        });
 
-        ids
+        for path in dependency_file_paths {
+            match self.dependency_path_to_handles_map.get_mut(path) {
+                Some(handles) => handles.push(handle),
+                None => {
+                    self.dependency_path_to_handles_map
+                        .insert(path.clone(), vec![handle]);
+                }
+            }
+        }
     }
 
+    fn record_reload_result(
+        &mut self,
+        handle: WeakHandle<A>,
+        result: &Result<(), AssetManagerError>,
+    ) {
+        match result {
+            Ok(_) => {
+                if let Some(slot) = self.get_live_slot_entry_mut(handle) {
+                    slot.last_reload_time = Some(Instant::now());
+                    slot.last_error = None;
+                }
+            }
+            Err(error) => self.set_last_error(handle, Some(describe_error_chain(error))),
+        }
+    }
+
+    fn set_last_error(&mut self, handle: WeakHandle<A>, error: Option<String>) {
+        if let Some(slot) = self.get_live_slot_entry_mut(handle) {
+            slot.last_error = error;
+        }
+    }
+
+    // The callbacks are taken out of the manager while they run, since they borrow it.
     fn run_asset_reload_callbacks(
         &mut self,
-        asset_id: &String,
-    ) -> Result<Option<()>, AssetManagerError> {
-        if let Some(callbacks) = self.callbacks.get_mut(asset_id.as_str()) {
-            for func in callbacks {
-                func();
+        handle: WeakHandle<A>,
+        asset_id: &str,
+    ) -> Result<(), AssetManagerError> {
+        let mut callbacks = match self.callbacks.remove(&handle) {
+            Some(callbacks) => callbacks,
+            None => return Ok(()),
+        };
+
+        let mut result = Ok(());
+        for func in callbacks.iter_mut() {
+            match func(self) {
+                Ok(_) => {}
+                Err(error) => {
+                    result = Err(AssetManagerError::new(
+                        format!("reload callback for asset, \"{}\", failed", asset_id),
+                        AssetManagerErrorKind::AssetReloadCallbackError,
+                        Some(error),
+                    ));
+                    break;
+                }
             }
         }
+        self.callbacks.insert(handle, callbacks);
 
-        Ok(Some(()))
+        result
     }
 }
+
+impl<A: AsyncAsset + 'static> AssetManager<A> {
+    // Has the files that appear in watched directories (see load_new_asset_file()), and the
+    // assets that change (see reload_assets()), loaded in the background, so that they do not
+    // stall the GL thread.
+    pub fn enable_async_loading(&mut self) {
+        self.async_loader = Some(AsyncLoader {
+            load_asset: |manager, id, file_path| manager.load_asset_async(id, file_path),
+            create_reload_job: create_reload_job::<A>,
+        });
+    }
+
+    // Reads and decodes the asset on the worker pool, and returns a handle to it right away.
+    // The asset is created on the GL thread by a later call to pump(). Loading an asset that is
+    // already loaded or being loaded returns a handle to it instead.
+    pub fn load_asset_async<S: AsRef<str>>(
+        &mut self,
+        id: S,
+        file_path: S,
+    ) -> Result<AssetLoadHandle<A>, AssetManagerError> {
+        let asset_id = String::from(id.as_ref());
+        if let Some(handle) = self.get_handle(&asset_id) {
+            let state = AssetLoadState::Loaded(handle);
+            return Ok(AssetLoadHandle::new(asset_id, Arc::new(Mutex::new(state))));
+        }
+        if let Some(state) = self.pending_loads.get(&asset_id) {
+            return Ok(AssetLoadHandle::new(asset_id, Arc::clone(state)));
+        }
+
+        let abs_file_path = Self::to_absolute_file_path(file_path.as_ref())?;
+        let worker_pool = self.get_worker_pool()?;
+
+        let upload_sender = self.upload_sender.clone();
+        let job_asset_id = asset_id.clone();
+        let job_file_path = abs_file_path.clone();
+        worker_pool.execute(move || {
+            let decoded = panic::catch_unwind(AssertUnwindSafe(|| A::decode_file(&job_file_path)));
+            let create_asset: Result<AssetCreator<A>, String> = match decoded {
+                Ok(Ok(decoded)) => Ok(Box::new(move |id: &str, file_path: &Path| {
+                    A::from_decoded(id, file_path, decoded)
+                })),
+                Ok(Err(error)) => Err(describe_error_chain(&error)),
+                Err(_) => Err(String::from("decoder panicked")),
+            };
+
+            // Nothing is waiting for the asset anymore if the manager has been dropped.
+            let _ = upload_sender.send(PendingUpload::Load {
+                asset_id: job_asset_id,
+                abs_file_path: job_file_path,
+                create_asset,
+            });
+        })?;
+
+        let state = Arc::new(Mutex::new(AssetLoadState::Pending));
+        self.pending_loads
+            .insert(asset_id.clone(), Arc::clone(&state));
+
+        Ok(AssetLoadHandle::new(asset_id, state))
+    }
+}
+
+// Only what the asset needs to be decoded again is taken from it, since decoding runs on a worker
+// thread.
+fn create_reload_job<A: AsyncAsset + 'static>(asset: &A) -> ReloadJob<A> {
+    let reload_decoder = asset.reload_decoder();
+    Box::new(move || match reload_decoder() {
+        Ok(decoded) => {
+            let update_asset: AssetUpdater<A> =
+                Box::new(move |asset: &mut A| asset.reload_decoded(decoded));
+            Ok(update_asset)
+        }
+        Err(error) => Err(describe_error_chain(&error)),
+    })
+}
+
+impl AssetManager<Shader> {
+    // Loads the permutation of the shader in the file with the given defines, or returns it if
+    // it has already been loaded. Each permutation is stored under its own ID, built from the
+    // path of the file as in load_asset_file() (see shader_permutation_id()), and every
+    // permutation of a file is reloaded when the file changes.
+    pub fn load_shader_permutation(
+        &mut self,
+        abs_file_path: &Path,
+        defines: &[ShaderDefine],
+    ) -> Result<Handle<Shader>, AssetManagerError> {
+        let abs_file_path = normalize_path(abs_file_path);
+        let permutation_id =
+            shader_permutation_id(Self::file_path_to_asset_id(&abs_file_path)?, defines);
+        if let Some(handle) = self.get_handle(&permutation_id) {
+            return Ok(handle);
+        }
+
+        match Shader::with_defines(permutation_id.clone(), &abs_file_path, defines) {
+            Ok(shader) => self.insert_asset(permutation_id, Some(abs_file_path), shader),
+            Err(error) => Err(AssetManagerError::new(
+                format!(
+                    "failed to load shader permutation, \"{}\", from \"{}\"",
+                    permutation_id,
+                    abs_file_path.to_string_lossy()
+                ),
+                AssetManagerErrorKind::AssetLoadError,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+}
+
+// Builds the ID a shader permutation is stored under, e.g. "lit[MAX_LIGHTS=8,USE_NORMAL_MAP]".
+// Defines are sorted first, so the order they are given in does not matter. A shader without
+// defines keeps its plain ID.
+pub fn shader_permutation_id<S: AsRef<str>>(id: S, defines: &[ShaderDefine]) -> String {
+    let defines = normalize_shader_defines(defines);
+    if defines.is_empty() {
+        return id.as_ref().into();
+    }
+
+    let define_strings: Vec<String> = defines.iter().map(ShaderDefine::to_string).collect();
+    format!("{}[{}]", id.as_ref(), define_strings.join(","))
+}
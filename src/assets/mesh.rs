@@ -0,0 +1,720 @@
+extern crate gl;
+
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+
+use crate::assets::{Asset, AssetDecoder, AssetError, AssetErrorKind, AsyncAsset};
+use crate::graphics::{
+    Buffer, BufferKind, BufferUsage, ComponentType, VertexArray, VertexAttribute, VertexLayout,
+};
+
+// Attribute locations that shaders drawing meshes must declare with layout(location = ...).
+pub const MESH_POSITION_LOCATION: gl::types::GLuint = 0;
+pub const MESH_NORMAL_LOCATION: gl::types::GLuint = 1;
+pub const MESH_UV_LOCATION: gl::types::GLuint = 2;
+pub const MESH_TANGENT_LOCATION: gl::types::GLuint = 3;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum MeshErrorKind {
+    ParsingFailed,
+    BufferLoadingFailed,
+    MissingAttribute,
+    UnsupportedPrimitive,
+    UploadFailed,
+}
+
+#[derive(Debug)]
+pub struct MeshError {
+    source: Option<Box<dyn Error + 'static>>,
+    message: String,
+    kind: MeshErrorKind,
+}
+
+impl MeshError {
+    pub fn new(
+        message: impl AsRef<str>,
+        kind: MeshErrorKind,
+        source: Option<Box<dyn Error + 'static>>,
+    ) -> MeshError {
+        MeshError {
+            source,
+            message: message.as_ref().into(),
+            kind,
+        }
+    }
+
+    pub fn kind(&self) -> MeshErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for MeshError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct MeshVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    // The w component holds the handedness of the bitangent.
+    pub tangent: [f32; 4],
+}
+
+impl MeshVertex {
+    pub fn vertex_layout() -> VertexLayout {
+        VertexLayout::new(mem::size_of::<MeshVertex>())
+            .attribute(
+                VertexAttribute::new(
+                    "position",
+                    ComponentType::Float,
+                    3,
+                    mem::offset_of!(MeshVertex, position),
+                )
+                .at_location(MESH_POSITION_LOCATION),
+            )
+            .attribute(
+                VertexAttribute::new(
+                    "normal",
+                    ComponentType::Float,
+                    3,
+                    mem::offset_of!(MeshVertex, normal),
+                )
+                .at_location(MESH_NORMAL_LOCATION),
+            )
+            .attribute(
+                VertexAttribute::new(
+                    "uv",
+                    ComponentType::Float,
+                    2,
+                    mem::offset_of!(MeshVertex, uv),
+                )
+                .at_location(MESH_UV_LOCATION),
+            )
+            .attribute(
+                VertexAttribute::new(
+                    "tangent",
+                    ComponentType::Float,
+                    4,
+                    mem::offset_of!(MeshVertex, tangent),
+                )
+                .at_location(MESH_TANGENT_LOCATION),
+            )
+    }
+}
+
+// Geometry of a single primitive, decoded from a mesh file but not yet uploaded to the GPU.
+#[derive(Clone, Debug, Default)]
+pub struct PrimitiveData {
+    pub name: String,
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u32>,
+}
+
+pub struct MeshPrimitive {
+    name: String,
+    vertex_array: VertexArray,
+}
+
+impl MeshPrimitive {
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn vertex_array(&self) -> &VertexArray {
+        &self.vertex_array
+    }
+
+    pub fn draw(&self) {
+        self.vertex_array.draw(gl::TRIANGLES);
+    }
+}
+
+pub struct Mesh {
+    id: String,
+    src_file_path: PathBuf,
+    primitives: Vec<MeshPrimitive>,
+    is_loaded: bool,
+    is_stale: bool,
+}
+
+impl Asset for Mesh {
+    fn new<S: AsRef<str>>(id: S, file_path: &Path) -> Result<Self, AssetError> {
+        let data = Self::decode_file(file_path)?;
+        Self::from_decoded(id, file_path, data)
+    }
+
+    fn supported_file_extensions() -> &'static [&'static str] {
+        &["obj", "gltf", "glb"]
+    }
+
+    // The mesh file is parsed and uploaded before the current primitives are replaced, so a
+    // broken file leaves the current mesh untouched.
+    fn reload(&mut self) -> Result<(), AssetError> {
+        let data = (self.reload_decoder())()?;
+        self.reload_decoded(data)
+    }
+
+    fn destroy(&mut self) -> Result<(), AssetError> {
+        self.primitives.clear();
+
+        self.id.clear();
+        self.src_file_path.clear();
+        self.is_loaded = false;
+
+        Ok(())
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.is_loaded
+    }
+
+    fn is_stale(&self) -> bool {
+        self.is_stale
+    }
+
+    fn mark_stale(&mut self) {
+        self.is_stale = true;
+    }
+
+    fn get_src_file_path(&self) -> &Path {
+        self.src_file_path.as_path()
+    }
+
+    // The vertex and index buffers. The meshes are not kept on the CPU once uploaded.
+    fn get_memory_usage(&self) -> Option<usize> {
+        let memory_usage = self
+            .primitives
+            .iter()
+            .map(|primitive| {
+                let vertex_array = primitive.vertex_array();
+                let vertex_buffers_size: usize = vertex_array
+                    .vertex_buffers()
+                    .iter()
+                    .map(|(buffer, _)| buffer.size_in_bytes())
+                    .sum();
+                let index_buffer_size = vertex_array
+                    .index_buffer()
+                    .map_or(0, |buffer| buffer.size_in_bytes());
+
+                vertex_buffers_size + index_buffer_size
+            })
+            .sum();
+
+        Some(memory_usage)
+    }
+}
+
+impl AsyncAsset for Mesh {
+    type Decoded = Vec<PrimitiveData>;
+
+    fn decode_file(file_path: &Path) -> Result<Vec<PrimitiveData>, AssetError> {
+        match Self::decode(file_path) {
+            Ok(data) => Ok(data),
+            Err(error) => Err(AssetError::new(
+                format!("unable to load mesh from {}", file_path.to_string_lossy()),
+                AssetErrorKind::LoadingFailed,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+
+    fn from_decoded<S: AsRef<str>>(
+        id: S,
+        file_path: &Path,
+        data: Vec<PrimitiveData>,
+    ) -> Result<Self, AssetError> {
+        let primitives = match Self::upload(&data) {
+            Ok(primitives) => primitives,
+            Err(error) => {
+                return Err(AssetError::new(
+                    format!("unable to load mesh from {}", file_path.to_string_lossy()),
+                    AssetErrorKind::LoadingFailed,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        Ok(Self {
+            id: id.as_ref().into(),
+            src_file_path: file_path.to_path_buf(),
+            primitives,
+            is_loaded: true,
+            is_stale: false,
+        })
+    }
+
+    fn reload_decoder(&self) -> AssetDecoder<Vec<PrimitiveData>> {
+        let src_file_path = self.src_file_path.clone();
+        Box::new(move || match Self::decode(src_file_path.as_path()) {
+            Ok(data) => Ok(data),
+            Err(error) => Err(Self::reload_error(src_file_path.as_path(), error)),
+        })
+    }
+
+    fn reload_decoded(&mut self, data: Vec<PrimitiveData>) -> Result<(), AssetError> {
+        if !self.is_loaded {
+            return Err(AssetError::new(
+                format!("asset, '{}', not yet loaded", self.id.as_str()),
+                AssetErrorKind::NotLoaded,
+                None,
+            ));
+        }
+
+        match Self::upload(&data) {
+            Ok(primitives) => {
+                self.primitives = primitives;
+                self.is_stale = false;
+
+                Ok(())
+            }
+            Err(error) => Err(Self::reload_error(self.src_file_path.as_path(), error)),
+        }
+    }
+}
+
+impl Mesh {
+    pub fn primitives(&self) -> &[MeshPrimitive] {
+        self.primitives.as_slice()
+    }
+
+    pub fn draw(&self) {
+        for primitive in &self.primitives {
+            primitive.draw();
+        }
+    }
+
+    // Reads the primitives of a Wavefront OBJ, glTF, or GLB file. This does not touch the GPU.
+    pub fn decode(file_path: &Path) -> Result<Vec<PrimitiveData>, MeshError> {
+        match file_path.extension().and_then(OsStr::to_str) {
+            Some("obj") => Self::decode_obj(file_path),
+            Some("gltf") | Some("glb") => Self::decode_gltf(file_path),
+            _ => Err(MeshError::new(
+                format!(
+                    "mesh file extension of {} is neither \".obj\", \".gltf\", or \".glb\"",
+                    file_path.to_string_lossy()
+                ),
+                MeshErrorKind::ParsingFailed,
+                None,
+            )),
+        }
+    }
+
+    pub fn upload(data: &[PrimitiveData]) -> Result<Vec<MeshPrimitive>, MeshError> {
+        let mut primitives: Vec<MeshPrimitive> = vec![];
+        for primitive_data in data {
+            let vertex_buffer = Buffer::new(
+                BufferKind::Vertex,
+                BufferUsage::Static,
+                &primitive_data.vertices,
+            );
+            let index_buffer = Buffer::new(
+                BufferKind::Index,
+                BufferUsage::Static,
+                &primitive_data.indices,
+            );
+
+            let vertex_array = match VertexArray::with_fixed_locations(
+                vec![(vertex_buffer, MeshVertex::vertex_layout())],
+                Some(index_buffer),
+            ) {
+                Ok(vertex_array) => vertex_array,
+                Err(error) => {
+                    return Err(MeshError::new(
+                        format!(
+                            "unable to create the vertex array of primitive, \"{}\"",
+                            primitive_data.name
+                        ),
+                        MeshErrorKind::UploadFailed,
+                        Some(Box::new(error)),
+                    ))
+                }
+            };
+
+            primitives.push(MeshPrimitive {
+                name: primitive_data.name.clone(),
+                vertex_array,
+            });
+        }
+
+        Ok(primitives)
+    }
+
+    fn reload_error(file_path: &Path, error: MeshError) -> AssetError {
+        AssetError::new(
+            format!(
+                "unable to hot-reload mesh from {}",
+                file_path.to_string_lossy()
+            ),
+            AssetErrorKind::ReloadingFailed,
+            Some(Box::new(error)),
+        )
+    }
+
+    fn decode_obj(file_path: &Path) -> Result<Vec<PrimitiveData>, MeshError> {
+        let (models, _) = match tobj::load_obj(file_path, &tobj::GPU_LOAD_OPTIONS) {
+            Ok(obj) => obj,
+            Err(error) => {
+                return Err(MeshError::new(
+                    format!("unable to parse OBJ file, {}", file_path.to_string_lossy()),
+                    MeshErrorKind::ParsingFailed,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        let mut primitives: Vec<PrimitiveData> = vec![];
+        for model in models {
+            let mesh = model.mesh;
+            let num_vertices = mesh.positions.len() / 3;
+            let mut vertices: Vec<MeshVertex> = vec![MeshVertex::default(); num_vertices];
+            for (index, vertex) in vertices.iter_mut().enumerate() {
+                vertex
+                    .position
+                    .copy_from_slice(&mesh.positions[index * 3..index * 3 + 3]);
+                if mesh.normals.len() >= (index + 1) * 3 {
+                    vertex
+                        .normal
+                        .copy_from_slice(&mesh.normals[index * 3..index * 3 + 3]);
+                }
+                if mesh.texcoords.len() >= (index + 1) * 2 {
+                    vertex
+                        .uv
+                        .copy_from_slice(&mesh.texcoords[index * 2..index * 2 + 2]);
+                }
+            }
+
+            let mut primitive = PrimitiveData {
+                name: model.name,
+                vertices,
+                indices: mesh.indices,
+            };
+            validate_indices(&primitive)?;
+            if mesh.normals.is_empty() {
+                compute_normals(&mut primitive);
+            }
+            compute_tangents(&mut primitive);
+
+            primitives.push(primitive);
+        }
+
+        Ok(primitives)
+    }
+
+    // Primitives are read in the local space of their mesh. Node transforms are not applied.
+    fn decode_gltf(file_path: &Path) -> Result<Vec<PrimitiveData>, MeshError> {
+        let gltf = match gltf::Gltf::open(file_path) {
+            Ok(gltf) => gltf,
+            Err(error) => {
+                return Err(MeshError::new(
+                    format!("unable to parse glTF file, {}", file_path.to_string_lossy()),
+                    MeshErrorKind::ParsingFailed,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        let buffers = load_gltf_buffers(&gltf, file_path)?;
+
+        let mut primitives: Vec<PrimitiveData> = vec![];
+        for mesh in gltf.document.meshes() {
+            let mesh_name = match mesh.name() {
+                Some(name) => String::from(name),
+                None => format!("mesh-{}", mesh.index()),
+            };
+
+            for primitive in mesh.primitives() {
+                let primitive_name = format!("{}/{}", mesh_name, primitive.index());
+                if primitive.mode() != gltf::mesh::Mode::Triangles {
+                    return Err(MeshError::new(
+                        format!(
+                            "primitive, \"{}\", is not made of triangles",
+                            primitive_name
+                        ),
+                        MeshErrorKind::UnsupportedPrimitive,
+                        None,
+                    ));
+                }
+
+                let reader = primitive
+                    .reader(|buffer| buffers.get(buffer.index()).map(|data| data.as_slice()));
+
+                let positions: Vec<[f32; 3]> = match reader.read_positions() {
+                    Some(positions) => positions.collect(),
+                    None => {
+                        return Err(MeshError::new(
+                            format!("primitive, \"{}\", has no positions", primitive_name),
+                            MeshErrorKind::MissingAttribute,
+                            None,
+                        ))
+                    }
+                };
+
+                let mut vertices: Vec<MeshVertex> = positions
+                    .iter()
+                    .map(|position| MeshVertex {
+                        position: *position,
+                        ..MeshVertex::default()
+                    })
+                    .collect();
+
+                let has_normals = match reader.read_normals() {
+                    Some(normals) => {
+                        for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                            vertex.normal = normal;
+                        }
+                        true
+                    }
+                    None => false,
+                };
+
+                if let Some(uvs) = reader.read_tex_coords(0) {
+                    for (vertex, uv) in vertices.iter_mut().zip(uvs.into_f32()) {
+                        vertex.uv = uv;
+                    }
+                }
+
+                let has_tangents = match reader.read_tangents() {
+                    Some(tangents) => {
+                        for (vertex, tangent) in vertices.iter_mut().zip(tangents) {
+                            vertex.tangent = tangent;
+                        }
+                        true
+                    }
+                    None => false,
+                };
+
+                let indices: Vec<u32> = match reader.read_indices() {
+                    Some(indices) => indices.into_u32().collect(),
+                    None => (0..vertices.len() as u32).collect(),
+                };
+
+                let mut primitive_data = PrimitiveData {
+                    name: primitive_name,
+                    vertices,
+                    indices,
+                };
+                validate_indices(&primitive_data)?;
+                if !has_normals {
+                    compute_normals(&mut primitive_data);
+                }
+                if !has_tangents {
+                    compute_tangents(&mut primitive_data);
+                }
+
+                primitives.push(primitive_data);
+            }
+        }
+
+        Ok(primitives)
+    }
+}
+
+fn load_gltf_buffers(gltf: &gltf::Gltf, file_path: &Path) -> Result<Vec<Vec<u8>>, MeshError> {
+    let mut buffers: Vec<Vec<u8>> = vec![];
+    for buffer in gltf.document.buffers() {
+        let data = match buffer.source() {
+            gltf::buffer::Source::Bin => match &gltf.blob {
+                Some(blob) => blob.clone(),
+                None => {
+                    return Err(MeshError::new(
+                        format!(
+                            "glTF file, {}, has no binary chunk",
+                            file_path.to_string_lossy()
+                        ),
+                        MeshErrorKind::BufferLoadingFailed,
+                        None,
+                    ))
+                }
+            },
+            gltf::buffer::Source::Uri(uri) if uri.starts_with("data:") => {
+                let encoded_data = match uri.split_once(";base64,") {
+                    Some((_, encoded_data)) => encoded_data,
+                    None => {
+                        return Err(MeshError::new(
+                            "glTF data URIs must be base64-encoded",
+                            MeshErrorKind::BufferLoadingFailed,
+                            None,
+                        ))
+                    }
+                };
+
+                match base64::engine::general_purpose::STANDARD.decode(encoded_data) {
+                    Ok(data) => data,
+                    Err(error) => {
+                        return Err(MeshError::new(
+                            "unable to decode glTF data URI",
+                            MeshErrorKind::BufferLoadingFailed,
+                            Some(Box::new(error)),
+                        ))
+                    }
+                }
+            }
+            gltf::buffer::Source::Uri(uri) => {
+                let buffer_path = match file_path.parent() {
+                    Some(parent) => parent.join(uri),
+                    None => PathBuf::from(uri),
+                };
+
+                match fs::read(&buffer_path) {
+                    Ok(data) => data,
+                    Err(error) => {
+                        return Err(MeshError::new(
+                            format!(
+                                "unable to read glTF buffer from {}",
+                                buffer_path.to_string_lossy()
+                            ),
+                            MeshErrorKind::BufferLoadingFailed,
+                            Some(Box::new(error)),
+                        ))
+                    }
+                }
+            }
+        };
+
+        buffers.push(data);
+    }
+
+    Ok(buffers)
+}
+
+// Malformed files can refer to vertices that do not exist, which would otherwise be read past
+// the end of the vertices when computing normals and tangents, and when drawing.
+fn validate_indices(primitive: &PrimitiveData) -> Result<(), MeshError> {
+    let num_vertices = primitive.vertices.len();
+    match primitive
+        .indices
+        .iter()
+        .find(|index| **index as usize >= num_vertices)
+    {
+        Some(index) => Err(MeshError::new(
+            format!(
+                "primitive, \"{}\", refers to vertex {}, but only has {} vertices",
+                primitive.name, index, num_vertices
+            ),
+            MeshErrorKind::ParsingFailed,
+            None,
+        )),
+        None => Ok(()),
+    }
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let length = dot(a, a).sqrt();
+    match length > f32::EPSILON {
+        true => [a[0] / length, a[1] / length, a[2] / length],
+        false => a,
+    }
+}
+
+// Computes smooth vertex normals by accumulating the area-weighted normals of the triangles
+// sharing each vertex.
+fn compute_normals(primitive: &mut PrimitiveData) {
+    let mut normals: Vec<[f32; 3]> = vec![[0.0; 3]; primitive.vertices.len()];
+    for triangle in primitive.indices.chunks_exact(3) {
+        let [i0, i1, i2] = [triangle[0], triangle[1], triangle[2]].map(|i| i as usize);
+        let p0 = primitive.vertices[i0].position;
+        let face_normal = cross(
+            sub(primitive.vertices[i1].position, p0),
+            sub(primitive.vertices[i2].position, p0),
+        );
+        for index in [i0, i1, i2] {
+            for axis in 0..3 {
+                normals[index][axis] += face_normal[axis];
+            }
+        }
+    }
+
+    for (vertex, normal) in primitive.vertices.iter_mut().zip(normals) {
+        vertex.normal = normalize(normal);
+    }
+}
+
+// Computes per-vertex tangents from the UVs, following Lengyel's method.
+fn compute_tangents(primitive: &mut PrimitiveData) {
+    let mut tangents: Vec<[f32; 3]> = vec![[0.0; 3]; primitive.vertices.len()];
+    let mut bitangents: Vec<[f32; 3]> = vec![[0.0; 3]; primitive.vertices.len()];
+    for triangle in primitive.indices.chunks_exact(3) {
+        let [i0, i1, i2] = [triangle[0], triangle[1], triangle[2]].map(|i| i as usize);
+        let (v0, v1, v2) = (
+            &primitive.vertices[i0],
+            &primitive.vertices[i1],
+            &primitive.vertices[i2],
+        );
+
+        let edge1 = sub(v1.position, v0.position);
+        let edge2 = sub(v2.position, v0.position);
+        let (du1, dv1) = (v1.uv[0] - v0.uv[0], v1.uv[1] - v0.uv[1]);
+        let (du2, dv2) = (v2.uv[0] - v0.uv[0], v2.uv[1] - v0.uv[1]);
+
+        let determinant = du1 * dv2 - du2 * dv1;
+        if determinant.abs() <= f32::EPSILON {
+            continue;
+        }
+
+        let r = 1.0 / determinant;
+        let tangent = [0, 1, 2].map(|axis| (edge1[axis] * dv2 - edge2[axis] * dv1) * r);
+        let bitangent = [0, 1, 2].map(|axis| (edge2[axis] * du1 - edge1[axis] * du2) * r);
+        for index in [i0, i1, i2] {
+            for axis in 0..3 {
+                tangents[index][axis] += tangent[axis];
+                bitangents[index][axis] += bitangent[axis];
+            }
+        }
+    }
+
+    for (index, vertex) in primitive.vertices.iter_mut().enumerate() {
+        let normal = vertex.normal;
+
+        // Gram-Schmidt orthogonalization against the normal. Vertices without usable UVs get an
+        // arbitrary tangent perpendicular to the normal.
+        let tangent = tangents[index];
+        let projection = dot(normal, tangent);
+        let mut orthogonal = normalize(sub(tangent, normal.map(|n| n * projection)));
+        if dot(orthogonal, orthogonal) <= f32::EPSILON {
+            let axis = match normal[0].abs() < 0.9 {
+                true => [1.0, 0.0, 0.0],
+                false => [0.0, 1.0, 0.0],
+            };
+            orthogonal = normalize(cross(normal, axis));
+        }
+
+        let handedness = match dot(cross(normal, orthogonal), bitangents[index]) < 0.0 {
+            true => -1.0,
+            false => 1.0,
+        };
+
+        vertex.tangent = [orthogonal[0], orthogonal[1], orthogonal[2], handedness];
+    }
+}
@@ -1,7 +1,27 @@
 pub mod assets;
+pub mod commands;
+pub mod dependency;
+pub mod handle;
+pub mod loading;
 pub mod manager;
+pub mod mesh;
+pub mod preprocessor;
+pub mod registry;
+pub mod shader_diagnostic;
+pub mod texture;
 pub mod watcher;
+pub mod worker_pool;
 
 pub use assets::*;
+pub use commands::*;
+pub use dependency::*;
+pub use handle::{Handle, WeakHandle};
+pub use loading::AssetLoadHandle;
 pub use manager::*;
+pub use mesh::*;
+pub use preprocessor::*;
+pub use registry::*;
+pub use shader_diagnostic::*;
+pub use texture::*;
 pub use watcher::*;
+pub use worker_pool::*;
@@ -0,0 +1,384 @@
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::assets::{ShaderError, ShaderErrorKind};
+
+// A preprocessor definition injected into a shader, e.g. "USE_NORMAL_MAP" or "MAX_LIGHTS=8".
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ShaderDefine {
+    name: String,
+    value: Option<String>,
+}
+
+impl ShaderDefine {
+    pub fn new<S: AsRef<str>>(name: S) -> Self {
+        Self {
+            name: name.as_ref().trim().into(),
+            value: None,
+        }
+    }
+
+    pub fn with_value<S: AsRef<str>, V: AsRef<str>>(name: S, value: V) -> Self {
+        Self {
+            name: name.as_ref().trim().into(),
+            value: Some(value.as_ref().trim().into()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+}
+
+// Parses "NAME" and "NAME=VALUE".
+impl From<&str> for ShaderDefine {
+    fn from(define: &str) -> Self {
+        match define.split_once('=') {
+            Some((name, value)) => Self::with_value(name, value),
+            None => Self::new(define),
+        }
+    }
+}
+
+impl fmt::Display for ShaderDefine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}={}", self.name, value),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+// Sorts and de-duplicates a define set, so that the same set always produces the same
+// permutation regardless of the order it was given in. Later definitions of a name win.
+pub fn normalize_shader_defines(defines: &[ShaderDefine]) -> Vec<ShaderDefine> {
+    let mut normalized_defines: Vec<ShaderDefine> = vec![];
+    for define in defines.iter().rev() {
+        if !normalized_defines
+            .iter()
+            .any(|normalized_define| normalized_define.name == define.name)
+        {
+            normalized_defines.push(define.clone());
+        }
+    }
+    normalized_defines.sort();
+
+    normalized_defines
+}
+
+// GLSL source with every #include expanded. The source string number used in its #line
+// directives is an index into `source_files`, with 0 being the file that was preprocessed.
+#[derive(Clone, Debug, Default)]
+pub struct PreprocessedSource {
+    pub source: String,
+    pub source_files: Vec<PathBuf>,
+}
+
+impl PreprocessedSource {
+    // Files pulled in through #include directives.
+    pub fn included_files(&self) -> &[PathBuf] {
+        match self.source_files.len() {
+            0 => &[],
+            _ => &self.source_files[1..],
+        }
+    }
+}
+
+// Removes "." and ".." components without touching the file system, so that paths of included
+// files match the ones reported by the assets watcher.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized_path = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized_path.pop();
+            }
+            component => normalized_path.push(component.as_os_str()),
+        }
+    }
+
+    normalized_path
+}
+
+// Expands the #include "path" directives in the given lines of a shader file. Lines are paired
+// with their line number in the file, so that gaps (e.g. from splitting a combined shader into
+// stages) get #line directives too. Each file is included at most once, which also acts as an
+// include guard, and "#pragma once" lines are dropped. The given defines are injected right
+// after the #version line, since nothing but comments may come before it.
+pub fn preprocess_shader_source(
+    lines: &[(usize, &str)],
+    file_path: &Path,
+    defines: &[ShaderDefine],
+) -> Result<PreprocessedSource, ShaderError> {
+    let mut preprocessed_source = PreprocessedSource {
+        source: String::new(),
+        source_files: vec![normalize_path(file_path)],
+    };
+
+    let has_version_directive = lines.iter().any(|(_, line)| is_version_directive(line));
+    if !has_version_directive && !defines.is_empty() {
+        push_defines(&mut preprocessed_source.source, defines);
+        preprocessed_source.source.push_str("#line 1 0\n");
+    }
+
+    preprocess_lines(lines, 0, file_path, defines, &mut preprocessed_source)?;
+
+    Ok(preprocessed_source)
+}
+
+fn parse_include_directive(line: &str) -> Option<&str> {
+    let directive = line.trim().strip_prefix('#')?.trim_start();
+    let include_path = directive.strip_prefix("include")?.trim();
+
+    include_path
+        .strip_prefix('"')
+        .and_then(|include_path| include_path.strip_suffix('"'))
+        .or_else(|| {
+            include_path
+                .strip_prefix('<')
+                .and_then(|include_path| include_path.strip_suffix('>'))
+        })
+}
+
+fn is_version_directive(line: &str) -> bool {
+    match line.trim().strip_prefix('#') {
+        Some(directive) => directive.trim_start().starts_with("version"),
+        None => false,
+    }
+}
+
+fn push_defines(output: &mut String, defines: &[ShaderDefine]) {
+    for define in defines {
+        match &define.value {
+            Some(value) => output.push_str(format!("#define {} {}\n", define.name, value).as_str()),
+            None => output.push_str(format!("#define {}\n", define.name).as_str()),
+        }
+    }
+}
+
+fn is_pragma_once(line: &str) -> bool {
+    let directive = match line.trim().strip_prefix('#') {
+        Some(directive) => directive.trim_start(),
+        None => return false,
+    };
+
+    match directive.strip_prefix("pragma") {
+        Some(pragma) => pragma.trim() == "once",
+        None => false,
+    }
+}
+
+fn preprocess_lines(
+    lines: &[(usize, &str)],
+    file_index: usize,
+    file_path: &Path,
+    defines: &[ShaderDefine],
+    preprocessed_source: &mut PreprocessedSource,
+) -> Result<(), ShaderError> {
+    let mut expected_line_number: usize = 1;
+    for (line_number, line) in lines {
+        let line_number = *line_number;
+
+        // A #line directive cannot come before the #version directive, so it is emitted right
+        // after the first line instead.
+        let is_first_line = preprocessed_source.source.is_empty();
+        if line_number != expected_line_number && !is_first_line {
+            preprocessed_source
+                .source
+                .push_str(format!("#line {} {}\n", line_number, file_index).as_str());
+        }
+
+        if let Some(include_path) = parse_include_directive(line) {
+            let resolved_path = match file_path.parent() {
+                Some(parent) => normalize_path(&parent.join(include_path)),
+                None => normalize_path(Path::new(include_path)),
+            };
+
+            if preprocessed_source.source_files.contains(&resolved_path) {
+                preprocessed_source.source.push('\n');
+            } else {
+                let contents = match fs::read_to_string(&resolved_path) {
+                    Ok(contents) => contents,
+                    Err(error) => {
+                        return Err(ShaderError::new(
+                            format!(
+                                "unable to include {} from {}, line {}",
+                                resolved_path.to_string_lossy(),
+                                file_path.to_string_lossy(),
+                                line_number
+                            ),
+                            ShaderErrorKind::IncludeError,
+                            Some(Box::new(error)),
+                        ))
+                    }
+                };
+
+                let include_index = preprocessed_source.source_files.len();
+                preprocessed_source.source_files.push(resolved_path.clone());
+
+                let include_lines: Vec<(usize, &str)> = contents
+                    .lines()
+                    .enumerate()
+                    .map(|(index, line)| (index + 1, line))
+                    .collect();
+                preprocessed_source
+                    .source
+                    .push_str(format!("#line 1 {}\n", include_index).as_str());
+                preprocess_lines(
+                    &include_lines,
+                    include_index,
+                    &resolved_path,
+                    &[],
+                    preprocessed_source,
+                )?;
+                preprocessed_source
+                    .source
+                    .push_str(format!("#line {} {}\n", line_number + 1, file_index).as_str());
+            }
+        } else if is_pragma_once(line) {
+            preprocessed_source.source.push('\n');
+        } else {
+            preprocessed_source.source.push_str(line);
+            preprocessed_source.source.push('\n');
+
+            let is_injection_point =
+                file_index == 0 && !defines.is_empty() && is_version_directive(line);
+            if is_injection_point {
+                push_defines(&mut preprocessed_source.source, defines);
+            }
+
+            if is_injection_point || (is_first_line && line_number != 1) {
+                preprocessed_source
+                    .source
+                    .push_str(format!("#line {} {}\n", line_number + 1, file_index).as_str());
+            }
+        }
+
+        expected_line_number = line_number + 1;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn number_lines(source: &str) -> Vec<(usize, &str)> {
+        source
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line))
+            .collect()
+    }
+
+    fn preprocess(lines: &[(usize, &str)], defines: &[ShaderDefine]) -> String {
+        preprocess_shader_source(lines, Path::new("shaders/test.frag"), defines)
+            .unwrap()
+            .source
+    }
+
+    #[test]
+    fn leaves_sources_without_directives_as_is() {
+        let source = "#version 330 core\nout vec4 color;\nvoid main() {}\n";
+        assert_eq!(preprocess(&number_lines(source), &[]), source);
+    }
+
+    #[test]
+    fn injects_defines_after_version_directive() {
+        let source = "#version 330 core\nout vec4 color;\n";
+        let defines = [
+            ShaderDefine::new("USE_FOG"),
+            ShaderDefine::from("MAX_LIGHTS=8"),
+        ];
+        assert_eq!(
+            preprocess(&number_lines(source), &defines),
+            "#version 330 core\n#define USE_FOG\n#define MAX_LIGHTS 8\n#line 2 0\nout vec4 color;\n"
+        );
+    }
+
+    #[test]
+    fn injects_defines_at_start_without_version_directive() {
+        let source = "void main() {}\n";
+        assert_eq!(
+            preprocess(&number_lines(source), &[ShaderDefine::new("USE_FOG")]),
+            "#define USE_FOG\n#line 1 0\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn marks_gaps_between_lines() {
+        let lines = [(1, "#version 330 core"), (5, "void main() {}")];
+        assert_eq!(
+            preprocess(&lines, &[]),
+            "#version 330 core\n#line 5 0\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn marks_lines_after_a_first_line_not_at_the_start() {
+        let lines = [(3, "#version 330 core"), (4, "void main() {}")];
+        assert_eq!(
+            preprocess(&lines, &[]),
+            "#version 330 core\n#line 4 0\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn expands_includes_once_with_their_source_string_numbers() {
+        let directory_path = env::temp_dir().join("mulay-gfx-preprocessor-test");
+        fs::create_dir_all(&directory_path).unwrap();
+        fs::write(
+            directory_path.join("common.glsl"),
+            "#pragma once\nfloat f() { return 1.0; }\n",
+        )
+        .unwrap();
+
+        let source = "#version 330 core\n\
+                      #include \"common.glsl\"\n\
+                      #include \"./common.glsl\"\n\
+                      void main() {}\n";
+        let file_path = directory_path.join("main.frag");
+        let preprocessed_source = preprocess_shader_source(&number_lines(source), &file_path, &[]);
+        fs::remove_dir_all(&directory_path).unwrap();
+
+        let preprocessed_source = preprocessed_source.unwrap();
+        assert_eq!(
+            preprocessed_source.source,
+            "#version 330 core\n\
+             #line 1 1\n\
+             \n\
+             float f() { return 1.0; }\n\
+             #line 3 0\n\
+             \n\
+             void main() {}\n"
+        );
+        assert_eq!(
+            preprocessed_source.included_files(),
+            &[directory_path.join("common.glsl")]
+        );
+    }
+
+    #[test]
+    fn fails_on_missing_includes() {
+        let lines = [(1, "#include <missing.glsl>")];
+        let error =
+            preprocess_shader_source(&lines, Path::new("shaders/test.frag"), &[]).unwrap_err();
+        assert_eq!(error.kind(), ShaderErrorKind::IncludeError);
+    }
+
+    #[test]
+    fn normalizes_paths_without_the_file_system() {
+        assert_eq!(
+            normalize_path(Path::new("shaders/./lib/../common.glsl")),
+            PathBuf::from("shaders/common.glsl")
+        );
+    }
+}
@@ -0,0 +1,676 @@
+use std::any::{self, Any, TypeId};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::assets::loading::FinishedUpload;
+use crate::assets::{
+    Asset, AssetKey, AssetManager, AssetManagerError, AssetManagerErrorKind, AssetThumbnail,
+    DependencyGraph, Handle, UnloadPolicy,
+};
+
+// The ID and key of an asset loaded from a new file, along with its (boxed) handle.
+type NewAsset = (String, AssetKey, Box<dyn Any>);
+
+// What an asset manager finished in the background (see AssetManager::pump()).
+#[derive(Default)]
+struct FinishedUploads {
+    loaded_keys: Vec<AssetKey>,
+    new_assets: Vec<NewAsset>,
+    reloaded_keys: Vec<AssetKey>,
+}
+
+// The parts of an asset manager that the registry uses without knowing its asset type.
+trait ErasedAssetManager {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn pump(&mut self, time_budget: Duration) -> Vec<AssetManagerError>;
+    fn take_finished_uploads(&mut self) -> FinishedUploads;
+    fn set_unload_policy(&mut self, unload_policy: UnloadPolicy);
+    fn collect_garbage(&mut self) -> Vec<AssetManagerError>;
+    fn load_new_asset_file(
+        &mut self,
+        abs_file_path: &Path,
+    ) -> Result<Option<NewAsset>, AssetManagerError>;
+    fn mark_assets_missing_by_file_paths(&mut self, paths: &[PathBuf]) -> Vec<String>;
+    // Only the keys of the assets that have been reloaded right away are returned. The ones
+    // reloaded in the background are finished by pump().
+    fn reload_file_paths(&mut self, paths: &[PathBuf]) -> (Vec<AssetKey>, Vec<AssetManagerError>);
+    fn rebuild_asset(
+        &mut self,
+        key: AssetKey,
+        assets: &AssetRegistry,
+    ) -> Result<(), AssetManagerError>;
+    fn load_asset_dependencies(
+        &mut self,
+        key: AssetKey,
+        assets: &mut AssetRegistry,
+    ) -> Result<(), AssetManagerError>;
+    fn asset_infos(&self) -> Vec<AssetInfo>;
+    // Returns false if the asset is being reloaded in the background.
+    fn reload_asset(&mut self, key: AssetKey) -> Result<bool, AssetManagerError>;
+    fn destroy_asset(&mut self, key: AssetKey) -> Result<(), AssetManagerError>;
+    // Returns None if the asset no longer exists.
+    fn get_asset_dependencies(&self, key: AssetKey) -> Option<Vec<AssetKey>>;
+}
+
+impl<A: Asset + 'static> ErasedAssetManager for AssetManager<A> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn pump(&mut self, time_budget: Duration) -> Vec<AssetManagerError> {
+        AssetManager::pump(self, time_budget)
+    }
+
+    fn take_finished_uploads(&mut self) -> FinishedUploads {
+        let mut finished_uploads = FinishedUploads::default();
+        for finished_upload in AssetManager::take_finished_uploads(self) {
+            match finished_upload {
+                FinishedUpload::Loaded(handle) => {
+                    finished_uploads.loaded_keys.push(AssetKey::of(&handle))
+                }
+                FinishedUpload::NewFile(handle) => {
+                    let asset_id = String::from(self.get_asset_id(&handle).unwrap_or_default());
+                    finished_uploads.new_assets.push((
+                        asset_id,
+                        AssetKey::of(&handle),
+                        Box::new(handle),
+                    ));
+                }
+                FinishedUpload::Reloaded(handle) => {
+                    finished_uploads.reloaded_keys.push(AssetKey::of(&handle))
+                }
+            }
+        }
+
+        finished_uploads
+    }
+
+    fn set_unload_policy(&mut self, unload_policy: UnloadPolicy) {
+        AssetManager::set_unload_policy(self, unload_policy)
+    }
+
+    fn collect_garbage(&mut self) -> Vec<AssetManagerError> {
+        AssetManager::collect_garbage(self)
+    }
+
+    fn load_new_asset_file(
+        &mut self,
+        abs_file_path: &Path,
+    ) -> Result<Option<NewAsset>, AssetManagerError> {
+        match AssetManager::load_new_asset_file(self, abs_file_path)? {
+            Some(handle) => {
+                let asset_id = String::from(self.get_asset_id(&handle).unwrap_or_default());
+                Ok(Some((asset_id, AssetKey::of(&handle), Box::new(handle))))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn mark_assets_missing_by_file_paths(&mut self, paths: &[PathBuf]) -> Vec<String> {
+        AssetManager::mark_assets_missing_by_file_paths(self, paths)
+            .iter()
+            .filter_map(|handle| self.get_asset_id(handle).map(String::from))
+            .collect()
+    }
+
+    fn reload_file_paths(&mut self, paths: &[PathBuf]) -> (Vec<AssetKey>, Vec<AssetManagerError>) {
+        let mut reloaded_keys: Vec<AssetKey> = vec![];
+        let mut errors: Vec<AssetManagerError> = vec![];
+        for handle in self.mark_assets_stale_by_file_paths(paths) {
+            match reload_asset(self, &handle) {
+                Ok(true) => reloaded_keys.push(AssetKey::of(&handle)),
+                Ok(false) => {}
+                Err(error) => errors.push(error),
+            }
+        }
+
+        (reloaded_keys, errors)
+    }
+
+    fn rebuild_asset(
+        &mut self,
+        key: AssetKey,
+        assets: &AssetRegistry,
+    ) -> Result<(), AssetManagerError> {
+        match key.downcast::<A>().and_then(|handle| self.upgrade(handle)) {
+            Some(handle) => AssetManager::rebuild_asset(self, &handle, assets),
+            // The asset was destroyed in the meantime, so there is nothing to rebuild.
+            None => Ok(()),
+        }
+    }
+
+    fn load_asset_dependencies(
+        &mut self,
+        key: AssetKey,
+        assets: &mut AssetRegistry,
+    ) -> Result<(), AssetManagerError> {
+        match key.downcast::<A>().and_then(|handle| self.upgrade(handle)) {
+            Some(handle) => AssetManager::load_asset_dependencies(self, &handle, assets),
+            None => Ok(()),
+        }
+    }
+
+    fn asset_infos(&self) -> Vec<AssetInfo> {
+        self.handles()
+            .iter()
+            .filter_map(|handle| {
+                let asset = self.get(handle)?;
+                Some(AssetInfo {
+                    key: AssetKey::of(handle),
+                    id: String::from(self.get_asset_id(handle)?),
+                    src_file_path: asset.get_src_file_path().to_path_buf(),
+                    is_loaded: asset.is_loaded(),
+                    is_stale: asset.is_stale(),
+                    is_missing: self.is_asset_missing(handle),
+                    last_reload_time: self.get_last_reload_time(handle),
+                    last_error: self.get_last_error(handle).map(String::from),
+                    memory_usage: asset.get_memory_usage(),
+                    thumbnail: asset.get_thumbnail(),
+                })
+            })
+            .collect()
+    }
+
+    fn reload_asset(&mut self, key: AssetKey) -> Result<bool, AssetManagerError> {
+        match key.downcast::<A>().and_then(|handle| self.upgrade(handle)) {
+            Some(handle) => reload_asset(self, &handle),
+            None => Err(AssetManagerError::new(
+                format!("asset of type, {}, no longer exists", key.short_type_name()),
+                AssetManagerErrorKind::StaleHandle,
+                None,
+            )),
+        }
+    }
+
+    fn destroy_asset(&mut self, key: AssetKey) -> Result<(), AssetManagerError> {
+        match key.downcast::<A>().and_then(|handle| self.upgrade(handle)) {
+            Some(handle) => AssetManager::destroy_asset(self, &handle),
+            None => Err(AssetManagerError::new(
+                format!("asset of type, {}, no longer exists", key.short_type_name()),
+                AssetManagerErrorKind::StaleHandle,
+                None,
+            )),
+        }
+    }
+
+    fn get_asset_dependencies(&self, key: AssetKey) -> Option<Vec<AssetKey>> {
+        let handle = self.upgrade(key.downcast::<A>()?)?;
+        self.get(&handle)
+            .map(|asset| asset.get_asset_dependencies())
+    }
+}
+
+// Reloads the asset in the background if its manager supports it (see
+// AssetManager::reload_asset_async()), or right away otherwise. Returns whether the asset has
+// been reloaded already.
+fn reload_asset<A: Asset + 'static>(
+    manager: &mut AssetManager<A>,
+    handle: &Handle<A>,
+) -> Result<bool, AssetManagerError> {
+    if manager.reload_asset_async(handle)? {
+        return Ok(false);
+    }
+
+    manager.reload_asset(handle).map(|_| true)
+}
+
+// A summary of an asset, e.g. for listing the loaded assets.
+#[derive(Clone, Debug)]
+pub struct AssetInfo {
+    key: AssetKey,
+    id: String,
+    src_file_path: PathBuf,
+    is_loaded: bool,
+    is_stale: bool,
+    is_missing: bool,
+    last_reload_time: Option<Instant>,
+    last_error: Option<String>,
+    memory_usage: Option<usize>,
+    thumbnail: Option<AssetThumbnail>,
+}
+
+impl AssetInfo {
+    pub fn key(&self) -> AssetKey {
+        self.key
+    }
+
+    pub fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    // Empty for assets that were not loaded from a file.
+    pub fn src_file_path(&self) -> &Path {
+        self.src_file_path.as_path()
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.is_loaded
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.is_stale
+    }
+
+    pub fn is_missing(&self) -> bool {
+        self.is_missing
+    }
+
+    // None if the asset has not been reloaded since it was loaded.
+    pub fn last_reload_time(&self) -> Option<Instant> {
+        self.last_reload_time
+    }
+
+    // Set if the last reload, or rebuild, of the asset failed (see AssetManager::get_last_error()).
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    // See Asset::get_memory_usage().
+    pub fn memory_usage(&self) -> Option<usize> {
+        self.memory_usage
+    }
+
+    pub fn thumbnail(&self) -> Option<&AssetThumbnail> {
+        self.thumbnail.as_ref()
+    }
+}
+
+// Holds the asset managers of every asset type, and reloads assets along with the assets built
+// from them. When a shader is reloaded, for example, the programs it is linked into are
+// rebuilt right after, each asset only after all of the assets it depends on.
+pub struct AssetRegistry {
+    managers: HashMap<TypeId, Box<dyn ErasedAssetManager>>,
+    dependency_graph: DependencyGraph,
+    // Handles to the assets loaded from files that appeared in watched directories, which
+    // nothing else refers to yet. The registry keeps them loaded until another asset depends on
+    // them and holds its own handle, after which they are unloaded like any other asset.
+    new_asset_handles: Vec<(AssetKey, Box<dyn Any>)>,
+}
+
+impl AssetRegistry {
+    pub fn new() -> Self {
+        Self {
+            managers: HashMap::new(),
+            dependency_graph: DependencyGraph::new(),
+            new_asset_handles: vec![],
+        }
+    }
+
+    // Replaces the manager previously registered for the asset type, if any. The dependencies
+    // of the assets already in the manager are tracked from then on.
+    pub fn register<A: Asset + 'static>(&mut self, manager: AssetManager<A>) {
+        self.managers.insert(TypeId::of::<A>(), Box::new(manager));
+
+        let manager = match self.manager::<A>() {
+            Some(manager) => manager,
+            None => return,
+        };
+        let asset_dependencies: Vec<(AssetKey, Vec<AssetKey>)> = manager
+            .handles()
+            .iter()
+            .filter_map(|handle| {
+                let asset = manager.get(handle)?;
+                Some((AssetKey::of(handle), asset.get_asset_dependencies()))
+            })
+            .collect();
+        for (key, dependencies) in asset_dependencies {
+            self.dependency_graph.set_dependencies(key, &dependencies);
+        }
+    }
+
+    pub fn manager<A: Asset + 'static>(&self) -> Option<&AssetManager<A>> {
+        self.managers
+            .get(&TypeId::of::<A>())
+            .and_then(|manager| manager.as_any().downcast_ref())
+    }
+
+    pub fn manager_mut<A: Asset + 'static>(&mut self) -> Option<&mut AssetManager<A>> {
+        self.managers
+            .get_mut(&TypeId::of::<A>())
+            .and_then(|manager| manager.as_any_mut().downcast_mut())
+    }
+
+    pub fn get<A: Asset + 'static>(&self, handle: &Handle<A>) -> Option<&A> {
+        self.manager::<A>()?.get(handle)
+    }
+
+    pub fn dependency_graph(&self) -> &DependencyGraph {
+        &self.dependency_graph
+    }
+
+    // Loads an asset (see AssetManager::load_asset()), along with the assets it refers to.
+    pub fn load_asset<A: Asset + 'static, S: AsRef<str>>(
+        &mut self,
+        id: S,
+        file_path: S,
+    ) -> Result<Handle<A>, AssetManagerError> {
+        let handle = match self.manager_mut::<A>() {
+            Some(manager) => manager.load_asset(id, file_path)?,
+            None => return Err(Self::unregistered_asset_type_error::<A>()),
+        };
+        self.load_asset_dependencies(AssetKey::of(&handle))?;
+
+        Ok(handle)
+    }
+
+    // Returns a handle to the asset loaded from the file, loading the asset, along with the
+    // assets it refers to, if there is none yet. See AssetManager::load_asset_file().
+    pub fn get_or_load_asset_file<A: Asset + 'static>(
+        &mut self,
+        abs_file_path: &Path,
+    ) -> Result<Handle<A>, AssetManagerError> {
+        let manager = match self.manager_mut::<A>() {
+            Some(manager) => manager,
+            None => return Err(Self::unregistered_asset_type_error::<A>()),
+        };
+        if let Some(handle) = manager.get_handle_by_file_path(abs_file_path) {
+            return Ok(handle);
+        }
+
+        let handle = manager.load_asset_file(abs_file_path)?;
+        self.load_asset_dependencies(AssetKey::of(&handle))?;
+
+        Ok(handle)
+    }
+
+    // Adds an asset built in code (see AssetManager::add_asset()) and starts tracking its
+    // dependencies.
+    pub fn add_asset<A: Asset + 'static, S: AsRef<str>>(
+        &mut self,
+        id: S,
+        asset: A,
+    ) -> Result<Handle<A>, AssetManagerError> {
+        let handle = match self.manager_mut::<A>() {
+            Some(manager) => manager.add_asset(id, asset)?,
+            None => return Err(Self::unregistered_asset_type_error::<A>()),
+        };
+        self.update_asset_dependencies(AssetKey::of(&handle));
+
+        Ok(handle)
+    }
+
+    // Sets the unload policy of every asset manager (see AssetManager::set_unload_policy()).
+    pub fn set_unload_policy(&mut self, unload_policy: UnloadPolicy) {
+        for manager in self.managers.values_mut() {
+            manager.set_unload_policy(unload_policy);
+        }
+    }
+
+    // Pumps every asset manager (see AssetManager::pump()), and forgets the dependencies of
+    // the assets that got unloaded. The assets that finished loading in the background get
+    // the assets they refer to loaded, and the ones that finished reloading get the assets
+    // built from them rebuilt, as if they had been loaded and reloaded through the registry.
+    // Returns the IDs of the assets loaded from new files (see load_new_asset_files()).
+    pub fn pump(&mut self, time_budget: Duration) -> (Vec<String>, Vec<AssetManagerError>) {
+        self.new_asset_handles
+            .retain(|(key, _)| self.dependency_graph.get_dependents(*key).is_empty());
+
+        let mut errors: Vec<AssetManagerError> = vec![];
+        let mut loaded_keys: Vec<AssetKey> = vec![];
+        let mut new_assets: Vec<NewAsset> = vec![];
+        let mut reloaded_keys: Vec<AssetKey> = vec![];
+        for manager in self.managers.values_mut() {
+            errors.extend(manager.pump(time_budget));
+
+            let finished_uploads = manager.take_finished_uploads();
+            loaded_keys.extend(finished_uploads.loaded_keys);
+            new_assets.extend(finished_uploads.new_assets);
+            reloaded_keys.extend(finished_uploads.reloaded_keys);
+        }
+
+        let mut asset_ids: Vec<String> = vec![];
+        for (asset_id, key, handle) in new_assets {
+            asset_ids.push(asset_id);
+            loaded_keys.push(key);
+            self.new_asset_handles.push((key, handle));
+        }
+        for key in loaded_keys {
+            if let Err(error) = self.load_asset_dependencies(key) {
+                errors.push(error);
+            }
+        }
+        let errors = self.rebuild_reloaded_assets(&reloaded_keys, errors);
+        self.remove_unloaded_asset_dependencies();
+
+        (asset_ids, errors)
+    }
+
+    // Destroys every unused asset, regardless of the unload policy (see
+    // AssetManager::collect_garbage()), and forgets their dependencies.
+    pub fn collect_garbage(&mut self) -> Vec<AssetManagerError> {
+        let mut errors: Vec<AssetManagerError> = vec![];
+        for manager in self.managers.values_mut() {
+            errors.extend(manager.collect_garbage());
+        }
+        self.remove_unloaded_asset_dependencies();
+
+        errors
+    }
+
+    // Sorted by asset type, then by asset ID.
+    pub fn asset_infos(&self) -> Vec<AssetInfo> {
+        let mut asset_infos: Vec<AssetInfo> = self
+            .managers
+            .values()
+            .flat_map(|manager| manager.asset_infos())
+            .collect();
+        asset_infos.sort_by(|info, other_info| {
+            (info.key.short_type_name(), info.id.as_str())
+                .cmp(&(other_info.key.short_type_name(), other_info.id.as_str()))
+        });
+
+        asset_infos
+    }
+
+    // Returns the assets with the given ID. Each asset type has its own IDs, so there can be
+    // more than one.
+    pub fn find_assets<S: AsRef<str>>(&self, id: S) -> Vec<AssetKey> {
+        self.managers
+            .values()
+            .flat_map(|manager| manager.asset_infos())
+            .filter(|info| info.id == id.as_ref())
+            .map(|info| info.key)
+            .collect()
+    }
+
+    // Reloads the given assets from their files, then rebuilds the assets that depend on them
+    // (see reload_file_paths()). Assets that get reloaded in the background are finished by
+    // pump().
+    pub fn reload_assets(&mut self, keys: &[AssetKey]) -> Vec<AssetManagerError> {
+        let mut reloaded_keys: Vec<AssetKey> = vec![];
+        let mut errors: Vec<AssetManagerError> = vec![];
+        for key in keys {
+            let result = match self.managers.get_mut(&key.type_id()) {
+                Some(manager) => manager.reload_asset(*key),
+                None => continue,
+            };
+            match result {
+                Ok(true) => reloaded_keys.push(*key),
+                Ok(false) => {}
+                Err(error) => errors.push(error),
+            }
+        }
+
+        self.rebuild_reloaded_assets(&reloaded_keys, errors)
+    }
+
+    // Destroys the asset right away, even if there are handles to it, which become stale. The
+    // assets built from it are left as is.
+    pub fn destroy_asset(&mut self, key: AssetKey) -> Result<(), AssetManagerError> {
+        let result = match self.managers.get_mut(&key.type_id()) {
+            Some(manager) => manager.destroy_asset(key),
+            None => {
+                return Err(AssetManagerError::new(
+                    format!(
+                        "no asset manager is registered for assets of type, {}",
+                        key.short_type_name()
+                    ),
+                    AssetManagerErrorKind::UnregisteredAssetType,
+                    None,
+                ))
+            }
+        };
+        self.update_asset_dependencies(key);
+
+        result
+    }
+
+    // Loads the files that appeared in watched directories with the managers that support
+    // them, and returns the IDs of the new assets. The IDs of the assets loaded in the
+    // background are returned by pump() instead.
+    pub fn load_new_asset_files(
+        &mut self,
+        paths: &[PathBuf],
+    ) -> (Vec<String>, Vec<AssetManagerError>) {
+        let mut asset_ids: Vec<String> = vec![];
+        let mut new_keys: Vec<AssetKey> = vec![];
+        let mut errors: Vec<AssetManagerError> = vec![];
+        for path in paths {
+            for manager in self.managers.values_mut() {
+                match manager.load_new_asset_file(path) {
+                    Ok(Some((asset_id, key, handle))) => {
+                        asset_ids.push(asset_id);
+                        new_keys.push(key);
+                        self.new_asset_handles.push((key, handle));
+                    }
+                    Ok(None) => {}
+                    Err(error) => errors.push(error),
+                }
+            }
+        }
+
+        for key in new_keys {
+            if let Err(error) = self.load_asset_dependencies(key) {
+                errors.push(error);
+            }
+        }
+
+        (asset_ids, errors)
+    }
+
+    // Returns the IDs of the assets that were newly marked as missing.
+    pub fn mark_assets_missing_by_file_paths(&mut self, paths: &[PathBuf]) -> Vec<String> {
+        let mut asset_ids: Vec<String> = vec![];
+        for manager in self.managers.values_mut() {
+            asset_ids.extend(manager.mark_assets_missing_by_file_paths(paths));
+        }
+
+        asset_ids
+    }
+
+    // Reloads the assets built from the given files, then rebuilds the assets that depend on
+    // them in topological order. Assets that fail to reload keep their last loaded version,
+    // and their dependents are left as is. Assets that get reloaded in the background are
+    // finished by pump().
+    pub fn reload_file_paths(&mut self, paths: &[PathBuf]) -> Vec<AssetManagerError> {
+        let mut reloaded_keys: Vec<AssetKey> = vec![];
+        let mut errors: Vec<AssetManagerError> = vec![];
+        for manager in self.managers.values_mut() {
+            let (manager_reloaded_keys, manager_errors) = manager.reload_file_paths(paths);
+            reloaded_keys.extend(manager_reloaded_keys);
+            errors.extend(manager_errors);
+        }
+
+        self.rebuild_reloaded_assets(&reloaded_keys, errors)
+    }
+
+    // Loads what the reloaded assets now refer to, and rebuilds the assets that depend on them
+    // in topological order. Errors are added to the given ones.
+    fn rebuild_reloaded_assets(
+        &mut self,
+        reloaded_keys: &[AssetKey],
+        mut errors: Vec<AssetManagerError>,
+    ) -> Vec<AssetManagerError> {
+        // Reloading can change what an asset refers to.
+        for key in reloaded_keys {
+            if let Err(error) = self.load_asset_dependencies(*key) {
+                errors.push(error);
+            }
+        }
+
+        let dependent_keys = match self
+            .dependency_graph
+            .get_dependents_in_reload_order(reloaded_keys)
+        {
+            Ok(dependent_keys) => dependent_keys,
+            Err(error) => {
+                errors.push(error);
+                return errors;
+            }
+        };
+
+        for key in dependent_keys {
+            // The manager is taken out of the registry while its asset is rebuilt, so that the
+            // asset can look up its dependencies in the other managers.
+            let mut manager = match self.managers.remove(&key.type_id()) {
+                Some(manager) => manager,
+                None => continue,
+            };
+            let result = manager.rebuild_asset(key, self);
+            self.managers.insert(key.type_id(), manager);
+
+            match result {
+                Ok(_) => self.update_asset_dependencies(key),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        errors
+    }
+
+    // The manager of the asset is taken out of the registry in the meantime, so that the asset
+    // can load the assets it refers to from the other managers. The dependencies of the asset
+    // are updated either way, since it may have found some of them before failing.
+    fn load_asset_dependencies(&mut self, key: AssetKey) -> Result<(), AssetManagerError> {
+        let mut manager = match self.managers.remove(&key.type_id()) {
+            Some(manager) => manager,
+            None => return Ok(()),
+        };
+        let result = manager.load_asset_dependencies(key, self);
+        self.managers.insert(key.type_id(), manager);
+        self.update_asset_dependencies(key);
+
+        result
+    }
+
+    fn remove_unloaded_asset_dependencies(&mut self) {
+        for key in self.dependency_graph.keys() {
+            let is_unloaded = match self.managers.get(&key.type_id()) {
+                Some(manager) => manager.get_asset_dependencies(key).is_none(),
+                None => true,
+            };
+            if is_unloaded {
+                self.dependency_graph.remove_asset(key);
+            }
+        }
+    }
+
+    fn update_asset_dependencies(&mut self, key: AssetKey) {
+        let dependencies = match self.managers.get(&key.type_id()) {
+            Some(manager) => manager.get_asset_dependencies(key),
+            None => None,
+        };
+
+        match dependencies {
+            Some(dependencies) => self.dependency_graph.set_dependencies(key, &dependencies),
+            None => self.dependency_graph.remove_asset(key),
+        }
+    }
+
+    fn unregistered_asset_type_error<A: 'static>() -> AssetManagerError {
+        AssetManagerError::new(
+            format!(
+                "no asset manager is registered for assets of type, {}",
+                any::type_name::<A>()
+            ),
+            AssetManagerErrorKind::UnregisteredAssetType,
+            None,
+        )
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Number of lines shown before and after the line a diagnostic points at.
+pub const SHADER_SOURCE_CONTEXT_LINES: usize = 2;
+
+// Source string number, line, and column of a diagnostic.
+type ShaderLocation = (usize, usize, Option<usize>);
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ShaderDiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for ShaderDiagnosticSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+// A single error, or warning, from the info log of a shader compiler.
+#[derive(Clone, Debug)]
+pub struct ShaderDiagnostic {
+    severity: ShaderDiagnosticSeverity,
+    file_path: Option<PathBuf>,
+    line: Option<usize>,
+    column: Option<usize>,
+    message: String,
+    // Lines of the file around the line of the diagnostic, paired with their line numbers.
+    source_excerpt: Vec<(usize, String)>,
+}
+
+impl ShaderDiagnostic {
+    pub fn severity(&self) -> ShaderDiagnosticSeverity {
+        self.severity
+    }
+
+    // None if the compiler did not say where the diagnostic is, or referred to a source string
+    // that the preprocessor did not emit.
+    pub fn file_path(&self) -> Option<&Path> {
+        self.file_path.as_deref()
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    // Only some drivers report columns. They start at 1.
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+
+    pub fn message(&self) -> &str {
+        self.message.as_str()
+    }
+
+    pub fn source_excerpt(&self) -> &[(usize, String)] {
+        self.source_excerpt.as_slice()
+    }
+}
+
+// E.g. "assets/shaders/triangle.frag:12:5: error: undeclared identifier".
+impl fmt::Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file_path) = &self.file_path {
+            write!(f, "{}:", file_path.to_string_lossy())?;
+        }
+        if let Some(line) = self.line {
+            write!(f, "{}:", line)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, "{}:", column)?;
+        }
+        if self.file_path.is_some() || self.line.is_some() {
+            write!(f, " ")?;
+        }
+
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+// Parses the info log of a failed compilation into diagnostics. The source string numbers in
+// the log are indices into `source_files`, as set by the #line directives emitted by the
+// preprocessor (see PreprocessedSource). The formats of the common drivers are understood:
+//   Mesa:          0:12(5): error: ...
+//   NVIDIA:        0(12) : error C0000: ...
+//   AMD and Apple: ERROR: 0:12: ...
+// Lines in none of them become diagnostics without a location.
+pub fn parse_shader_info_log(info_log: &str, source_files: &[PathBuf]) -> Vec<ShaderDiagnostic> {
+    let mut diagnostics: Vec<ShaderDiagnostic> = vec![];
+    let mut file_lines: HashMap<usize, Vec<String>> = HashMap::new();
+    for log_line in info_log.lines() {
+        let log_line = log_line.trim().trim_end_matches('\0');
+        if log_line.is_empty() {
+            continue;
+        }
+
+        let (severity, rest) = match strip_severity_prefix(log_line) {
+            Some((severity, rest)) => (Some(severity), rest),
+            None => (None, log_line),
+        };
+        let (location, rest) = match parse_location(rest) {
+            Some((location, rest)) => (Some(location), rest),
+            None => (None, rest),
+        };
+        let (severity, message) = match severity {
+            Some(severity) => (severity, rest.trim()),
+            None => match strip_severity_prefix(rest.trim()) {
+                Some((severity, message)) => (severity, message.trim()),
+                None => (ShaderDiagnosticSeverity::Error, rest.trim()),
+            },
+        };
+
+        let mut diagnostic = ShaderDiagnostic {
+            severity,
+            file_path: None,
+            line: None,
+            column: None,
+            message: String::from(message),
+            source_excerpt: vec![],
+        };
+        if let Some((source_index, line, column)) = location {
+            diagnostic.file_path = source_files.get(source_index).cloned();
+            diagnostic.line = Some(line);
+            diagnostic.column = column;
+
+            if let Some(file_path) = &diagnostic.file_path {
+                let lines = file_lines
+                    .entry(source_index)
+                    .or_insert_with(|| read_file_lines(file_path));
+                diagnostic.source_excerpt = excerpt_lines(lines, line);
+            }
+        }
+
+        diagnostics.push(diagnostic);
+    }
+
+    diagnostics
+}
+
+// Strips "ERROR:", "error:", or "error C0000:", and their warning counterparts.
+fn strip_severity_prefix(text: &str) -> Option<(ShaderDiagnosticSeverity, &str)> {
+    let lowercase_text = text.to_ascii_lowercase();
+    let (severity, rest) = if lowercase_text.starts_with("error") {
+        (ShaderDiagnosticSeverity::Error, &text["error".len()..])
+    } else if lowercase_text.starts_with("warning") {
+        (ShaderDiagnosticSeverity::Warning, &text["warning".len()..])
+    } else {
+        return None;
+    };
+
+    let (code, rest) = rest.split_once(':')?;
+    let is_code = code
+        .trim()
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    match is_code {
+        true => Some((severity, rest)),
+        false => None,
+    }
+}
+
+// Parses "S:L(C):", "S(L) :", or "S:L:" into the source string number, line, and column.
+fn parse_location(text: &str) -> Option<(ShaderLocation, &str)> {
+    let text = text.trim_start();
+    let (source_index, rest) = take_number(text)?;
+    if let Some(rest) = rest.strip_prefix('(') {
+        let (line, rest) = take_number(rest)?;
+        let rest = rest.strip_prefix(')')?.trim_start().strip_prefix(':')?;
+        return Some(((source_index, line, None), rest));
+    }
+
+    let (line, rest) = take_number(rest.strip_prefix(':')?)?;
+    let (column, rest) = match rest.strip_prefix('(') {
+        Some(rest) => {
+            let (column, rest) = take_number(rest)?;
+            (Some(column), rest.strip_prefix(')')?)
+        }
+        None => (None, rest),
+    };
+    let rest = rest.strip_prefix(':')?;
+
+    Some(((source_index, line, column), rest))
+}
+
+fn take_number(text: &str) -> Option<(usize, &str)> {
+    let num_digits = text.chars().take_while(char::is_ascii_digit).count();
+    let number = text[..num_digits].parse().ok()?;
+
+    Some((number, &text[num_digits..]))
+}
+
+// Files that cannot be read, e.g. because they were removed since they were compiled, get no
+// excerpts.
+fn read_file_lines(file_path: &Path) -> Vec<String> {
+    match fs::read_to_string(file_path) {
+        Ok(contents) => contents.lines().map(String::from).collect(),
+        Err(_) => vec![],
+    }
+}
+
+fn excerpt_lines(lines: &[String], line: usize) -> Vec<(usize, String)> {
+    if line == 0 || line > lines.len() {
+        return vec![];
+    }
+
+    let first_line = line.saturating_sub(SHADER_SOURCE_CONTEXT_LINES).max(1);
+    let last_line = (line + SHADER_SOURCE_CONTEXT_LINES).min(lines.len());
+    (first_line..=last_line)
+        .map(|line_number| (line_number, lines[line_number - 1].clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::slice;
+
+    fn source_files() -> Vec<PathBuf> {
+        vec![
+            PathBuf::from("shaders/missing.frag"),
+            PathBuf::from("shaders/missing.glsl"),
+        ]
+    }
+
+    fn parse_one(info_log: &str) -> ShaderDiagnostic {
+        let mut diagnostics = parse_shader_info_log(info_log, &source_files());
+        assert_eq!(diagnostics.len(), 1);
+
+        diagnostics.remove(0)
+    }
+
+    #[test]
+    fn parses_mesa_diagnostics() {
+        let diagnostic = parse_one("1:12(5): error: `color' undeclared");
+        assert_eq!(diagnostic.severity(), ShaderDiagnosticSeverity::Error);
+        assert_eq!(
+            diagnostic.file_path(),
+            Some(Path::new("shaders/missing.glsl"))
+        );
+        assert_eq!(diagnostic.line(), Some(12));
+        assert_eq!(diagnostic.column(), Some(5));
+        assert_eq!(diagnostic.message(), "`color' undeclared");
+    }
+
+    #[test]
+    fn parses_nvidia_diagnostics() {
+        let diagnostic = parse_one("0(7) : warning C7022: unrecognized profile specifier");
+        assert_eq!(diagnostic.severity(), ShaderDiagnosticSeverity::Warning);
+        assert_eq!(
+            diagnostic.file_path(),
+            Some(Path::new("shaders/missing.frag"))
+        );
+        assert_eq!(diagnostic.line(), Some(7));
+        assert_eq!(diagnostic.column(), None);
+        assert_eq!(diagnostic.message(), "unrecognized profile specifier");
+    }
+
+    #[test]
+    fn parses_amd_diagnostics() {
+        let diagnostic = parse_one("ERROR: 0:3: 'color' : undeclared identifier");
+        assert_eq!(diagnostic.severity(), ShaderDiagnosticSeverity::Error);
+        assert_eq!(
+            diagnostic.file_path(),
+            Some(Path::new("shaders/missing.frag"))
+        );
+        assert_eq!(diagnostic.line(), Some(3));
+        assert_eq!(diagnostic.column(), None);
+        assert_eq!(diagnostic.message(), "'color' : undeclared identifier");
+    }
+
+    #[test]
+    fn keeps_lines_without_a_location() {
+        let diagnostic = parse_one("ERROR: 1 compilation errors.  No code generated.\0");
+        assert_eq!(diagnostic.severity(), ShaderDiagnosticSeverity::Error);
+        assert_eq!(diagnostic.file_path(), None);
+        assert_eq!(diagnostic.line(), None);
+        assert_eq!(
+            diagnostic.message(),
+            "1 compilation errors.  No code generated."
+        );
+    }
+
+    #[test]
+    fn skips_source_strings_that_were_not_emitted() {
+        let diagnostic = parse_one("5:2(1): error: syntax error");
+        assert_eq!(diagnostic.file_path(), None);
+        assert_eq!(diagnostic.line(), Some(2));
+        assert_eq!(diagnostic.to_string(), "2:1: error: syntax error");
+    }
+
+    #[test]
+    fn parses_every_line_of_the_log() {
+        let info_log = "0:1(1): warning: extension unused\n\n0:2(3): error: syntax error\n";
+        let diagnostics = parse_shader_info_log(info_log, &source_files());
+        let severities: Vec<ShaderDiagnosticSeverity> =
+            diagnostics.iter().map(ShaderDiagnostic::severity).collect();
+        assert_eq!(
+            severities,
+            vec![
+                ShaderDiagnosticSeverity::Warning,
+                ShaderDiagnosticSeverity::Error
+            ]
+        );
+    }
+
+    #[test]
+    fn excerpts_lines_around_the_diagnostic() {
+        let file_path = env::temp_dir().join("mulay-gfx-shader-diagnostic-test.frag");
+        fs::write(&file_path, "a\nb\nc\nd\ne\nf\n").unwrap();
+        let diagnostics =
+            parse_shader_info_log("0:2(1): error: syntax error", slice::from_ref(&file_path));
+        fs::remove_file(&file_path).unwrap();
+
+        assert_eq!(
+            diagnostics[0].source_excerpt(),
+            &[
+                (1, String::from("a")),
+                (2, String::from("b")),
+                (3, String::from("c")),
+                (4, String::from("d")),
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            format!("{}:2:1: error: syntax error", file_path.to_string_lossy())
+        );
+    }
+}
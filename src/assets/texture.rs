@@ -0,0 +1,605 @@
+extern crate gl;
+
+use std::error::Error;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use image::{self, DynamicImage};
+use serde::Deserialize;
+
+use crate::assets::{Asset, AssetDecoder, AssetError, AssetErrorKind, AssetThumbnail, AsyncAsset};
+
+// Maximum width and height of texture thumbnails.
+const THUMBNAIL_SIZE: usize = 64;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum TextureErrorKind {
+    DecodingFailed,
+    UnsupportedFormat,
+    InvalidSettings,
+}
+
+#[derive(Debug)]
+pub struct TextureError {
+    source: Option<Box<dyn Error + 'static>>,
+    message: String,
+    kind: TextureErrorKind,
+}
+
+impl TextureError {
+    pub fn new(
+        message: impl AsRef<str>,
+        kind: TextureErrorKind,
+        source: Option<Box<dyn Error + 'static>>,
+    ) -> TextureError {
+        TextureError {
+            source,
+            message: message.as_ref().into(),
+            kind,
+        }
+    }
+
+    pub fn kind(&self) -> TextureErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for TextureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureWrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder,
+}
+
+impl TextureWrap {
+    fn gl_enum(&self) -> gl::types::GLenum {
+        match self {
+            TextureWrap::Repeat => gl::REPEAT,
+            TextureWrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            TextureWrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+            TextureWrap::ClampToBorder => gl::CLAMP_TO_BORDER,
+        }
+    }
+}
+
+// Settings can be given to a texture through a sidecar file next to its image, named after the
+// image with ".texture" appended (e.g. "bricks.png.texture"), e.g.:
+//
+//     min_filter = "nearest"
+//     mag_filter = "nearest"
+//     wrap_s = "clamp_to_edge"
+//     generate_mipmaps = false
+//
+// Settings that are left out, or all of them if there is no sidecar file, take their default
+// values.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TextureSettings {
+    pub min_filter: TextureFilter,
+    pub mag_filter: TextureFilter,
+    pub wrap_s: TextureWrap,
+    pub wrap_t: TextureWrap,
+    pub generate_mipmaps: bool,
+    // Whether the colour data of LDR images is in sRGB space, in which case the GPU converts
+    // it to linear space when sampled. HDR images are always treated as linear.
+    pub srgb: bool,
+    // Images are stored top row first, while OpenGL expects the bottom row first.
+    pub flip_vertically: bool,
+}
+
+impl Default for TextureSettings {
+    fn default() -> Self {
+        Self {
+            min_filter: TextureFilter::Linear,
+            mag_filter: TextureFilter::Linear,
+            wrap_s: TextureWrap::Repeat,
+            wrap_t: TextureWrap::Repeat,
+            generate_mipmaps: true,
+            srgb: true,
+            flip_vertically: true,
+        }
+    }
+}
+
+impl TextureSettings {
+    pub fn sidecar_file_path(image_file_path: &Path) -> PathBuf {
+        let mut file_path: OsString = image_file_path.as_os_str().to_owned();
+        file_path.push(".texture");
+        PathBuf::from(file_path)
+    }
+
+    // Reads the settings of the image from its sidecar file, if it has one.
+    pub fn from_sidecar_file(image_file_path: &Path) -> Result<Self, TextureError> {
+        let file_path = Self::sidecar_file_path(image_file_path);
+        if !file_path.is_file() {
+            return Ok(Self::default());
+        }
+
+        let contents = match fs::read_to_string(file_path.as_path()) {
+            Ok(contents) => contents,
+            Err(error) => {
+                return Err(TextureError::new(
+                    format!(
+                        "unable to read texture settings from {}",
+                        file_path.to_string_lossy()
+                    ),
+                    TextureErrorKind::InvalidSettings,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        match toml::from_str(contents.as_str()) {
+            Ok(settings) => Ok(settings),
+            Err(error) => Err(TextureError::new(
+                format!(
+                    "texture settings in {} are not valid",
+                    file_path.to_string_lossy()
+                ),
+                TextureErrorKind::InvalidSettings,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+}
+
+// Pixel data decoded from an image file, ready to be uploaded to the GPU.
+pub enum DecodedImage {
+    Ldr {
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+    },
+    Hdr {
+        width: u32,
+        height: u32,
+        pixels: Vec<f32>,
+    },
+}
+
+impl DecodedImage {
+    pub fn width(&self) -> u32 {
+        match self {
+            DecodedImage::Ldr { width, .. } | DecodedImage::Hdr { width, .. } => *width,
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match self {
+            DecodedImage::Ldr { height, .. } | DecodedImage::Hdr { height, .. } => *height,
+        }
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        matches!(self, DecodedImage::Hdr { .. })
+    }
+}
+
+// An image along with the settings it was decoded with, which it gets uploaded with too.
+pub struct DecodedTexture {
+    image: DecodedImage,
+    settings: TextureSettings,
+}
+
+pub struct Texture {
+    id: String,
+    texture_id: gl::types::GLuint,
+    src_file_path: PathBuf,
+    settings: TextureSettings,
+    width: u32,
+    height: u32,
+    is_hdr: bool,
+    thumbnail: Option<AssetThumbnail>,
+    is_loaded: bool,
+    is_stale: bool,
+}
+
+impl Asset for Texture {
+    fn new<S: AsRef<str>>(id: S, file_path: &Path) -> Result<Self, AssetError> {
+        let decoded_texture = Self::decode_file(file_path)?;
+        Self::from_decoded(id, file_path, decoded_texture)
+    }
+
+    fn supported_file_extensions() -> &'static [&'static str] {
+        &["png", "jpg", "jpeg", "tga", "hdr"]
+    }
+
+    // The image is decoded before anything is uploaded, so a broken file leaves the current
+    // texture untouched.
+    fn reload(&mut self) -> Result<(), AssetError> {
+        let decoded_texture = (self.reload_decoder())()?;
+        self.reload_decoded(decoded_texture)
+    }
+
+    fn destroy(&mut self) -> Result<(), AssetError> {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture_id);
+        };
+
+        self.id.clear();
+        self.src_file_path.clear();
+        self.is_loaded = false;
+
+        Ok(())
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.is_loaded
+    }
+
+    fn is_stale(&self) -> bool {
+        self.is_stale
+    }
+
+    fn mark_stale(&mut self) {
+        self.is_stale = true;
+    }
+
+    fn get_src_file_path(&self) -> &Path {
+        self.src_file_path.as_path()
+    }
+
+    fn get_memory_usage(&self) -> Option<usize> {
+        let bytes_per_pixel: usize = match self.is_hdr {
+            true => 16,
+            false => 4,
+        };
+        let mut memory_usage = self.width as usize * self.height as usize * bytes_per_pixel;
+        // A full mipmap chain adds a third of the base level.
+        if self.settings.generate_mipmaps {
+            memory_usage += memory_usage / 3;
+        }
+        if let Some(thumbnail) = &self.thumbnail {
+            memory_usage += thumbnail.pixels.len();
+        }
+
+        Some(memory_usage)
+    }
+
+    fn get_thumbnail(&self) -> Option<AssetThumbnail> {
+        self.thumbnail.clone()
+    }
+}
+
+impl AsyncAsset for Texture {
+    type Decoded = DecodedTexture;
+
+    // The settings are read from the sidecar file of the image (see TextureSettings). They can be
+    // changed afterwards through set_settings().
+    fn decode_file(file_path: &Path) -> Result<DecodedTexture, AssetError> {
+        let decoded_texture = TextureSettings::from_sidecar_file(file_path).and_then(|settings| {
+            Ok(DecodedTexture {
+                image: Self::decode(file_path, &settings)?,
+                settings,
+            })
+        });
+        match decoded_texture {
+            Ok(decoded_texture) => Ok(decoded_texture),
+            Err(error) => Err(AssetError::new(
+                format!(
+                    "unable to load texture from {}",
+                    file_path.to_string_lossy()
+                ),
+                AssetErrorKind::LoadingFailed,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+
+    fn from_decoded<S: AsRef<str>>(
+        id: S,
+        file_path: &Path,
+        decoded_texture: DecodedTexture,
+    ) -> Result<Self, AssetError> {
+        let DecodedTexture { image, settings } = decoded_texture;
+
+        let mut texture_id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenTextures(1, &mut texture_id);
+        };
+        Self::upload(texture_id, &image, &settings);
+
+        Ok(Self {
+            id: id.as_ref().into(),
+            texture_id,
+            src_file_path: file_path.to_path_buf(),
+            settings,
+            width: image.width(),
+            height: image.height(),
+            is_hdr: image.is_hdr(),
+            thumbnail: Self::create_thumbnail(&image, &settings),
+            is_loaded: true,
+            is_stale: false,
+        })
+    }
+
+    // Decodes with the settings the texture currently has.
+    fn reload_decoder(&self) -> AssetDecoder<DecodedTexture> {
+        let src_file_path = self.src_file_path.clone();
+        let settings = self.settings;
+        Box::new(
+            move || match Self::decode(src_file_path.as_path(), &settings) {
+                Ok(image) => Ok(DecodedTexture { image, settings }),
+                Err(error) => Err(AssetError::new(
+                    format!(
+                        "unable to hot-reload texture from {}",
+                        src_file_path.to_string_lossy()
+                    ),
+                    AssetErrorKind::ReloadingFailed,
+                    Some(Box::new(error)),
+                )),
+            },
+        )
+    }
+
+    fn reload_decoded(&mut self, decoded_texture: DecodedTexture) -> Result<(), AssetError> {
+        if !self.is_loaded {
+            return Err(AssetError::new(
+                format!("asset, '{}', not yet loaded", self.id.as_str()),
+                AssetErrorKind::NotLoaded,
+                None,
+            ));
+        }
+
+        let DecodedTexture { image, settings } = decoded_texture;
+        Self::upload(self.texture_id, &image, &settings);
+
+        self.width = image.width();
+        self.height = image.height();
+        self.is_hdr = image.is_hdr();
+        self.thumbnail = Self::create_thumbnail(&image, &settings);
+        self.is_stale = false;
+
+        Ok(())
+    }
+}
+
+impl Texture {
+    pub fn get_texture_id(&self) -> gl::types::GLuint {
+        self.texture_id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        self.is_hdr
+    }
+
+    pub fn settings(&self) -> &TextureSettings {
+        &self.settings
+    }
+
+    // Changing the settings re-uploads the texture from its source file, since sRGB handling
+    // and flipping affect the uploaded data.
+    pub fn set_settings(&mut self, settings: TextureSettings) -> Result<(), AssetError> {
+        let old_settings = self.settings;
+        self.settings = settings;
+
+        match self.reload() {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                self.settings = old_settings;
+                Err(error)
+            }
+        }
+    }
+
+    pub fn bind(&self, texture_unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + texture_unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture_id);
+        };
+    }
+
+    pub fn decode(
+        file_path: &Path,
+        settings: &TextureSettings,
+    ) -> Result<DecodedImage, TextureError> {
+        let image: DynamicImage = match image::open(file_path) {
+            Ok(image) => image,
+            Err(image::ImageError::Unsupported(error)) => {
+                return Err(TextureError::new(
+                    format!(
+                        "image format of {} is not supported",
+                        file_path.to_string_lossy()
+                    ),
+                    TextureErrorKind::UnsupportedFormat,
+                    Some(Box::new(error)),
+                ))
+            }
+            Err(error) => {
+                return Err(TextureError::new(
+                    format!("unable to decode image, {}", file_path.to_string_lossy()),
+                    TextureErrorKind::DecodingFailed,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        let image = match settings.flip_vertically {
+            true => image.flipv(),
+            false => image,
+        };
+
+        let decoded_image = match image {
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+                let pixels = image.to_rgba32f();
+                DecodedImage::Hdr {
+                    width: pixels.width(),
+                    height: pixels.height(),
+                    pixels: pixels.into_raw(),
+                }
+            }
+            _ => {
+                let pixels = image.to_rgba8();
+                DecodedImage::Ldr {
+                    width: pixels.width(),
+                    height: pixels.height(),
+                    pixels: pixels.into_raw(),
+                }
+            }
+        };
+
+        Ok(decoded_image)
+    }
+
+    // Samples the nearest pixels of the image, so that it costs next to nothing even for large
+    // textures. HDR colours are clamped to [0, 1]. Returns None for empty images.
+    pub fn create_thumbnail(
+        image: &DecodedImage,
+        settings: &TextureSettings,
+    ) -> Option<AssetThumbnail> {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let scale = (THUMBNAIL_SIZE as f32 / width.max(height) as f32).min(1f32);
+        let thumbnail_width = ((width as f32 * scale).round() as usize).max(1);
+        let thumbnail_height = ((height as f32 * scale).round() as usize).max(1);
+
+        let mut pixels: Vec<u8> = Vec::with_capacity(thumbnail_width * thumbnail_height * 4);
+        for y in 0..thumbnail_height {
+            // Flipped images are flipped back, since thumbnails are stored top row first.
+            let src_y = match settings.flip_vertically {
+                true => height - 1 - y * height / thumbnail_height,
+                false => y * height / thumbnail_height,
+            };
+            for x in 0..thumbnail_width {
+                let index = (src_y * width + x * width / thumbnail_width) * 4;
+                match image {
+                    DecodedImage::Ldr { pixels: src, .. } => {
+                        pixels.extend_from_slice(&src[index..index + 4])
+                    }
+                    DecodedImage::Hdr { pixels: src, .. } => pixels.extend(
+                        src[index..index + 4]
+                            .iter()
+                            .map(|component| (component.clamp(0f32, 1f32) * 255f32).round() as u8),
+                    ),
+                }
+            }
+        }
+
+        Some(AssetThumbnail {
+            width: thumbnail_width,
+            height: thumbnail_height,
+            pixels: Arc::new(pixels),
+        })
+    }
+
+    pub fn upload(texture_id: gl::types::GLuint, image: &DecodedImage, settings: &TextureSettings) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture_id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+
+            match image {
+                DecodedImage::Ldr {
+                    width,
+                    height,
+                    pixels,
+                } => {
+                    let internal_format = match settings.srgb {
+                        true => gl::SRGB8_ALPHA8,
+                        false => gl::RGBA8,
+                    };
+                    gl::TexImage2D(
+                        gl::TEXTURE_2D,
+                        0,
+                        internal_format as gl::types::GLint,
+                        *width as gl::types::GLsizei,
+                        *height as gl::types::GLsizei,
+                        0,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        pixels.as_ptr() as *const gl::types::GLvoid,
+                    );
+                }
+                DecodedImage::Hdr {
+                    width,
+                    height,
+                    pixels,
+                } => {
+                    gl::TexImage2D(
+                        gl::TEXTURE_2D,
+                        0,
+                        gl::RGBA32F as gl::types::GLint,
+                        *width as gl::types::GLsizei,
+                        *height as gl::types::GLsizei,
+                        0,
+                        gl::RGBA,
+                        gl::FLOAT,
+                        pixels.as_ptr() as *const gl::types::GLvoid,
+                    );
+                }
+            }
+
+            let min_filter = match (settings.min_filter, settings.generate_mipmaps) {
+                (TextureFilter::Nearest, false) => gl::NEAREST,
+                (TextureFilter::Linear, false) => gl::LINEAR,
+                (TextureFilter::Nearest, true) => gl::NEAREST_MIPMAP_NEAREST,
+                (TextureFilter::Linear, true) => gl::LINEAR_MIPMAP_LINEAR,
+            };
+            let mag_filter = match settings.mag_filter {
+                TextureFilter::Nearest => gl::NEAREST,
+                TextureFilter::Linear => gl::LINEAR,
+            };
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MIN_FILTER,
+                min_filter as gl::types::GLint,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MAG_FILTER,
+                mag_filter as gl::types::GLint,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_S,
+                settings.wrap_s.gl_enum() as gl::types::GLint,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_T,
+                settings.wrap_t.gl_enum() as gl::types::GLint,
+            );
+
+            if settings.generate_mipmaps {
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+            }
+
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        };
+    }
+}
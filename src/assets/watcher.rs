@@ -1,13 +1,21 @@
+use std::env;
 use std::error::Error;
 use std::fmt;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use glob::{MatchOptions, Pattern};
 use notify::{self, Watcher};
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum AssetsWatcherErrorKind {
     InitializationError,
+    InvalidBackend,
+    EventQueuePoisoned,
+    InvalidGlobPattern,
+    PathNotFound,
+    WatchFailed,
 }
 
 #[derive(Debug)]
@@ -29,6 +37,10 @@ impl AssetsWatcherError {
             kind,
         }
     }
+
+    pub fn kind(&self) -> AssetsWatcherErrorKind {
+        self.kind
+    }
 }
 
 impl fmt::Display for AssetsWatcherError {
@@ -43,48 +55,319 @@ impl Error for AssetsWatcherError {
     }
 }
 
+// Changes are only reported once a path has been quiet for this long, so that the several
+// events an editor produces for a single save result in a single reload.
+pub const DEFAULT_DEBOUNCE_WINDOW: Duration = Duration::from_millis(100);
+
+// Name of the environment variable that selects the watcher backend when none is given at
+// construction. Accepts "native", "poll", or "poll:<interval in milliseconds>".
+pub const WATCHER_BACKEND_ENV_VAR: &str = "MULAY_GFX_WATCHER";
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum WatcherBackend {
+    // File system notifications from the OS (inotify, FSEvents, ReadDirectoryChangesW).
+    Native,
+    // Scans the watched paths every interval, comparing modification times and hashes of file
+    // contents. Slower, but works where the OS does not deliver notifications, such as network
+    // mounts and Docker bind mounts.
+    Polling { interval: Duration },
+}
+
+impl WatcherBackend {
+    // Reads the backend from WATCHER_BACKEND_ENV_VAR, defaulting to the native one.
+    pub fn from_env() -> Result<Self, AssetsWatcherError> {
+        match env::var(WATCHER_BACKEND_ENV_VAR) {
+            Ok(value) => Self::parse(value.as_str()),
+            Err(env::VarError::NotPresent) => Ok(WatcherBackend::Native),
+            Err(error) => Err(AssetsWatcherError::new(
+                format!("value of {} is not valid unicode", WATCHER_BACKEND_ENV_VAR),
+                AssetsWatcherErrorKind::InvalidBackend,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, AssetsWatcherError> {
+        let value = value.trim();
+        match value {
+            "" | "native" => return Ok(WatcherBackend::Native),
+            "poll" => {
+                return Ok(WatcherBackend::Polling {
+                    interval: DEFAULT_POLL_INTERVAL,
+                })
+            }
+            _ => {}
+        }
+
+        match value
+            .strip_prefix("poll:")
+            .map(|interval| interval.parse::<u64>())
+        {
+            Some(Ok(interval_ms)) if interval_ms > 0 => Ok(WatcherBackend::Polling {
+                interval: Duration::from_millis(interval_ms),
+            }),
+            _ => Err(AssetsWatcherError::new(
+                format!(
+                    "unknown watcher backend, \"{}\". Expected \"native\", \"poll\", or \
+                    \"poll:<interval in milliseconds>\"",
+                    value
+                ),
+                AssetsWatcherErrorKind::InvalidBackend,
+                None,
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum AssetEvent {
+    Modified(PathBuf),
+    Created(PathBuf),
+    Removed(PathBuf),
+    Renamed { from: PathBuf, to: PathBuf },
+}
+
+impl AssetEvent {
+    // Path the event leaves the asset at. For renames, this is the new path.
+    pub fn path(&self) -> &Path {
+        match self {
+            AssetEvent::Modified(path) | AssetEvent::Created(path) | AssetEvent::Removed(path) => {
+                path.as_path()
+            }
+            AssetEvent::Renamed { to, .. } => to.as_path(),
+        }
+    }
+
+    // Merges a new event for a path with the one still pending for it. Returns None when the
+    // two cancel each other out, e.g. a temporary file that is created and then removed.
+    fn coalesce(pending_event: AssetEvent, new_event: AssetEvent) -> Option<AssetEvent> {
+        match (pending_event, new_event) {
+            (AssetEvent::Created(_), AssetEvent::Modified(path)) => Some(AssetEvent::Created(path)),
+            (AssetEvent::Created(_), AssetEvent::Removed(_)) => None,
+            // Editors that save atomically (e.g. vim) remove the file and write a new one.
+            (AssetEvent::Removed(_), AssetEvent::Created(path))
+            | (AssetEvent::Removed(_), AssetEvent::Modified(path))
+            | (AssetEvent::Modified(_), AssetEvent::Created(path)) => {
+                Some(AssetEvent::Modified(path))
+            }
+            (AssetEvent::Renamed { from, to }, AssetEvent::Modified(_))
+            | (AssetEvent::Renamed { from, to }, AssetEvent::Created(_)) => {
+                Some(AssetEvent::Renamed { from, to })
+            }
+            (_, new_event) => Some(new_event),
+        }
+    }
+}
+
+struct PendingEvent {
+    event: AssetEvent,
+    last_updated_at: Instant,
+}
+
+#[derive(Default)]
+struct EventQueue {
+    pending_events: Vec<PendingEvent>,
+}
+
+impl EventQueue {
+    fn push(&mut self, event: AssetEvent) {
+        // A rename supersedes whatever is pending for the path it moved away from.
+        if let AssetEvent::Renamed { from, .. } = &event {
+            let from = from.clone();
+            self.pending_events
+                .retain(|pending_event| pending_event.event.path() != from.as_path());
+        }
+
+        let now = Instant::now();
+        let pending_index = self
+            .pending_events
+            .iter()
+            .position(|pending_event| pending_event.event.path() == event.path());
+        match pending_index {
+            Some(index) => {
+                let pending_event = self.pending_events.remove(index);
+                if let Some(event) = AssetEvent::coalesce(pending_event.event, event) {
+                    self.pending_events.insert(
+                        index,
+                        PendingEvent {
+                            event,
+                            last_updated_at: now,
+                        },
+                    );
+                }
+            }
+            None => self.pending_events.push(PendingEvent {
+                event,
+                last_updated_at: now,
+            }),
+        }
+    }
+
+    // Removes and returns the events whose paths have been quiet for the debounce window, in
+    // the order they were first seen.
+    fn take_settled_events(&mut self, debounce_window: Duration) -> Vec<AssetEvent> {
+        let now = Instant::now();
+        let mut settled_events: Vec<AssetEvent> = vec![];
+        let mut index = 0;
+        while index < self.pending_events.len() {
+            if now.duration_since(self.pending_events[index].last_updated_at) >= debounce_window {
+                settled_events.push(self.pending_events.remove(index).event);
+            } else {
+                index += 1;
+            }
+        }
+
+        settled_events
+    }
+}
+
+// Decides which files in a watched directory are reported. Patterns are matched against paths
+// relative to the directory, e.g. "**/*.png" or "shaders/*.glsl". A file is reported if it
+// matches any include pattern (or there are none) and none of the exclude patterns.
+#[derive(Clone, Debug, Default)]
+pub struct WatchFilter {
+    include_patterns: Vec<Pattern>,
+    exclude_patterns: Vec<Pattern>,
+}
+
+impl WatchFilter {
+    pub fn new<S: AsRef<str>>(
+        include_patterns: &[S],
+        exclude_patterns: &[S],
+    ) -> Result<Self, AssetsWatcherError> {
+        Ok(Self {
+            include_patterns: Self::compile_patterns(include_patterns)?,
+            exclude_patterns: Self::compile_patterns(exclude_patterns)?,
+        })
+    }
+
+    pub fn matches(&self, relative_path: &Path) -> bool {
+        let match_options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        let is_included = self.include_patterns.is_empty()
+            || self
+                .include_patterns
+                .iter()
+                .any(|pattern| pattern.matches_path_with(relative_path, match_options));
+        let is_excluded = self
+            .exclude_patterns
+            .iter()
+            .any(|pattern| pattern.matches_path_with(relative_path, match_options));
+
+        is_included && !is_excluded
+    }
+
+    fn compile_patterns<S: AsRef<str>>(patterns: &[S]) -> Result<Vec<Pattern>, AssetsWatcherError> {
+        let mut compiled_patterns: Vec<Pattern> = vec![];
+        for pattern in patterns {
+            match Pattern::new(pattern.as_ref()) {
+                Ok(compiled_pattern) => compiled_patterns.push(compiled_pattern),
+                Err(error) => {
+                    return Err(AssetsWatcherError::new(
+                        format!("invalid glob pattern, \"{}\"", pattern.as_ref()),
+                        AssetsWatcherErrorKind::InvalidGlobPattern,
+                        Some(Box::new(error)),
+                    ))
+                }
+            }
+        }
+
+        Ok(compiled_patterns)
+    }
+}
+
+struct WatchedDirectory {
+    path: PathBuf,
+    filter: WatchFilter,
+}
+
 pub struct AssetsWatcher {
-    watcher: notify::RecommendedWatcher,
-    stale_paths: Arc<RwLock<Vec<PathBuf>>>,
+    watcher: Box<dyn Watcher + Send>,
+    backend: WatcherBackend,
+    event_queue: Arc<Mutex<EventQueue>>,
+    debounce_window: Duration,
+    watched_directories: Vec<WatchedDirectory>,
 }
 
 impl AssetsWatcher {
+    // Uses the backend selected through WATCHER_BACKEND_ENV_VAR.
     pub fn new() -> Result<Self, AssetsWatcherError> {
+        Self::with_backend(WatcherBackend::from_env()?, DEFAULT_DEBOUNCE_WINDOW)
+    }
+
+    pub fn with_backend(
+        backend: WatcherBackend,
+        debounce_window: Duration,
+    ) -> Result<Self, AssetsWatcherError> {
         fn watcher_func(
-            stale_paths: &Arc<RwLock<Vec<PathBuf>>>,
+            event_queue: &Arc<Mutex<EventQueue>>,
             event: notify::Result<notify::Event>,
         ) {
-            match event {
-                Ok(notify::Event {
-                    kind: notify::EventKind::Modify(notify::event::ModifyKind::Any),
-                    paths,
-                    ..
-                }) => {
-                    let mut lock_guard = match stale_paths.write() {
-                        Ok(lock_guard) => lock_guard,
-                        Err(error) => {
-                            panic!(
-                                "watcher attempted to write-lock a poisoned lock on \
-                                the tracked stale assets. Error: {:?}",
-                                error
-                            );
-                        }
-                    };
-                    for path in paths {
-                        lock_guard.push(path);
+            use notify::event::{ModifyKind, RenameMode};
+            use notify::EventKind;
+
+            let event = match event {
+                Ok(event) => event,
+                Err(error) => {
+                    log::error!("Watcher error for asset manager occurred: {error}");
+                    return;
+                }
+            };
+
+            let mut paths = event.paths.into_iter();
+            let asset_events: Vec<AssetEvent> = match event.kind {
+                EventKind::Create(_) => paths.map(AssetEvent::Created).collect(),
+                EventKind::Remove(_) => paths.map(AssetEvent::Removed).collect(),
+                EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                    paths.map(AssetEvent::Removed).collect()
+                }
+                EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                    paths.map(AssetEvent::Created).collect()
+                }
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                    match (paths.next(), paths.next()) {
+                        (Some(from), Some(to)) => vec![AssetEvent::Renamed { from, to }],
+                        _ => vec![],
                     }
                 }
-                Err(error) => println!("[STUB] Watcher error for asset manager occurred: {error}"),
-                _ => {}
+                // Without knowing which side of a rename a path is on, the only safe
+                // assumption is that its contents changed.
+                EventKind::Modify(_) => paths.map(AssetEvent::Modified).collect(),
+                _ => vec![],
+            };
+
+            // Events cannot be reported from here. A poisoned queue is reported the next time
+            // events are polled instead.
+            if let Ok(mut event_queue) = event_queue.lock() {
+                for asset_event in asset_events {
+                    event_queue.push(asset_event);
+                }
             }
         }
 
-        let stale_paths: Arc<RwLock<Vec<PathBuf>>> = Arc::new(RwLock::new(vec![]));
+        let event_queue: Arc<Mutex<EventQueue>> = Arc::new(Mutex::new(EventQueue::default()));
 
-        let stale_paths_clone = Arc::clone(&stale_paths);
-        let watcher = match notify::recommended_watcher(move |event| {
-            watcher_func(&stale_paths_clone, event);
-        }) {
+        let event_queue_clone = Arc::clone(&event_queue);
+        let event_handler = move |event| {
+            watcher_func(&event_queue_clone, event);
+        };
+        let watcher: notify::Result<Box<dyn Watcher + Send>> = match backend {
+            WatcherBackend::Native => notify::recommended_watcher(event_handler)
+                .map(|watcher| Box::new(watcher) as Box<dyn Watcher + Send>),
+            WatcherBackend::Polling { interval } => notify::PollWatcher::new(
+                event_handler,
+                notify::Config::default()
+                    .with_poll_interval(interval)
+                    .with_compare_contents(true),
+            )
+            .map(|watcher| Box::new(watcher) as Box<dyn Watcher + Send>),
+        };
+        let watcher = match watcher {
             Ok(watcher) => watcher,
             Err(error) => {
                 return Err(AssetsWatcherError::new(
@@ -97,54 +380,238 @@ impl AssetsWatcher {
 
         Ok(Self {
             watcher,
-            stale_paths,
+            backend,
+            event_queue,
+            debounce_window,
+            watched_directories: vec![],
         })
     }
 
-    pub fn add_paths_to_watchlist<S: AsRef<str>>(&mut self, paths: &Vec<S>) {
+    pub fn add_paths_to_watchlist<S: AsRef<Path>>(
+        &mut self,
+        paths: &Vec<S>,
+    ) -> Result<(), AssetsWatcherError> {
         for path in paths {
-            // Docs of notify-rs does not specify any reason for an error to be returned, so
-            // for now, we can confidently use unwrap() in this case.
-            self.watcher
-                .watch(Path::new(path.as_ref()), notify::RecursiveMode::Recursive)
-                .unwrap();
+            self.watch(path.as_ref())?;
         }
+
+        Ok(())
     }
 
-    pub fn get_stale_paths(&self) -> Vec<PathBuf> {
-        let lock_guard = match self.stale_paths.read() {
-            Ok(lock_guard) => lock_guard,
+    // Watches every file under a directory that passes the filter, including files created
+    // after this call.
+    pub fn watch_directory<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        filter: WatchFilter,
+    ) -> Result<(), AssetsWatcherError> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            return Err(AssetsWatcherError::new(
+                format!("{} is not a directory", path.to_string_lossy()),
+                AssetsWatcherErrorKind::PathNotFound,
+                None,
+            ));
+        }
+
+        self.watch(path)?;
+
+        // Event paths are absolute, so the directory has to be as well.
+        let abs_path = match env::current_dir() {
+            Ok(current_dir) => current_dir.join(path),
             Err(error) => {
-                // CHANGE THIS TO ERROR INSTEAD OF PANIC.
-                panic!(
-                    "watcher attempted to read-lock a poisoned lock on the \
-                    tracked stale assets. Error: {:?}",
-                    error
-                );
+                return Err(AssetsWatcherError::new(
+                    "current working directory cannot be used",
+                    AssetsWatcherErrorKind::WatchFailed,
+                    Some(Box::new(error)),
+                ))
             }
         };
+        self.watched_directories.push(WatchedDirectory {
+            path: abs_path,
+            filter,
+        });
 
-        let mut paths: Vec<PathBuf> = vec![];
-        for path in lock_guard.iter() {
-            paths.push(path.clone());
-        }
+        Ok(())
+    }
+
+    pub fn backend(&self) -> WatcherBackend {
+        self.backend
+    }
+
+    pub fn debounce_window(&self) -> Duration {
+        self.debounce_window
+    }
 
-        paths
+    // Returns the changes whose paths have settled since the last poll. Each path is reported
+    // at most once per poll, with its events coalesced.
+    pub fn poll_events(&self) -> Result<Vec<AssetEvent>, AssetsWatcherError> {
+        match self.event_queue.lock() {
+            Ok(mut event_queue) => Ok(event_queue
+                .take_settled_events(self.debounce_window)
+                .into_iter()
+                .filter_map(|event| self.filter_event(event))
+                .collect()),
+            Err(_) => Err(AssetsWatcherError::new(
+                "watcher attempted to lock a poisoned queue of asset events",
+                AssetsWatcherErrorKind::EventQueuePoisoned,
+                None,
+            )),
+        }
     }
 
-    pub fn clear_stale_paths(&self) {
-        let mut lock_guard = match self.stale_paths.write() {
-            Ok(lock_guard) => lock_guard,
+    fn watch(&mut self, path: &Path) -> Result<(), AssetsWatcherError> {
+        match self.watcher.watch(path, notify::RecursiveMode::Recursive) {
+            Ok(_) => Ok(()),
             Err(error) => {
-                // CHANGE THIS TO ERROR INSTEAD OF PANIC.
-                panic!(
-                    "watcher attempted to read-lock a poisoned lock on the \
-                    tracked stale assets. Error: {:?}",
-                    error
-                );
+                let kind = match error.kind {
+                    notify::ErrorKind::PathNotFound => AssetsWatcherErrorKind::PathNotFound,
+                    notify::ErrorKind::Io(ref io_error)
+                        if io_error.kind() == std::io::ErrorKind::NotFound =>
+                    {
+                        AssetsWatcherErrorKind::PathNotFound
+                    }
+                    _ => AssetsWatcherErrorKind::WatchFailed,
+                };
+
+                Err(AssetsWatcherError::new(
+                    format!("unable to watch {}", path.to_string_lossy()),
+                    kind,
+                    Some(Box::new(error)),
+                ))
             }
-        };
+        }
+    }
+
+    // Paths inside watched directories have to pass the filter of the innermost directory they
+    // are in. Other paths were explicitly added, so they always pass. A rename across the
+    // boundary of a filter becomes a creation or a removal.
+    fn filter_event(&self, event: AssetEvent) -> Option<AssetEvent> {
+        match event {
+            AssetEvent::Renamed { from, to } => {
+                match (self.is_path_watched(&from), self.is_path_watched(&to)) {
+                    (true, true) => Some(AssetEvent::Renamed { from, to }),
+                    (true, false) => Some(AssetEvent::Removed(from)),
+                    (false, true) => Some(AssetEvent::Created(to)),
+                    (false, false) => None,
+                }
+            }
+            event => match self.is_path_watched(event.path()) {
+                true => Some(event),
+                false => None,
+            },
+        }
+    }
+
+    fn is_path_watched(&self, path: &Path) -> bool {
+        let innermost_directory = self
+            .watched_directories
+            .iter()
+            .filter(|directory| path.starts_with(directory.path.as_path()))
+            .max_by_key(|directory| directory.path.components().count());
+
+        match innermost_directory {
+            Some(directory) => match path.strip_prefix(directory.path.as_path()) {
+                Ok(relative_path) => directory.filter.matches(relative_path),
+                Err(_) => false,
+            },
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modified(path: &str) -> AssetEvent {
+        AssetEvent::Modified(PathBuf::from(path))
+    }
+
+    fn created(path: &str) -> AssetEvent {
+        AssetEvent::Created(PathBuf::from(path))
+    }
+
+    fn removed(path: &str) -> AssetEvent {
+        AssetEvent::Removed(PathBuf::from(path))
+    }
+
+    fn renamed(from: &str, to: &str) -> AssetEvent {
+        AssetEvent::Renamed {
+            from: PathBuf::from(from),
+            to: PathBuf::from(to),
+        }
+    }
+
+    #[test]
+    fn keeps_creations_that_are_then_modified() {
+        assert_eq!(
+            AssetEvent::coalesce(created("a.png"), modified("a.png")),
+            Some(created("a.png"))
+        );
+    }
+
+    #[test]
+    fn drops_creations_that_are_then_removed() {
+        assert_eq!(
+            AssetEvent::coalesce(created("a.png"), removed("a.png")),
+            None
+        );
+    }
+
+    #[test]
+    fn reads_atomic_saves_as_modifications() {
+        assert_eq!(
+            AssetEvent::coalesce(removed("a.png"), created("a.png")),
+            Some(modified("a.png"))
+        );
+        assert_eq!(
+            AssetEvent::coalesce(removed("a.png"), modified("a.png")),
+            Some(modified("a.png"))
+        );
+        assert_eq!(
+            AssetEvent::coalesce(modified("a.png"), created("a.png")),
+            Some(modified("a.png"))
+        );
+    }
+
+    #[test]
+    fn keeps_renames_that_are_then_written_to() {
+        assert_eq!(
+            AssetEvent::coalesce(renamed("a.png", "b.png"), modified("b.png")),
+            Some(renamed("a.png", "b.png"))
+        );
+        assert_eq!(
+            AssetEvent::coalesce(renamed("a.png", "b.png"), created("b.png")),
+            Some(renamed("a.png", "b.png"))
+        );
+    }
+
+    #[test]
+    fn takes_the_new_event_otherwise() {
+        assert_eq!(
+            AssetEvent::coalesce(modified("a.png"), removed("a.png")),
+            Some(removed("a.png"))
+        );
+        assert_eq!(
+            AssetEvent::coalesce(modified("a.png"), modified("a.png")),
+            Some(modified("a.png"))
+        );
+    }
+
+    #[test]
+    fn coalesces_queued_events_per_path() {
+        let mut event_queue = EventQueue::default();
+        event_queue.push(modified("a.png"));
+        event_queue.push(created("b.png"));
+        event_queue.push(removed("b.png"));
+        event_queue.push(created("a.png"));
+        event_queue.push(renamed("a.png", "c.png"));
 
-        lock_guard.clear();
+        assert_eq!(
+            event_queue.take_settled_events(Duration::ZERO),
+            vec![renamed("a.png", "c.png")]
+        );
+        assert!(event_queue.take_settled_events(Duration::ZERO).is_empty());
     }
 }
@@ -0,0 +1,114 @@
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::assets::{AssetManagerError, AssetManagerErrorKind};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+// A fixed set of threads that run jobs off the render thread, such as decoding assets. Clones
+// share the same threads, which finish the jobs still queued and stop once the last clone is
+// dropped.
+#[derive(Clone)]
+pub struct WorkerPool {
+    shared: Arc<WorkerPoolShared>,
+}
+
+struct WorkerPoolShared {
+    job_sender: Mutex<Option<mpsc::Sender<Job>>>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl Drop for WorkerPoolShared {
+    fn drop(&mut self) {
+        // Closing the channel makes the workers exit once they have emptied it.
+        if let Ok(job_sender) = self.job_sender.get_mut() {
+            job_sender.take();
+        }
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl WorkerPool {
+    pub fn new(num_workers: usize) -> Result<Self, AssetManagerError> {
+        let (job_sender, job_receiver) = mpsc::channel::<Job>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let mut workers: Vec<thread::JoinHandle<()>> = vec![];
+        for worker_index in 0..num_workers.max(1) {
+            let job_receiver = Arc::clone(&job_receiver);
+            let worker = thread::Builder::new()
+                .name(format!("asset-worker-{}", worker_index))
+                .spawn(move || loop {
+                    let job = match job_receiver.lock() {
+                        Ok(job_receiver) => job_receiver.recv(),
+                        Err(_) => break,
+                    };
+
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                });
+
+            match worker {
+                Ok(worker) => workers.push(worker),
+                Err(error) => {
+                    return Err(AssetManagerError::new(
+                        "unable to spawn an asset worker thread",
+                        AssetManagerErrorKind::WorkerPoolError,
+                        Some(Box::new(error)),
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            shared: Arc::new(WorkerPoolShared {
+                job_sender: Mutex::new(Some(job_sender)),
+                workers,
+            }),
+        })
+    }
+
+    // Leaves one core for the render thread.
+    pub fn default_num_workers() -> usize {
+        match thread::available_parallelism() {
+            Ok(parallelism) => parallelism.get().saturating_sub(1).max(1),
+            Err(_) => 1,
+        }
+    }
+
+    pub fn num_workers(&self) -> usize {
+        self.shared.workers.len()
+    }
+
+    pub fn execute<F: FnOnce() + Send + 'static>(&self, job: F) -> Result<(), AssetManagerError> {
+        let job_sender = match self.shared.job_sender.lock() {
+            Ok(job_sender) => job_sender,
+            Err(_) => {
+                return Err(AssetManagerError::new(
+                    "job queue of the worker pool is poisoned",
+                    AssetManagerErrorKind::WorkerPoolError,
+                    None,
+                ))
+            }
+        };
+
+        let is_sent = match job_sender.as_ref() {
+            Some(job_sender) => job_sender.send(Box::new(job)).is_ok(),
+            None => false,
+        };
+        match is_sent {
+            true => Ok(()),
+            false => Err(AssetManagerError::new(
+                "worker pool is no longer running",
+                AssetManagerErrorKind::WorkerPoolError,
+                None,
+            )),
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum CommandErrorKind {
+    ParseError,
+    UnknownCommand,
+    MissingArgument,
+    TooManyArguments,
+    InvalidArgument,
+    ExecutionFailed,
+}
+
+#[derive(Debug)]
+pub struct CommandError {
+    source: Option<Box<dyn Error + 'static>>,
+    message: String,
+    kind: CommandErrorKind,
+}
+
+impl CommandError {
+    pub fn new(
+        message: impl AsRef<str>,
+        kind: CommandErrorKind,
+        source: Option<Box<dyn Error + 'static>>,
+    ) -> CommandError {
+        CommandError {
+            source,
+            message: message.as_ref().into(),
+            kind,
+        }
+    }
+
+    pub fn kind(&self) -> CommandErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for CommandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ArgKind {
+    String,
+    Int,
+    Float,
+    Bool,
+}
+
+impl ArgKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Int => "integer",
+            Self::Float => "number",
+            Self::Bool => "boolean",
+        }
+    }
+
+    pub fn parse(&self, text: &str) -> Option<ArgValue> {
+        match self {
+            Self::String => Some(ArgValue::String(String::from(text))),
+            Self::Int => text.parse().ok().map(ArgValue::Int),
+            Self::Float => text.parse().ok().map(ArgValue::Float),
+            Self::Bool => match text.to_ascii_lowercase().as_str() {
+                "true" | "on" | "yes" | "1" => Some(ArgValue::Bool(true)),
+                "false" | "off" | "no" | "0" => Some(ArgValue::Bool(false)),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArgValue {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl ArgValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    // Integers are accepted too, since they are numbers as well.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Int(value) => Some(*value as f64),
+            Self::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ArgArity {
+    Required,
+    Optional,
+    // One or more values. Only the last argument of a command can be variadic.
+    Variadic,
+}
+
+type ArgCompleter<C> = Box<dyn Fn(&C) -> Vec<String>>;
+
+pub struct CommandArg<C> {
+    name: String,
+    kind: ArgKind,
+    arity: ArgArity,
+    completer: Option<ArgCompleter<C>>,
+}
+
+impl<C> CommandArg<C> {
+    pub fn new<S: AsRef<str>>(name: S, kind: ArgKind) -> Self {
+        Self {
+            name: String::from(name.as_ref()),
+            kind,
+            arity: ArgArity::Required,
+            completer: None,
+        }
+    }
+
+    pub fn optional(mut self) -> Self {
+        self.arity = ArgArity::Optional;
+        self
+    }
+
+    pub fn variadic(mut self) -> Self {
+        self.arity = ArgArity::Variadic;
+        self
+    }
+
+    // Sets where tab completion gets the possible values of the argument from, e.g. the IDs of
+    // the loaded assets.
+    pub fn completer<F: Fn(&C) -> Vec<String> + 'static>(mut self, completer: F) -> Self {
+        self.completer = Some(Box::new(completer));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn kind(&self) -> ArgKind {
+        self.kind
+    }
+
+    pub fn arity(&self) -> ArgArity {
+        self.arity
+    }
+
+    pub fn complete(&self, context: &C) -> Vec<String> {
+        match &self.completer {
+            Some(completer) => completer(context),
+            None => match self.kind {
+                ArgKind::Bool => vec![String::from("false"), String::from("true")],
+                _ => vec![],
+            },
+        }
+    }
+
+    // E.g. "<asset-id>", "[filter]" or "<value>...".
+    pub fn usage(&self) -> String {
+        match self.arity {
+            ArgArity::Required => format!("<{}>", self.name),
+            ArgArity::Optional => format!("[{}]", self.name),
+            ArgArity::Variadic => format!("<{}>...", self.name),
+        }
+    }
+}
+
+// The values of the arguments a command was called with, by argument name.
+#[derive(Clone, Debug, Default)]
+pub struct CommandArgs {
+    values: HashMap<String, Vec<ArgValue>>,
+}
+
+impl CommandArgs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push<S: AsRef<str>>(&mut self, name: S, value: ArgValue) {
+        self.values
+            .entry(String::from(name.as_ref()))
+            .or_default()
+            .push(value);
+    }
+
+    // Returns the first value of the argument, or None if an optional argument was omitted.
+    pub fn get<S: AsRef<str>>(&self, name: S) -> Option<&ArgValue> {
+        self.get_all(name).first()
+    }
+
+    // Returns every value of a variadic argument.
+    pub fn get_all<S: AsRef<str>>(&self, name: S) -> &[ArgValue] {
+        match self.values.get(name.as_ref()) {
+            Some(values) => values.as_slice(),
+            None => &[],
+        }
+    }
+
+    pub fn get_str<S: AsRef<str>>(&self, name: S) -> Option<&str> {
+        self.get(name).and_then(ArgValue::as_str)
+    }
+
+    pub fn get_int<S: AsRef<str>>(&self, name: S) -> Option<i64> {
+        self.get(name).and_then(ArgValue::as_int)
+    }
+
+    pub fn get_float<S: AsRef<str>>(&self, name: S) -> Option<f64> {
+        self.get(name).and_then(ArgValue::as_float)
+    }
+
+    pub fn get_bool<S: AsRef<str>>(&self, name: S) -> Option<bool> {
+        self.get(name).and_then(ArgValue::as_bool)
+    }
+}
+
+// Handlers get the context the commands run in (e.g. the application state), and return the
+// lines to print to the console.
+type CommandHandler<C> = Box<dyn FnMut(&mut C, &CommandArgs) -> Result<Vec<String>, CommandError>>;
+
+pub struct Command<C> {
+    name: String,
+    description: String,
+    args: Vec<CommandArg<C>>,
+    handler: CommandHandler<C>,
+}
+
+impl<C> Command<C> {
+    pub fn new<
+        S: AsRef<str>,
+        D: AsRef<str>,
+        F: FnMut(&mut C, &CommandArgs) -> Result<Vec<String>, CommandError> + 'static,
+    >(
+        name: S,
+        description: D,
+        handler: F,
+    ) -> Self {
+        Self {
+            name: String::from(name.as_ref()),
+            description: String::from(description.as_ref()),
+            args: vec![],
+            handler: Box::new(handler),
+        }
+    }
+
+    // Arguments are matched in the order they are added. Optional arguments should come after
+    // the required ones, and a variadic argument last.
+    pub fn arg(mut self, arg: CommandArg<C>) -> Self {
+        self.args.push(arg);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn description(&self) -> &str {
+        self.description.as_str()
+    }
+
+    pub fn args(&self) -> &[CommandArg<C>] {
+        self.args.as_slice()
+    }
+
+    // E.g. "reload <asset-id>".
+    pub fn usage(&self) -> String {
+        let mut usage = self.name.clone();
+        for arg in &self.args {
+            usage.push(' ');
+            usage.push_str(arg.usage().as_str());
+        }
+
+        usage
+    }
+
+    // Returns the argument that the word at the given position (0 being the first word after
+    // the command name) is a value of.
+    pub fn get_arg_at(&self, position: usize) -> Option<&CommandArg<C>> {
+        match self.args.get(position) {
+            Some(arg) => Some(arg),
+            None => self
+                .args
+                .last()
+                .filter(|arg| arg.arity() == ArgArity::Variadic),
+        }
+    }
+
+    pub fn run(&mut self, context: &mut C, words: &[String]) -> Result<Vec<String>, CommandError> {
+        let args = self.parse_args(words)?;
+        (self.handler)(context, &args)
+    }
+
+    fn parse_args(&self, words: &[String]) -> Result<CommandArgs, CommandError> {
+        let mut args = CommandArgs::new();
+        let mut words = words.iter();
+        for arg in &self.args {
+            let num_values = match (arg.arity(), words.len()) {
+                (ArgArity::Required | ArgArity::Variadic, 0) => {
+                    return Err(CommandError::new(
+                        format!(
+                            "missing argument, {}, of command, \"{}\". Usage: {}",
+                            arg.usage(),
+                            self.name,
+                            self.usage()
+                        ),
+                        CommandErrorKind::MissingArgument,
+                        None,
+                    ))
+                }
+                (ArgArity::Variadic, num_words) => num_words,
+                (_, 0) => 0,
+                (_, _) => 1,
+            };
+
+            for word in words.by_ref().take(num_values) {
+                match arg.kind().parse(word) {
+                    Some(value) => args.push(arg.name(), value),
+                    None => {
+                        return Err(CommandError::new(
+                            format!(
+                                "argument, {}, of command, \"{}\", must be a {}, but is \"{}\"",
+                                arg.usage(),
+                                self.name,
+                                arg.kind().name(),
+                                word
+                            ),
+                            CommandErrorKind::InvalidArgument,
+                            None,
+                        ))
+                    }
+                }
+            }
+        }
+
+        if words.len() > 0 {
+            return Err(CommandError::new(
+                format!(
+                    "too many arguments given to command, \"{}\". Usage: {}",
+                    self.name,
+                    self.usage()
+                ),
+                CommandErrorKind::TooManyArguments,
+                None,
+            ));
+        }
+
+        Ok(args)
+    }
+}
@@ -0,0 +1,71 @@
+// Previously run command lines, browsed with the up and down keys like in a shell. The line
+// being typed is kept aside while browsing, and comes back once the newest entry is passed.
+#[derive(Clone, Debug)]
+pub struct CommandHistory {
+    entries: Vec<String>,
+    max_num_entries: usize,
+    // Index of the entry being shown, or None while not browsing.
+    cursor: Option<usize>,
+    draft: String,
+}
+
+impl CommandHistory {
+    pub fn new(max_num_entries: usize) -> Self {
+        Self {
+            entries: vec![],
+            max_num_entries,
+            cursor: None,
+            draft: String::new(),
+        }
+    }
+
+    // Stops browsing. Repeating the last entry does not add it again.
+    pub fn push<S: AsRef<str>>(&mut self, line: S) {
+        self.cursor = None;
+        self.draft.clear();
+
+        let line = line.as_ref();
+        if line.trim().is_empty() || self.entries.last().map(String::as_str) == Some(line) {
+            return;
+        }
+
+        self.entries.push(String::from(line));
+        if self.entries.len() > self.max_num_entries {
+            self.entries.remove(0);
+        }
+    }
+
+    // Returns the entry before the one being shown, or None if there is none. `current_line`
+    // is what is being typed, which is restored by next_entry() afterwards.
+    pub fn previous_entry(&mut self, current_line: &str) -> Option<&str> {
+        let index = match self.cursor {
+            Some(0) => return None,
+            Some(index) => index - 1,
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = String::from(current_line);
+                self.entries.len() - 1
+            }
+        };
+        self.cursor = Some(index);
+
+        Some(self.entries[index].as_str())
+    }
+
+    // Returns the entry after the one being shown, or the line that was being typed once the
+    // newest entry is passed. Returns None while not browsing.
+    pub fn next_entry(&mut self) -> Option<&str> {
+        let index = self.cursor?;
+        if index + 1 < self.entries.len() {
+            self.cursor = Some(index + 1);
+            Some(self.entries[index + 1].as_str())
+        } else {
+            self.cursor = None;
+            Some(self.draft.as_str())
+        }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        self.entries.as_slice()
+    }
+}
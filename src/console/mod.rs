@@ -0,0 +1,9 @@
+pub mod command;
+pub mod history;
+pub mod registry;
+pub mod tokenizer;
+
+pub use command::*;
+pub use history::*;
+pub use registry::*;
+pub use tokenizer::*;
@@ -0,0 +1,348 @@
+use std::collections::BTreeMap;
+
+use crate::console::{quote_word, tokenize, Command, CommandError, CommandErrorKind};
+
+// What the console sends to whoever runs its commands.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConsoleRequest {
+    Execute(String),
+    Complete(String),
+}
+
+// The result of tab-completing a command line. `line` is the completed line, and `candidates`
+// lists the possible completions when there is more than one.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Completion {
+    pub line: String,
+    pub candidates: Vec<String>,
+}
+
+// Commands that subsystems register to be run from the console, e.g. "reload <asset-id>". The
+// context is whatever the commands act on, and is given to them when they are run. "help" is
+// always available, and lists the registered commands.
+pub struct CommandRegistry<C> {
+    commands: BTreeMap<String, Command<C>>,
+}
+
+impl<C> CommandRegistry<C> {
+    const HELP_COMMAND_NAME: &'static str = "help";
+
+    pub fn new() -> Self {
+        Self {
+            commands: BTreeMap::new(),
+        }
+    }
+
+    // Replaces the command previously registered under the same name, if any.
+    pub fn register(&mut self, command: Command<C>) {
+        self.commands.insert(String::from(command.name()), command);
+    }
+
+    pub fn get_command<S: AsRef<str>>(&self, name: S) -> Option<&Command<C>> {
+        self.commands.get(name.as_ref())
+    }
+
+    // Sorted, and including "help".
+    pub fn command_names(&self) -> Vec<&str> {
+        let mut command_names: Vec<&str> = self.commands.keys().map(String::as_str).collect();
+        if !self.commands.contains_key(Self::HELP_COMMAND_NAME) {
+            command_names.push(Self::HELP_COMMAND_NAME);
+            command_names.sort();
+        }
+
+        command_names
+    }
+
+    // Parses and runs a command line, and returns the lines to print to the console. Empty
+    // lines do nothing.
+    pub fn execute(&mut self, context: &mut C, line: &str) -> Result<Vec<String>, CommandError> {
+        let words: Vec<String> = tokenize(line)?
+            .into_iter()
+            .map(|token| token.text)
+            .collect();
+        let (command_name, args) = match words.split_first() {
+            Some((command_name, args)) => (command_name, args),
+            None => return Ok(vec![]),
+        };
+
+        match self.commands.get_mut(command_name) {
+            Some(command) => command.run(context, args),
+            None if command_name == Self::HELP_COMMAND_NAME => self.help(args),
+            None => Err(Self::unknown_command_error(command_name)),
+        }
+    }
+
+    // Completes the last word of a command line, up to where the possible completions start to
+    // differ. Command names are completed first, then the arguments that have a completer (see
+    // CommandArg::completer()).
+    pub fn complete(&self, context: &C, line: &str) -> Completion {
+        let no_completion = Completion {
+            line: String::from(line),
+            candidates: vec![],
+        };
+
+        // Lines with an unterminated quote cannot be completed.
+        let mut tokens = match tokenize(line) {
+            Ok(tokens) => tokens,
+            Err(_) => return no_completion,
+        };
+
+        // A line ending with whitespace completes a new, empty word.
+        let ends_with_whitespace = line.ends_with(char::is_whitespace);
+        let (word, word_start) = match tokens.pop() {
+            Some(token) if !ends_with_whitespace => (token.text, token.start),
+            Some(token) => {
+                tokens.push(token);
+                (String::new(), line.len())
+            }
+            None => (String::new(), line.len()),
+        };
+
+        let possible_values: Vec<String> = match tokens.split_first() {
+            None => self.command_names().into_iter().map(String::from).collect(),
+            Some((command_name, _)) if command_name.text == Self::HELP_COMMAND_NAME => {
+                match tokens.len() {
+                    1 => self.command_names().into_iter().map(String::from).collect(),
+                    _ => vec![],
+                }
+            }
+            Some((command_name, args)) => match self
+                .commands
+                .get(&command_name.text)
+                .and_then(|command| command.get_arg_at(args.len()))
+            {
+                Some(arg) => arg.complete(context),
+                None => vec![],
+            },
+        };
+
+        let mut candidates: Vec<String> = possible_values
+            .into_iter()
+            .filter(|value| value.starts_with(word.as_str()))
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+
+        let completed_word = match candidates.as_slice() {
+            [] => return no_completion,
+            [candidate] => format!("{} ", quote_word(candidate)),
+            _ => {
+                let common_prefix = Self::get_common_prefix(&candidates);
+                // Leave the word as typed if completing it would not add anything, so that its
+                // quotes are kept.
+                if common_prefix.len() <= word.len() {
+                    return Completion {
+                        line: String::from(line),
+                        candidates,
+                    };
+                }
+                quote_word(common_prefix)
+            }
+        };
+
+        let mut completed_line = String::from(&line[..word_start]);
+        completed_line.push_str(completed_word.as_str());
+        if candidates.len() == 1 {
+            candidates.clear();
+        }
+
+        Completion {
+            line: completed_line,
+            candidates,
+        }
+    }
+
+    fn help(&self, args: &[String]) -> Result<Vec<String>, CommandError> {
+        match args {
+            [] => {
+                let mut lines: Vec<String> = vec![String::from("Available commands:")];
+                lines.push(format!(
+                    "    {} [command] - Lists the commands, or describes one of them.",
+                    Self::HELP_COMMAND_NAME
+                ));
+                for command in self.commands.values() {
+                    lines.push(format!(
+                        "    {} - {}",
+                        command.usage(),
+                        command.description()
+                    ));
+                }
+
+                Ok(lines)
+            }
+            [command_name] => match self.commands.get(command_name) {
+                Some(command) => Ok(vec![
+                    format!("Usage: {}", command.usage()),
+                    format!("    {}", command.description()),
+                ]),
+                None if command_name == Self::HELP_COMMAND_NAME => Ok(vec![
+                    format!("Usage: {} [command]", Self::HELP_COMMAND_NAME),
+                    String::from("    Lists the commands, or describes one of them."),
+                ]),
+                None => Err(Self::unknown_command_error(command_name)),
+            },
+            _ => Err(CommandError::new(
+                format!(
+                    "too many arguments given to command, \"{}\". Usage: {} [command]",
+                    Self::HELP_COMMAND_NAME,
+                    Self::HELP_COMMAND_NAME
+                ),
+                CommandErrorKind::TooManyArguments,
+                None,
+            )),
+        }
+    }
+
+    fn get_common_prefix(values: &[String]) -> &str {
+        let first_value: &str = match values.first() {
+            Some(value) => value.as_str(),
+            None => return "",
+        };
+
+        let mut prefix_len = first_value.len();
+        for value in &values[1..] {
+            prefix_len = first_value
+                .char_indices()
+                .zip(value.chars())
+                .take_while(|((_, a), b)| a == b)
+                .last()
+                .map(|((index, c), _)| index + c.len_utf8())
+                .unwrap_or(0)
+                .min(prefix_len);
+        }
+
+        &first_value[..prefix_len]
+    }
+
+    fn unknown_command_error(command_name: &str) -> CommandError {
+        CommandError::new(
+            format!(
+                "unknown command, \"{}\". Type \"help\" to list the available commands",
+                command_name
+            ),
+            CommandErrorKind::UnknownCommand,
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::{ArgKind, CommandArg};
+
+    fn create_registry() -> CommandRegistry<()> {
+        let mut registry = CommandRegistry::new();
+        for name in ["reload", "reload-all", "remove"] {
+            registry.register(
+                Command::new(name, "Does nothing.", |_: &mut (), _| Ok(vec![])).arg(
+                    CommandArg::new("asset-id", ArgKind::String)
+                        .optional()
+                        .completer(|_: &()| {
+                            vec![
+                                String::from("shaders/a b.vert"),
+                                String::from("shaders/b.vert"),
+                                String::from("textures/c.png"),
+                            ]
+                        }),
+                ),
+            );
+        }
+
+        registry
+    }
+
+    fn complete(line: &str) -> Completion {
+        create_registry().complete(&(), line)
+    }
+
+    #[test]
+    fn completes_unique_command_name() {
+        assert_eq!(
+            complete("rem"),
+            Completion {
+                line: String::from("remove "),
+                candidates: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn completes_common_prefix_of_command_names() {
+        assert_eq!(
+            complete("rel"),
+            Completion {
+                line: String::from("reload"),
+                candidates: vec![String::from("reload"), String::from("reload-all")],
+            }
+        );
+    }
+
+    #[test]
+    fn leaves_line_as_is_without_longer_prefix() {
+        assert_eq!(
+            complete("re"),
+            Completion {
+                line: String::from("re"),
+                candidates: vec![
+                    String::from("reload"),
+                    String::from("reload-all"),
+                    String::from("remove"),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn lists_every_command_for_empty_line() {
+        assert_eq!(
+            complete("").candidates,
+            vec!["help", "reload", "reload-all", "remove"]
+        );
+    }
+
+    #[test]
+    fn completes_arguments_with_their_completer() {
+        assert_eq!(complete("reload t").line, "reload textures/c.png ");
+        assert_eq!(
+            complete("reload shaders/").candidates,
+            vec!["shaders/a b.vert", "shaders/b.vert"]
+        );
+    }
+
+    #[test]
+    fn quotes_completed_arguments() {
+        assert_eq!(
+            complete("reload shaders/a").line,
+            r#"reload "shaders/a b.vert" "#
+        );
+        assert_eq!(
+            complete(r#"reload "shaders/a""#).line,
+            r#"reload "shaders/a b.vert" "#
+        );
+    }
+
+    #[test]
+    fn does_not_complete_unterminated_quotes() {
+        assert_eq!(
+            complete(r#"reload "shaders/a"#).line,
+            r#"reload "shaders/a"#
+        );
+    }
+
+    #[test]
+    fn completes_command_names_after_help() {
+        assert_eq!(complete("help rem").line, "help remove ");
+        assert_eq!(complete("help remove r").candidates, Vec::<String>::new());
+    }
+
+    #[test]
+    fn does_not_complete_past_the_last_argument() {
+        assert_eq!(complete("reload a.vert t").line, "reload a.vert t");
+    }
+
+    #[test]
+    fn does_not_complete_unknown_commands() {
+        assert_eq!(complete("unknown t").line, "unknown t");
+    }
+}
@@ -0,0 +1,181 @@
+use crate::console::{CommandError, CommandErrorKind};
+
+// A word of a command line, with its quotes and escapes resolved. `start` is the byte offset at
+// which the word begins in the line, quotes included.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Token {
+    pub text: String,
+    pub start: usize,
+}
+
+// Splits a command line into words. Words are separated by whitespace, unless it is quoted
+// with "double" or 'single' quotes, or escaped with a backslash. Backslashes escape the next
+// character everywhere but inside single quotes.
+pub fn tokenize(line: &str) -> Result<Vec<Token>, CommandError> {
+    let mut tokens: Vec<Token> = vec![];
+    let mut current_token: Option<Token> = None;
+    let mut quote: Option<char> = None;
+
+    let mut chars = line.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => {
+                if let Some(token) = current_token.take() {
+                    tokens.push(token);
+                }
+                continue;
+            }
+            (Some(quote_char), c) if c == quote_char => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None | Some('"'), '\\') => {
+                let escaped_char = match chars.next() {
+                    Some((_, escaped_char)) => escaped_char,
+                    None => {
+                        return Err(CommandError::new(
+                            "command line ends with an unescaped backslash",
+                            CommandErrorKind::ParseError,
+                            None,
+                        ))
+                    }
+                };
+                start_token(&mut current_token, index)
+                    .text
+                    .push(escaped_char);
+                continue;
+            }
+            (_, c) => {
+                start_token(&mut current_token, index).text.push(c);
+                continue;
+            }
+        }
+
+        // Quotes start a word too, so that "" is an empty word.
+        start_token(&mut current_token, index);
+    }
+
+    if let Some(quote_char) = quote {
+        return Err(CommandError::new(
+            format!("command line has an unterminated {} quote", quote_char),
+            CommandErrorKind::ParseError,
+            None,
+        ));
+    }
+
+    if let Some(token) = current_token {
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+// Quotes a word if it would not be read back as a single word otherwise.
+pub fn quote_word(word: &str) -> String {
+    let needs_quotes = word.is_empty()
+        || word
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\'));
+    if !needs_quotes {
+        return String::from(word);
+    }
+
+    let mut quoted_word = String::from("\"");
+    for c in word.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted_word.push('\\');
+        }
+        quoted_word.push(c);
+    }
+    quoted_word.push('"');
+
+    quoted_word
+}
+
+fn start_token(current_token: &mut Option<Token>, start: usize) -> &mut Token {
+    current_token.get_or_insert_with(|| Token {
+        text: String::new(),
+        start,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(line: &str) -> Vec<String> {
+        tokenize(line)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.text)
+            .collect()
+    }
+
+    #[test]
+    fn splits_on_whitespace() {
+        let tokens = tokenize("  reload \tshaders/a.vert ").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token {
+                    text: String::from("reload"),
+                    start: 2,
+                },
+                Token {
+                    text: String::from("shaders/a.vert"),
+                    start: 10,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_quoted_whitespace() {
+        assert_eq!(words(r#"set "a b" 'c  d'"#), vec!["set", "a b", "c  d"]);
+        assert_eq!(tokenize(r#"set "a b""#).unwrap()[1].start, 4);
+    }
+
+    #[test]
+    fn joins_quoted_parts_of_a_word() {
+        assert_eq!(words(r#"a"b c"'d'"#), vec!["ab cd"]);
+    }
+
+    #[test]
+    fn reads_empty_quotes_as_an_empty_word() {
+        assert_eq!(words(r#"set "" ''"#), vec!["set", "", ""]);
+    }
+
+    #[test]
+    fn resolves_escapes_outside_of_single_quotes() {
+        assert_eq!(words(r"a\ b"), vec!["a b"]);
+        assert_eq!(words(r#""a\"b\\""#), vec![r#"a"b\"#]);
+        assert_eq!(words(r"'a\b'"), vec![r"a\b"]);
+    }
+
+    #[test]
+    fn rejects_unterminated_quotes() {
+        for line in [r#"set "a b"#, "set 'a b"] {
+            let error = tokenize(line).unwrap_err();
+            assert_eq!(error.kind(), CommandErrorKind::ParseError);
+        }
+    }
+
+    #[test]
+    fn rejects_trailing_backslash() {
+        let error = tokenize(r"set a\").unwrap_err();
+        assert_eq!(error.kind(), CommandErrorKind::ParseError);
+    }
+
+    #[test]
+    fn quotes_words_that_would_be_split() {
+        assert_eq!(quote_word("plain"), "plain");
+        assert_eq!(quote_word(""), r#""""#);
+        assert_eq!(quote_word("a b"), r#""a b""#);
+        assert_eq!(quote_word(r#"a"b\"#), r#""a\"b\\""#);
+    }
+
+    #[test]
+    fn reads_quoted_words_back() {
+        for word in ["plain", "", "a b", r#"a"b\"#, "it's"] {
+            assert_eq!(words(quote_word(word).as_str()), vec![word]);
+        }
+    }
+}
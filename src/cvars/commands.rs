@@ -0,0 +1,160 @@
+use crate::console::{ArgKind, Command, CommandArg, CommandError, CommandErrorKind};
+use crate::console::{CommandArgs, CommandRegistry};
+use crate::cvars::{CvarError, CvarInfo, CvarRegistry, CvarSource};
+
+// Registers "cvar <name> [value]", "reset-cvar <name>", "list-cvars [filter]", and
+// "save-cvars". The commands reach the cvars through their context.
+pub fn register_cvar_commands<C>(commands: &mut CommandRegistry<C>)
+where
+    C: AsRef<CvarRegistry> + AsMut<CvarRegistry> + 'static,
+{
+    commands.register(
+        Command::new(
+            "cvar",
+            "Shows a cvar, or sets it to the value. The components of vectors are separated by \
+            commas, e.g. 0.14,0.14,0.14.",
+            show_or_set_cvar,
+        )
+        .arg(CommandArg::new("name", ArgKind::String).completer(get_cvar_names))
+        .arg(CommandArg::new("value", ArgKind::String).optional()),
+    );
+    commands.register(
+        Command::new(
+            "reset-cvar",
+            "Sets a cvar back to its default value.",
+            reset_cvar,
+        )
+        .arg(CommandArg::new("name", ArgKind::String).completer(get_cvar_names)),
+    );
+    commands.register(
+        Command::new(
+            "list-cvars",
+            "Lists the cvars, or the ones whose name contains the filter.",
+            list_cvars,
+        )
+        .arg(CommandArg::new("filter", ArgKind::String).optional()),
+    );
+    commands.register(Command::new(
+        "save-cvars",
+        "Saves the cvars to the config file. They are saved on exit as well.",
+        save_cvars,
+    ));
+}
+
+fn show_or_set_cvar<C: AsRef<CvarRegistry> + AsMut<CvarRegistry>>(
+    context: &mut C,
+    args: &CommandArgs,
+) -> Result<Vec<String>, CommandError> {
+    let name = args.get_str("name").unwrap_or_default();
+    let cvars = context.as_mut();
+
+    if let Some(text) = args.get_str("value") {
+        if let Err(error) = cvars.set_value_from_text(name, text) {
+            return Err(to_command_error(
+                format!("unable to set cvar, \"{}\"", name),
+                error,
+            ));
+        }
+    }
+
+    match cvars.get_info(name) {
+        Some(info) => Ok(vec![
+            describe_cvar(&info),
+            format!("    {}", info.description()),
+        ]),
+        None => Err(CommandError::new(
+            format!("there is no cvar named \"{}\"", name),
+            CommandErrorKind::InvalidArgument,
+            None,
+        )),
+    }
+}
+
+fn reset_cvar<C: AsMut<CvarRegistry>>(
+    context: &mut C,
+    args: &CommandArgs,
+) -> Result<Vec<String>, CommandError> {
+    let name = args.get_str("name").unwrap_or_default();
+    let cvars = context.as_mut();
+
+    match cvars.reset(name) {
+        Ok(_) => match cvars.get_info(name) {
+            Some(info) => Ok(vec![describe_cvar(&info)]),
+            None => Ok(vec![]),
+        },
+        Err(error) => Err(to_command_error(
+            format!("unable to reset cvar, \"{}\"", name),
+            error,
+        )),
+    }
+}
+
+fn list_cvars<C: AsRef<CvarRegistry>>(
+    context: &mut C,
+    args: &CommandArgs,
+) -> Result<Vec<String>, CommandError> {
+    let filter = args.get_str("filter").unwrap_or_default().to_lowercase();
+
+    let lines: Vec<String> = context
+        .as_ref()
+        .infos()
+        .iter()
+        .filter(|info| info.name().to_lowercase().contains(filter.as_str()))
+        .map(describe_cvar)
+        .collect();
+
+    match lines.is_empty() {
+        true => Ok(vec![String::from("No cvars found.")]),
+        false => Ok(lines),
+    }
+}
+
+fn save_cvars<C: AsRef<CvarRegistry>>(
+    context: &mut C,
+    _args: &CommandArgs,
+) -> Result<Vec<String>, CommandError> {
+    let cvars = context.as_ref();
+    match cvars.save_config_file() {
+        Ok(_) => Ok(vec![format!(
+            "Saved cvars to {}",
+            cvars.config_file_path().to_string_lossy()
+        )]),
+        Err(error) => Err(to_command_error("unable to save cvars", error)),
+    }
+}
+
+fn get_cvar_names<C: AsRef<CvarRegistry>>(context: &C) -> Vec<String> {
+    context
+        .as_ref()
+        .names()
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+// E.g. "render.clear_color = 0.14 0.14 0.14 (default: 0.14 0.14 0.14, range: [0, 1])".
+fn describe_cvar(info: &CvarInfo) -> String {
+    let mut description = format!(
+        "{} = {} (default: {}",
+        info.name(),
+        info.value(),
+        info.default_value()
+    );
+    if let Some((min, max)) = info.range() {
+        description.push_str(format!(", range: [{}, {}]", min, max).as_str());
+    }
+    if info.source() == CvarSource::CommandLine {
+        description.push_str(", set from the command line, not saved");
+    }
+    description.push(')');
+
+    description
+}
+
+fn to_command_error<S: AsRef<str>>(message: S, error: CvarError) -> CommandError {
+    CommandError::new(
+        message,
+        CommandErrorKind::ExecutionFailed,
+        Some(Box::new(error)),
+    )
+}
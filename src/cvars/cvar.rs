@@ -0,0 +1,500 @@
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum CvarErrorKind {
+    InvalidName,
+    AlreadyRegistered,
+    NotFound,
+    InvalidValue,
+    OutOfRange,
+    InvalidArgument,
+    ConfigReadError,
+    ConfigParseError,
+    ConfigWriteError,
+}
+
+#[derive(Debug)]
+pub struct CvarError {
+    source: Option<Box<dyn Error + 'static>>,
+    message: String,
+    kind: CvarErrorKind,
+}
+
+impl CvarError {
+    pub fn new(
+        message: impl AsRef<str>,
+        kind: CvarErrorKind,
+        source: Option<Box<dyn Error + 'static>>,
+    ) -> CvarError {
+        CvarError {
+            source,
+            message: message.as_ref().into(),
+            kind,
+        }
+    }
+
+    pub fn kind(&self) -> CvarErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for CvarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for CvarError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum CvarKind {
+    Bool,
+    Int,
+    Float,
+    String,
+    // A fixed number of floats, e.g. a colour.
+    Vector(usize),
+}
+
+impl CvarKind {
+    // E.g. "an integer", for error messages.
+    pub fn name(&self) -> String {
+        match self {
+            Self::Bool => String::from("a boolean"),
+            Self::Int => String::from("an integer"),
+            Self::Float => String::from("a number"),
+            Self::String => String::from("a string"),
+            Self::Vector(num_components) => format!("a vector of {} numbers", num_components),
+        }
+    }
+
+    // Parses a value as typed in the console or given in the command line. The components of
+    // vectors are separated by whitespace or commas, e.g. "0.14 0.14 0.14" or "0.14,0.14,0.14".
+    pub fn parse(&self, text: &str) -> Option<CvarValue> {
+        let text = text.trim();
+        match self {
+            Self::Bool => match text.to_ascii_lowercase().as_str() {
+                "true" | "on" | "yes" | "1" => Some(CvarValue::Bool(true)),
+                "false" | "off" | "no" | "0" => Some(CvarValue::Bool(false)),
+                _ => None,
+            },
+            Self::Int => text.parse().ok().map(CvarValue::Int),
+            Self::Float => text.parse().ok().map(CvarValue::Float),
+            Self::String => Some(CvarValue::String(String::from(text))),
+            Self::Vector(num_components) => {
+                let components: Option<Vec<f64>> = text
+                    .split(|c: char| c.is_whitespace() || c == ',')
+                    .filter(|component| !component.is_empty())
+                    .map(|component| component.parse().ok())
+                    .collect();
+                components
+                    .filter(|components| components.len() == *num_components)
+                    .map(CvarValue::Vector)
+            }
+        }
+    }
+
+    // Integers are accepted where floats are expected, but not the other way around.
+    pub fn parse_toml_value(&self, value: &toml::Value) -> Option<CvarValue> {
+        match (self, value) {
+            (Self::Bool, toml::Value::Boolean(value)) => Some(CvarValue::Bool(*value)),
+            (Self::Int, toml::Value::Integer(value)) => Some(CvarValue::Int(*value)),
+            (Self::Float, toml::Value::Integer(value)) => Some(CvarValue::Float(*value as f64)),
+            (Self::Float, toml::Value::Float(value)) => Some(CvarValue::Float(*value)),
+            (Self::String, toml::Value::String(value)) => Some(CvarValue::String(value.clone())),
+            (Self::Vector(num_components), toml::Value::Array(values))
+                if values.len() == *num_components =>
+            {
+                let components: Option<Vec<f64>> = values
+                    .iter()
+                    .map(|value| match value {
+                        toml::Value::Integer(value) => Some(*value as f64),
+                        toml::Value::Float(value) => Some(*value),
+                        _ => None,
+                    })
+                    .collect();
+                components.map(CvarValue::Vector)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CvarValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Vector(Vec<f64>),
+}
+
+impl CvarValue {
+    pub fn kind(&self) -> CvarKind {
+        match self {
+            Self::Bool(_) => CvarKind::Bool,
+            Self::Int(_) => CvarKind::Int,
+            Self::Float(_) => CvarKind::Float,
+            Self::String(_) => CvarKind::String,
+            Self::Vector(components) => CvarKind::Vector(components.len()),
+        }
+    }
+
+    // The numbers that a range limits. Booleans and strings have none.
+    pub fn numbers(&self) -> Vec<f64> {
+        match self {
+            Self::Int(value) => vec![*value as f64],
+            Self::Float(value) => vec![*value],
+            Self::Vector(components) => components.clone(),
+            Self::Bool(_) | Self::String(_) => vec![],
+        }
+    }
+
+    pub fn to_toml_value(&self) -> toml::Value {
+        match self {
+            Self::Bool(value) => toml::Value::Boolean(*value),
+            Self::Int(value) => toml::Value::Integer(*value),
+            Self::Float(value) => toml::Value::Float(*value),
+            Self::String(value) => toml::Value::String(value.clone()),
+            Self::Vector(components) => toml::Value::Array(
+                components
+                    .iter()
+                    .map(|component| toml::Value::Float(*component))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+// Shown the way it would be typed in the console.
+impl fmt::Display for CvarValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Int(value) => write!(f, "{}", value),
+            Self::Float(value) => write!(f, "{}", value),
+            Self::String(value) => write!(f, "\"{}\"", value),
+            Self::Vector(components) => {
+                let components: Vec<String> = components
+                    .iter()
+                    .map(|component| component.to_string())
+                    .collect();
+                write!(f, "{}", components.join(" "))
+            }
+        }
+    }
+}
+
+// Rust types that cvars can be read as.
+pub trait CvarType: Sized {
+    fn kind() -> CvarKind;
+    fn to_cvar_value(&self) -> CvarValue;
+    fn from_cvar_value(value: &CvarValue) -> Option<Self>;
+}
+
+impl CvarType for bool {
+    fn kind() -> CvarKind {
+        CvarKind::Bool
+    }
+
+    fn to_cvar_value(&self) -> CvarValue {
+        CvarValue::Bool(*self)
+    }
+
+    fn from_cvar_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+// Integers are kept as i64s, so only types that convert to an i64 without loss can be cvars, e.g.
+// not u64 or usize. The registry rejects values that do not fit the type.
+macro_rules! impl_int_cvar_type {
+    ($($type:ty),*) => {
+        $(
+            impl CvarType for $type {
+                fn kind() -> CvarKind {
+                    CvarKind::Int
+                }
+
+                fn to_cvar_value(&self) -> CvarValue {
+                    CvarValue::Int(i64::from(*self))
+                }
+
+                fn from_cvar_value(value: &CvarValue) -> Option<Self> {
+                    match value {
+                        CvarValue::Int(value) => (*value).try_into().ok(),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_int_cvar_type!(i32, i64, u32);
+
+impl CvarType for f32 {
+    fn kind() -> CvarKind {
+        CvarKind::Float
+    }
+
+    fn to_cvar_value(&self) -> CvarValue {
+        CvarValue::Float(f32_to_f64(*self))
+    }
+
+    fn from_cvar_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::Float(value) => Some(*value as f32),
+            _ => None,
+        }
+    }
+}
+
+impl CvarType for f64 {
+    fn kind() -> CvarKind {
+        CvarKind::Float
+    }
+
+    fn to_cvar_value(&self) -> CvarValue {
+        CvarValue::Float(*self)
+    }
+
+    fn from_cvar_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl CvarType for String {
+    fn kind() -> CvarKind {
+        CvarKind::String
+    }
+
+    fn to_cvar_value(&self) -> CvarValue {
+        CvarValue::String(self.clone())
+    }
+
+    fn from_cvar_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::String(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl<const N: usize> CvarType for [f32; N] {
+    fn kind() -> CvarKind {
+        CvarKind::Vector(N)
+    }
+
+    fn to_cvar_value(&self) -> CvarValue {
+        CvarValue::Vector(
+            self.iter()
+                .map(|component| f32_to_f64(*component))
+                .collect(),
+        )
+    }
+
+    fn from_cvar_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::Vector(components) if components.len() == N => {
+                let mut array = [0f32; N];
+                for (array_component, component) in array.iter_mut().zip(components) {
+                    *array_component = *component as f32;
+                }
+                Some(array)
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn f32_to_f64(value: f32) -> f64 {
+    value.to_string().parse().unwrap_or(value as f64)
+}
+
+pub type CvarCallback = Box<dyn FnMut(&CvarValue)>;
+
+// What a cvar gets registered with. The name is split into sections by dots, e.g.
+// "window.width", which become tables in the config file.
+pub struct CvarDefinition<T: CvarType> {
+    pub(crate) name: String,
+    pub(crate) description: String,
+    pub(crate) default_value: CvarValue,
+    pub(crate) range: Option<(f64, f64)>,
+    pub(crate) callbacks: Vec<CvarCallback>,
+    // Whether T can hold the value, e.g. that an integer fits a u32.
+    pub(crate) is_value_valid: fn(&CvarValue) -> bool,
+    phantom: PhantomData<T>,
+}
+
+impl<T: CvarType> CvarDefinition<T> {
+    pub fn new<S: AsRef<str>, D: AsRef<str>>(name: S, description: D, default_value: T) -> Self {
+        Self {
+            name: String::from(name.as_ref()),
+            description: String::from(description.as_ref()),
+            default_value: default_value.to_cvar_value(),
+            range: None,
+            callbacks: vec![],
+            is_value_valid: |value| T::from_cvar_value(value).is_some(),
+            phantom: PhantomData,
+        }
+    }
+
+    // Limits numbers, and every component of vectors, to [min, max]. Values outside of the
+    // range are rejected.
+    pub fn range(mut self, min: f64, max: f64) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+
+    // Called with the new value whenever the cvar is set to a different value.
+    pub fn on_change<F: FnMut(&CvarValue) + 'static>(mut self, callback: F) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+}
+
+// A typed handle to the value of a cvar. Handles can be cloned and sent to where the value is
+// needed, and always read the current value. Cvars are set through the registry, so that their
+// ranges are checked and their callbacks called.
+#[derive(Debug)]
+pub struct Cvar<T: CvarType> {
+    name: Arc<str>,
+    value: Arc<Mutex<CvarValue>>,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T: CvarType> Clone for Cvar<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            value: self.value.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: CvarType> Cvar<T> {
+    pub(crate) fn new(name: &str, value: Arc<Mutex<CvarValue>>) -> Self {
+        Self {
+            name: Arc::from(name),
+            value,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get(&self) -> T {
+        // The registry only stores values of the kind of T, so the conversion cannot fail.
+        let value = match self.value.lock() {
+            Ok(value) => value,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        match T::from_cvar_value(&value) {
+            Some(value) => value,
+            None => panic!("cvar, \"{}\", has a value of another type", self.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_booleans() {
+        for text in ["true", "On", " yes ", "1"] {
+            assert_eq!(CvarKind::Bool.parse(text), Some(CvarValue::Bool(true)));
+        }
+        for text in ["false", "OFF", "no", "0"] {
+            assert_eq!(CvarKind::Bool.parse(text), Some(CvarValue::Bool(false)));
+        }
+        assert_eq!(CvarKind::Bool.parse("maybe"), None);
+    }
+
+    #[test]
+    fn parses_numbers() {
+        assert_eq!(CvarKind::Int.parse(" -12 "), Some(CvarValue::Int(-12)));
+        assert_eq!(CvarKind::Int.parse("1.5"), None);
+        assert_eq!(CvarKind::Float.parse("1.5"), Some(CvarValue::Float(1.5)));
+        assert_eq!(CvarKind::Float.parse("2"), Some(CvarValue::Float(2.0)));
+        assert_eq!(CvarKind::Float.parse("fast"), None);
+    }
+
+    #[test]
+    fn parses_strings_as_is() {
+        assert_eq!(
+            CvarKind::String.parse(" shaders/a b.vert "),
+            Some(CvarValue::String(String::from("shaders/a b.vert")))
+        );
+    }
+
+    #[test]
+    fn parses_vectors_split_by_whitespace_or_commas() {
+        let color = Some(CvarValue::Vector(vec![0.25, 0.5, 1.0]));
+        assert_eq!(CvarKind::Vector(3).parse("0.25 0.5 1"), color);
+        assert_eq!(CvarKind::Vector(3).parse("0.25,0.5,1"), color);
+        assert_eq!(CvarKind::Vector(3).parse("0.25, 0.5,  1"), color);
+        assert_eq!(CvarKind::Vector(3).parse("0.25 0.5"), None);
+        assert_eq!(CvarKind::Vector(3).parse("0.25 0.5 white"), None);
+    }
+
+    #[test]
+    fn parses_toml_values() {
+        assert_eq!(
+            CvarKind::Float.parse_toml_value(&toml::Value::Integer(2)),
+            Some(CvarValue::Float(2.0))
+        );
+        assert_eq!(
+            CvarKind::Int.parse_toml_value(&toml::Value::Float(2.0)),
+            None
+        );
+        assert_eq!(
+            CvarKind::Vector(2).parse_toml_value(&toml::Value::Array(vec![
+                toml::Value::Integer(1),
+                toml::Value::Float(0.5)
+            ])),
+            Some(CvarValue::Vector(vec![1.0, 0.5]))
+        );
+        assert_eq!(
+            CvarKind::Vector(2)
+                .parse_toml_value(&toml::Value::Array(vec![toml::Value::Float(0.5)])),
+            None
+        );
+    }
+
+    #[test]
+    fn shows_values_the_way_they_are_typed() {
+        assert_eq!(CvarValue::Vector(vec![0.25, 1.0]).to_string(), "0.25 1");
+        assert_eq!(
+            CvarValue::String(String::from("a b")).to_string(),
+            "\"a b\""
+        );
+        assert_eq!(
+            CvarKind::Vector(2).parse(&CvarValue::Vector(vec![0.25, 1.0]).to_string()),
+            Some(CvarValue::Vector(vec![0.25, 1.0]))
+        );
+    }
+
+    #[test]
+    fn converts_floats_without_rounding_errors() {
+        assert_eq!(0.1f32.to_cvar_value(), CvarValue::Float(0.1));
+        assert_eq!(u32::from_cvar_value(&CvarValue::Int(-1)), None);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod commands;
+pub mod cvar;
+pub mod registry;
+
+pub use commands::*;
+pub use cvar::*;
+pub use registry::*;
@@ -0,0 +1,743 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::cvars::{
+    Cvar, CvarCallback, CvarDefinition, CvarError, CvarErrorKind, CvarKind, CvarType, CvarValue,
+};
+
+// Path of the config file that cvars are loaded from and saved to.
+pub const CONFIG_FILE_ENV_VAR: &str = "MULAY_GFX_CONFIG";
+pub const DEFAULT_CONFIG_FILE_PATH: &str = "mulay-gfx.toml";
+
+// Where the current value of a cvar came from.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum CvarSource {
+    Default,
+    ConfigFile,
+    // Set with "--set" in the command line. Such values only last for the current run, so they
+    // are not saved to the config file.
+    CommandLine,
+    Console,
+}
+
+struct CvarEntry {
+    description: String,
+    default_value: CvarValue,
+    range: Option<(f64, f64)>,
+    callbacks: Vec<CvarCallback>,
+    is_value_valid: fn(&CvarValue) -> bool,
+    value: Arc<Mutex<CvarValue>>,
+    source: CvarSource,
+    // What gets saved to the config file while the value comes from the command line, i.e. the
+    // value the cvar had before it was overridden.
+    saved_value: CvarValue,
+}
+
+impl CvarEntry {
+    fn get_value(&self) -> CvarValue {
+        match self.value.lock() {
+            Ok(value) => value.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    fn get_saved_value(&self) -> CvarValue {
+        match self.source {
+            CvarSource::CommandLine => self.saved_value.clone(),
+            _ => self.get_value(),
+        }
+    }
+}
+
+// A snapshot of a cvar, e.g. for listing the cvars in the console.
+#[derive(Clone, Debug)]
+pub struct CvarInfo {
+    name: String,
+    description: String,
+    value: CvarValue,
+    default_value: CvarValue,
+    range: Option<(f64, f64)>,
+    source: CvarSource,
+}
+
+impl CvarInfo {
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn description(&self) -> &str {
+        self.description.as_str()
+    }
+
+    pub fn kind(&self) -> CvarKind {
+        self.default_value.kind()
+    }
+
+    pub fn value(&self) -> &CvarValue {
+        &self.value
+    }
+
+    pub fn default_value(&self) -> &CvarValue {
+        &self.default_value
+    }
+
+    pub fn range(&self) -> Option<(f64, f64)> {
+        self.range
+    }
+
+    pub fn source(&self) -> CvarSource {
+        self.source
+    }
+}
+
+// Console variables: named, typed values that subsystems register, and that can be set from
+// the console, the command line, or the config file. Values in the config file of cvars that
+// have not been registered yet are applied once they are, so the config file can be loaded
+// before every subsystem is up.
+pub struct CvarRegistry {
+    cvars: BTreeMap<String, CvarEntry>,
+    pending_values: BTreeMap<String, toml::Value>,
+    config_file_path: PathBuf,
+}
+
+impl CvarRegistry {
+    pub fn new<P: Into<PathBuf>>(config_file_path: P) -> Self {
+        Self {
+            cvars: BTreeMap::new(),
+            pending_values: BTreeMap::new(),
+            config_file_path: config_file_path.into(),
+        }
+    }
+
+    // Uses the config file given in CONFIG_FILE_ENV_VAR, or DEFAULT_CONFIG_FILE_PATH.
+    pub fn from_env() -> Self {
+        match env::var_os(CONFIG_FILE_ENV_VAR) {
+            Some(config_file_path) => Self::new(config_file_path),
+            None => Self::new(DEFAULT_CONFIG_FILE_PATH),
+        }
+    }
+
+    pub fn config_file_path(&self) -> &Path {
+        self.config_file_path.as_path()
+    }
+
+    pub fn register<T: CvarType>(
+        &mut self,
+        definition: CvarDefinition<T>,
+    ) -> Result<Cvar<T>, CvarError> {
+        let name = definition.name;
+        Self::check_name(name.as_str())?;
+        if self.cvars.contains_key(&name) {
+            return Err(CvarError::new(
+                format!("cvar, \"{}\", has already been registered", name),
+                CvarErrorKind::AlreadyRegistered,
+                None,
+            ));
+        }
+
+        let mut entry = CvarEntry {
+            description: definition.description,
+            default_value: definition.default_value.clone(),
+            range: definition.range,
+            callbacks: definition.callbacks,
+            is_value_valid: definition.is_value_valid,
+            value: Arc::new(Mutex::new(definition.default_value.clone())),
+            source: CvarSource::Default,
+            saved_value: definition.default_value.clone(),
+        };
+        Self::check_value(name.as_str(), &entry, &definition.default_value)?;
+
+        // A bad value in the config file should not keep the subsystem from starting, so the
+        // cvar keeps its default value instead.
+        if let Some(pending_value) = self.pending_values.remove(&name) {
+            let value = Self::from_toml_value(name.as_str(), &entry, &pending_value)
+                .and_then(|value| Self::check_value(name.as_str(), &entry, &value).map(|_| value));
+            match value {
+                Ok(value) => {
+                    entry.value = Arc::new(Mutex::new(value));
+                    entry.source = CvarSource::ConfigFile;
+                }
+                Err(error) => log::warn!("Ignoring config file value. {}", error),
+            }
+        }
+
+        let cvar = Cvar::new(name.as_str(), entry.value.clone());
+        self.cvars.insert(name, entry);
+
+        Ok(cvar)
+    }
+
+    // Returns a handle to a registered cvar, if it can be read as T.
+    pub fn get_cvar<T: CvarType>(&self, name: &str) -> Option<Cvar<T>> {
+        let entry = self.cvars.get(name)?;
+        match T::kind() == entry.default_value.kind() {
+            true => Some(Cvar::new(name, entry.value.clone())),
+            false => None,
+        }
+    }
+
+    pub fn get_value(&self, name: &str) -> Option<CvarValue> {
+        self.cvars.get(name).map(CvarEntry::get_value)
+    }
+
+    pub fn get_info(&self, name: &str) -> Option<CvarInfo> {
+        self.cvars.get(name).map(|entry| CvarInfo {
+            name: String::from(name),
+            description: entry.description.clone(),
+            value: entry.get_value(),
+            default_value: entry.default_value.clone(),
+            range: entry.range,
+            source: entry.source,
+        })
+    }
+
+    // Sorted by name.
+    pub fn infos(&self) -> Vec<CvarInfo> {
+        self.cvars
+            .keys()
+            .filter_map(|name| self.get_info(name))
+            .collect()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.cvars.keys().map(String::as_str).collect()
+    }
+
+    // Sets the cvar as if from the console. The callbacks of the cvar are called if the value
+    // changes.
+    pub fn set_value(&mut self, name: &str, value: CvarValue) -> Result<(), CvarError> {
+        self.set_value_from(name, value, CvarSource::Console)
+    }
+
+    fn set_value_from(
+        &mut self,
+        name: &str,
+        value: CvarValue,
+        source: CvarSource,
+    ) -> Result<(), CvarError> {
+        let entry = match self.cvars.get_mut(name) {
+            Some(entry) => entry,
+            None => return Err(Self::not_found_error(name)),
+        };
+
+        let value = match (entry.default_value.kind(), value) {
+            (CvarKind::Float, CvarValue::Int(value)) => CvarValue::Float(value as f64),
+            (_, value) => value,
+        };
+        Self::check_value(name, entry, &value)?;
+
+        match (entry.source, source) {
+            // The command line takes precedence over the config file, whatever order they are
+            // applied in, so the config file value is only kept for saving.
+            (CvarSource::CommandLine, CvarSource::ConfigFile) => {
+                entry.saved_value = value;
+                return Ok(());
+            }
+            (CvarSource::CommandLine, CvarSource::CommandLine) => {}
+            (_, CvarSource::CommandLine) => entry.saved_value = entry.get_value(),
+            _ => {}
+        }
+        entry.source = source;
+
+        if entry.get_value() == value {
+            return Ok(());
+        }
+
+        match entry.value.lock() {
+            Ok(mut current_value) => *current_value = value.clone(),
+            Err(poisoned) => *poisoned.into_inner() = value.clone(),
+        }
+        for callback in entry.callbacks.iter_mut() {
+            callback(&value);
+        }
+
+        Ok(())
+    }
+
+    // Parses the value with CvarKind::parse(), and sets it as if from the console.
+    pub fn set_value_from_text(&mut self, name: &str, text: &str) -> Result<(), CvarError> {
+        let value = self.parse_value(name, text)?;
+        self.set_value(name, value)
+    }
+
+    fn parse_value(&self, name: &str, text: &str) -> Result<CvarValue, CvarError> {
+        let kind = match self.cvars.get(name) {
+            Some(entry) => entry.default_value.kind(),
+            None => return Err(Self::not_found_error(name)),
+        };
+
+        match kind.parse(text) {
+            Some(value) => Ok(value),
+            None => Err(CvarError::new(
+                format!(
+                    "value of cvar, \"{}\", must be {}, but is \"{}\"",
+                    name,
+                    kind.name(),
+                    text
+                ),
+                CvarErrorKind::InvalidValue,
+                None,
+            )),
+        }
+    }
+
+    pub fn reset(&mut self, name: &str) -> Result<(), CvarError> {
+        let default_value = match self.cvars.get(name) {
+            Some(entry) => entry.default_value.clone(),
+            None => return Err(Self::not_found_error(name)),
+        };
+
+        self.set_value_from(name, default_value, CvarSource::Default)
+    }
+
+    // Sets the cvars given as "--set <name>=<value>" in the command line, e.g.
+    // "--set window.width=1280". The values only last for the current run, and the config file
+    // keeps the previous ones. Returns the errors of the arguments that could not be applied.
+    pub fn set_values_from_args<I: IntoIterator<Item = String>>(
+        &mut self,
+        args: I,
+    ) -> Vec<CvarError> {
+        let mut errors: Vec<CvarError> = vec![];
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg != "--set" {
+                errors.push(CvarError::new(
+                    format!(
+                        "unknown command line argument, \"{}\". Cvars are set with \
+                        \"--set <name>=<value>\"",
+                        arg
+                    ),
+                    CvarErrorKind::InvalidArgument,
+                    None,
+                ));
+                continue;
+            }
+
+            let assignment = match args.next() {
+                Some(assignment) => assignment,
+                None => {
+                    errors.push(CvarError::new(
+                        "missing \"<name>=<value>\" after \"--set\"",
+                        CvarErrorKind::InvalidArgument,
+                        None,
+                    ));
+                    break;
+                }
+            };
+
+            let result = match assignment.split_once('=') {
+                Some((name, text)) => self.parse_value(name.trim(), text).and_then(|value| {
+                    self.set_value_from(name.trim(), value, CvarSource::CommandLine)
+                }),
+                None => Err(CvarError::new(
+                    format!(
+                        "command line argument, \"{}\", is not of the form \"<name>=<value>\"",
+                        assignment
+                    ),
+                    CvarErrorKind::InvalidArgument,
+                    None,
+                )),
+            };
+            if let Err(error) = result {
+                errors.push(error);
+            }
+        }
+
+        errors
+    }
+
+    // Sets the cvars to the values in the config file. A missing config file is not an error,
+    // since none gets written until the cvars are first saved. Returns the errors of the values
+    // that could not be applied.
+    pub fn load_config_file(&mut self) -> Vec<CvarError> {
+        let config_file_path_str = self.config_file_path.to_string_lossy().to_string();
+        let contents = match fs::read_to_string(&self.config_file_path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return vec![],
+            Err(error) => {
+                return vec![CvarError::new(
+                    format!("unable to read config file, {}", config_file_path_str),
+                    CvarErrorKind::ConfigReadError,
+                    Some(Box::new(error)),
+                )]
+            }
+        };
+
+        let table = match contents.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(error) => {
+                return vec![CvarError::new(
+                    format!("unable to parse config file, {}", config_file_path_str),
+                    CvarErrorKind::ConfigParseError,
+                    Some(Box::new(error)),
+                )]
+            }
+        };
+
+        let mut values: Vec<(String, toml::Value)> = vec![];
+        Self::flatten_table("", table, &mut values);
+
+        let mut errors: Vec<CvarError> = vec![];
+        for (name, toml_value) in values {
+            let entry = match self.cvars.get(&name) {
+                Some(entry) => entry,
+                None => {
+                    self.pending_values.insert(name, toml_value);
+                    continue;
+                }
+            };
+
+            let result =
+                Self::from_toml_value(name.as_str(), entry, &toml_value).and_then(|value| {
+                    self.set_value_from(name.as_str(), value, CvarSource::ConfigFile)
+                });
+            if let Err(error) = result {
+                errors.push(error);
+            }
+        }
+
+        errors
+    }
+
+    // Writes every cvar to the config file, including the ones read from it that were never
+    // registered, so that they are not lost. Cvars set from the command line are saved with the
+    // values they had before.
+    pub fn save_config_file(&self) -> Result<(), CvarError> {
+        let mut table = toml::Table::new();
+        for (name, toml_value) in &self.pending_values {
+            Self::insert_toml_value(&mut table, name, toml_value.clone())?;
+        }
+        for (name, entry) in &self.cvars {
+            Self::insert_toml_value(&mut table, name, entry.get_saved_value().to_toml_value())?;
+        }
+
+        let contents = match toml::to_string(&table) {
+            Ok(contents) => contents,
+            Err(error) => {
+                return Err(CvarError::new(
+                    "unable to serialize cvars",
+                    CvarErrorKind::ConfigWriteError,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        match fs::write(&self.config_file_path, contents) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(CvarError::new(
+                format!(
+                    "unable to write config file, {}",
+                    self.config_file_path.to_string_lossy()
+                ),
+                CvarErrorKind::ConfigWriteError,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+
+    // Names are sections of letters, digits, and underscores, separated by dots.
+    fn check_name(name: &str) -> Result<(), CvarError> {
+        let is_valid = name.split('.').all(|section| {
+            !section.is_empty()
+                && section
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+
+        match is_valid {
+            true => Ok(()),
+            false => Err(CvarError::new(
+                format!(
+                    "cvar name, \"{}\", must be made of letters, digits, and underscores, \
+                    separated by dots",
+                    name
+                ),
+                CvarErrorKind::InvalidName,
+                None,
+            )),
+        }
+    }
+
+    fn check_value(name: &str, entry: &CvarEntry, value: &CvarValue) -> Result<(), CvarError> {
+        let kind = entry.default_value.kind();
+        if value.kind() != kind {
+            return Err(CvarError::new(
+                format!(
+                    "value of cvar, \"{}\", must be {}, but is {}",
+                    name,
+                    kind.name(),
+                    value
+                ),
+                CvarErrorKind::InvalidValue,
+                None,
+            ));
+        }
+
+        // E.g. a negative integer given to a cvar read as a u32.
+        if !(entry.is_value_valid)(value) {
+            return Err(CvarError::new(
+                format!(
+                    "value of cvar, \"{}\", {}, does not fit the type of the cvar",
+                    name, value
+                ),
+                CvarErrorKind::OutOfRange,
+                None,
+            ));
+        }
+
+        if let Some((min, max)) = entry.range {
+            if value
+                .numbers()
+                .iter()
+                .any(|number| *number < min || *number > max)
+            {
+                return Err(CvarError::new(
+                    format!(
+                        "value of cvar, \"{}\", must be within [{}, {}], but is {}",
+                        name, min, max, value
+                    ),
+                    CvarErrorKind::OutOfRange,
+                    None,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn from_toml_value(
+        name: &str,
+        entry: &CvarEntry,
+        toml_value: &toml::Value,
+    ) -> Result<CvarValue, CvarError> {
+        let kind = entry.default_value.kind();
+        match kind.parse_toml_value(toml_value) {
+            Some(value) => Ok(value),
+            None => Err(CvarError::new(
+                format!(
+                    "value of cvar, \"{}\", in the config file must be {}, but is {}",
+                    name,
+                    kind.name(),
+                    toml_value
+                ),
+                CvarErrorKind::InvalidValue,
+                None,
+            )),
+        }
+    }
+
+    // Tables become sections of the names, e.g. "width" in [window] becomes "window.width".
+    fn flatten_table(prefix: &str, table: toml::Table, values: &mut Vec<(String, toml::Value)>) {
+        for (key, value) in table {
+            let name = match prefix.is_empty() {
+                true => key,
+                false => format!("{}.{}", prefix, key),
+            };
+
+            match value {
+                toml::Value::Table(table) => Self::flatten_table(name.as_str(), table, values),
+                value => values.push((name, value)),
+            }
+        }
+    }
+
+    fn insert_toml_value(
+        table: &mut toml::Table,
+        name: &str,
+        value: toml::Value,
+    ) -> Result<(), CvarError> {
+        let (sections, key) = match name.rsplit_once('.') {
+            Some((sections, key)) => (sections.split('.').collect(), key),
+            None => (vec![], name),
+        };
+
+        let mut table = table;
+        for section in sections {
+            let section_value = table
+                .entry(section)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            table = match section_value {
+                toml::Value::Table(section_table) => section_table,
+                _ => {
+                    return Err(CvarError::new(
+                        format!(
+                            "cvar, \"{}\", cannot be saved, since \"{}\" is a cvar as well",
+                            name, section
+                        ),
+                        CvarErrorKind::ConfigWriteError,
+                        None,
+                    ))
+                }
+            };
+        }
+        table.insert(String::from(key), value);
+
+        Ok(())
+    }
+
+    fn not_found_error(name: &str) -> CvarError {
+        CvarError::new(
+            format!(
+                "unknown cvar, \"{}\". Type \"list-cvars\" to list the available cvars",
+                name
+            ),
+            CvarErrorKind::NotFound,
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_registry() -> CvarRegistry {
+        let mut registry = CvarRegistry::new("mulay-gfx-test.toml");
+        registry
+            .register(CvarDefinition::new("window.width", "Width.", 800u32))
+            .unwrap();
+        registry
+            .register(CvarDefinition::new("render.exposure", "Exposure.", 1.0f32).range(0.0, 4.0))
+            .unwrap();
+        registry
+            .register(CvarDefinition::new(
+                "render.clear_color",
+                "Clear colour.",
+                [0.0f32, 0.0, 0.0],
+            ))
+            .unwrap();
+
+        registry
+    }
+
+    fn error_kind(result: Result<(), CvarError>) -> CvarErrorKind {
+        result.unwrap_err().kind()
+    }
+
+    #[test]
+    fn sets_values_from_text() {
+        let mut registry = create_registry();
+        registry
+            .set_value_from_text("window.width", "1280")
+            .unwrap();
+        registry
+            .set_value_from_text("render.clear_color", "0.1, 0.2, 0.3")
+            .unwrap();
+
+        let width: Cvar<u32> = registry.get_cvar("window.width").unwrap();
+        let clear_color: Cvar<[f32; 3]> = registry.get_cvar("render.clear_color").unwrap();
+        assert_eq!(width.get(), 1280);
+        assert_eq!(clear_color.get(), [0.1, 0.2, 0.3]);
+    }
+
+    #[test]
+    fn rejects_values_that_do_not_parse_or_fit() {
+        let mut registry = create_registry();
+        assert_eq!(
+            error_kind(registry.set_value_from_text("window.width", "wide")),
+            CvarErrorKind::InvalidValue
+        );
+        assert_eq!(
+            error_kind(registry.set_value_from_text("window.width", "-1")),
+            CvarErrorKind::OutOfRange
+        );
+        assert_eq!(
+            error_kind(registry.set_value_from_text("render.exposure", "5")),
+            CvarErrorKind::OutOfRange
+        );
+        assert_eq!(
+            error_kind(registry.set_value_from_text("render.clear_color", "0.1 0.2")),
+            CvarErrorKind::InvalidValue
+        );
+        assert_eq!(
+            error_kind(registry.set_value_from_text("window.height", "720")),
+            CvarErrorKind::NotFound
+        );
+        assert_eq!(
+            registry.get_value("window.width"),
+            Some(CvarValue::Int(800))
+        );
+    }
+
+    #[test]
+    fn accepts_integers_for_floats() {
+        let mut registry = create_registry();
+        registry
+            .set_value("render.exposure", CvarValue::Int(2))
+            .unwrap();
+        assert_eq!(
+            registry.get_value("render.exposure"),
+            Some(CvarValue::Float(2.0))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        let mut registry = create_registry();
+        for name in ["", "window.", ".width", "window width", "window-width"] {
+            let result = registry.register(CvarDefinition::new(name, "Invalid.", true));
+            assert_eq!(result.unwrap_err().kind(), CvarErrorKind::InvalidName);
+        }
+        let result = registry.register(CvarDefinition::new("window.width", "Again.", 640u32));
+        assert_eq!(result.unwrap_err().kind(), CvarErrorKind::AlreadyRegistered);
+    }
+
+    #[test]
+    fn sets_values_from_command_line_arguments() {
+        let mut registry = create_registry();
+        let args = [
+            "--set",
+            "window.width = 1280",
+            "--set",
+            "render.exposure=2.5",
+            "--verbose",
+            "--set",
+            "render.exposure",
+            "--set",
+        ];
+        let errors = registry.set_values_from_args(args.into_iter().map(String::from));
+
+        let error_kinds: Vec<CvarErrorKind> = errors.iter().map(CvarError::kind).collect();
+        assert_eq!(
+            error_kinds,
+            vec![
+                CvarErrorKind::InvalidArgument,
+                CvarErrorKind::InvalidArgument,
+                CvarErrorKind::InvalidArgument,
+            ]
+        );
+        assert_eq!(
+            registry.get_value("window.width"),
+            Some(CvarValue::Int(1280))
+        );
+        assert_eq!(
+            registry.get_info("render.exposure").unwrap().source(),
+            CvarSource::CommandLine
+        );
+    }
+
+    #[test]
+    fn flattens_config_file_tables_into_names() {
+        let table: toml::Table = "[window]\nwidth = 1280\n[render.post]\nbloom = true\n"
+            .parse()
+            .unwrap();
+        let mut values: Vec<(String, toml::Value)> = vec![];
+        CvarRegistry::flatten_table("", table, &mut values);
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(
+            values,
+            vec![
+                (
+                    String::from("render.post.bloom"),
+                    toml::Value::Boolean(true)
+                ),
+                (String::from("window.width"), toml::Value::Integer(1280)),
+            ]
+        );
+    }
+}
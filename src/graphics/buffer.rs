@@ -0,0 +1,141 @@
+extern crate gl;
+
+use std::mem;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum BufferKind {
+    Vertex,
+    Index,
+}
+
+impl BufferKind {
+    pub fn gl_target(&self) -> gl::types::GLenum {
+        match self {
+            BufferKind::Vertex => gl::ARRAY_BUFFER,
+            BufferKind::Index => gl::ELEMENT_ARRAY_BUFFER,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum BufferUsage {
+    Static,
+    Dynamic,
+    Stream,
+}
+
+impl BufferUsage {
+    pub fn gl_usage(&self) -> gl::types::GLenum {
+        match self {
+            BufferUsage::Static => gl::STATIC_DRAW,
+            BufferUsage::Dynamic => gl::DYNAMIC_DRAW,
+            BufferUsage::Stream => gl::STREAM_DRAW,
+        }
+    }
+}
+
+pub struct Buffer {
+    id: gl::types::GLuint,
+    kind: BufferKind,
+    usage: BufferUsage,
+    len: usize,
+    element_size: usize,
+    capacity_in_bytes: usize,
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteBuffers(1, &self.id) }
+    }
+}
+
+impl Buffer {
+    pub fn new<T: Copy>(kind: BufferKind, usage: BufferUsage, data: &[T]) -> Self {
+        let mut buffer_id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut buffer_id);
+        };
+
+        let mut buffer: Self = Self {
+            id: buffer_id,
+            kind,
+            usage,
+            len: 0,
+            element_size: mem::size_of::<T>(),
+            capacity_in_bytes: 0,
+        };
+        buffer.update(data);
+
+        buffer
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    pub fn kind(&self) -> BufferKind {
+        self.kind
+    }
+
+    pub fn usage(&self) -> BufferUsage {
+        self.usage
+    }
+
+    // Number of elements in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Size, in bytes, of each element in the buffer.
+    pub fn element_size(&self) -> usize {
+        self.element_size
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        self.len * self.element_size
+    }
+
+    // Binding an index buffer outside of a vertex array modifies the vertex array that is
+    // currently bound, so only vertex arrays bind index buffers to their target.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindBuffer(self.kind.gl_target(), self.id);
+        };
+    }
+
+    // Replaces the contents of the buffer. The buffer storage is only reallocated when the
+    // new data does not fit in it. The data is uploaded through the copy-write target, which no
+    // vertex array keeps track of, so that updating an index buffer leaves the index buffer of
+    // the currently bound vertex array alone.
+    pub fn update<T: Copy>(&mut self, data: &[T]) {
+        let new_size: usize = mem::size_of_val(data);
+
+        unsafe {
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, self.id);
+            if new_size > self.capacity_in_bytes || self.capacity_in_bytes == 0 {
+                gl::BufferData(
+                    gl::COPY_WRITE_BUFFER,
+                    new_size as gl::types::GLsizeiptr,
+                    data.as_ptr() as *const gl::types::GLvoid,
+                    self.usage.gl_usage(),
+                );
+                self.capacity_in_bytes = new_size;
+            } else {
+                gl::BufferSubData(
+                    gl::COPY_WRITE_BUFFER,
+                    0,
+                    new_size as gl::types::GLsizeiptr,
+                    data.as_ptr() as *const gl::types::GLvoid,
+                );
+            }
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+        };
+
+        self.len = data.len();
+        self.element_size = mem::size_of::<T>();
+    }
+}
@@ -0,0 +1,496 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::assets::{
+    normalize_path, Asset, AssetError, AssetErrorKind, AssetKey, AssetRegistry, Handle, Texture,
+};
+use crate::graphics::{
+    is_sampler_type, uniform_type_name, Program, Sampler, UniformConstant, UniformError,
+    UniformErrorKind,
+};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum MaterialErrorKind {
+    ReadError,
+    ParseError,
+    InvalidUniformValue,
+    UnresolvedAsset,
+    UniformError,
+}
+
+#[derive(Debug)]
+pub struct MaterialError {
+    source: Option<Box<dyn Error + 'static>>,
+    message: String,
+    kind: MaterialErrorKind,
+}
+
+impl MaterialError {
+    pub fn new(
+        message: impl AsRef<str>,
+        kind: MaterialErrorKind,
+        source: Option<Box<dyn Error + 'static>>,
+    ) -> MaterialError {
+        MaterialError {
+            source,
+            message: message.as_ref().into(),
+            kind,
+        }
+    }
+
+    pub fn kind(&self) -> MaterialErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for MaterialError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref()
+    }
+}
+
+// The contents of a .material file, written in TOML:
+//
+//     program = "../shaders/lit.program"
+//
+//     [textures]
+//     albedoMap = "../textures/brick.png"
+//
+//     [uniforms]
+//     tint = [1.0, 0.9, 0.8, 1.0]
+//
+// Paths are relative to the directory of the .material file. Textures are keyed by the name
+// of the sampler uniform they are bound to, and the uniforms take the same values as the ones
+// of a .program manifest.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawMaterial {
+    program: PathBuf,
+    #[serde(default)]
+    textures: BTreeMap<String, PathBuf>,
+    #[serde(default)]
+    uniforms: BTreeMap<String, toml::Value>,
+}
+
+// The program and textures of a material are assets of their own, shared with every other
+// material that refers to the same files. They are loaded through the asset registry, which
+// reloads the material whenever its file changes. Reloaded programs and textures need nothing
+// from the material, since it only sets them up in bind(), but the uniforms and textures of the
+// material are checked against every new version of its program.
+pub struct Material {
+    id: String,
+    src_file_path: PathBuf,
+    program_file_path: PathBuf,
+    texture_file_paths: Vec<(String, PathBuf)>,
+    uniforms: Vec<(String, UniformConstant)>,
+    // Both are set once the material has loaded the assets it refers to.
+    program: Option<Handle<Program>>,
+    textures: Vec<(String, Handle<Texture>)>,
+    // What the material file was last reloaded into. It only replaces the current material
+    // once load_asset_dependencies() has loaded the assets it refers to, and checked it against
+    // its program.
+    reloaded_description: Option<MaterialDescription>,
+    is_loaded: bool,
+    is_stale: bool,
+}
+
+impl Asset for Material {
+    fn new<S: AsRef<str>>(id: S, file_path: &Path) -> Result<Self, AssetError> {
+        let raw_material = match Self::read_file(file_path) {
+            Ok(raw_material) => raw_material,
+            Err(error) => {
+                return Err(AssetError::new(
+                    format!("unable to load material, \"{}\"", id.as_ref()),
+                    AssetErrorKind::LoadingFailed,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        Ok(Self {
+            id: id.as_ref().into(),
+            src_file_path: file_path.to_path_buf(),
+            program_file_path: raw_material.program_file_path,
+            texture_file_paths: raw_material.texture_file_paths,
+            uniforms: raw_material.uniforms,
+            program: None,
+            textures: vec![],
+            reloaded_description: None,
+            is_loaded: true,
+            is_stale: false,
+        })
+    }
+
+    fn supported_file_extensions() -> &'static [&'static str] {
+        &["material"]
+    }
+
+    // Only the material file is read again. The new material is swapped in by
+    // load_asset_dependencies(), so a broken file leaves the current material untouched.
+    fn reload(&mut self) -> Result<(), AssetError> {
+        if !self.is_loaded {
+            return Err(AssetError::new(
+                format!("asset, '{}', not yet loaded", self.id.as_str()),
+                AssetErrorKind::NotLoaded,
+                None,
+            ));
+        }
+
+        let raw_material = match Self::read_file(self.src_file_path.as_path()) {
+            Ok(raw_material) => raw_material,
+            Err(error) => {
+                return Err(AssetError::new(
+                    format!(
+                        "unable to hot-reload material from {}",
+                        self.src_file_path.to_string_lossy()
+                    ),
+                    AssetErrorKind::ReloadingFailed,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        self.reloaded_description = Some(raw_material);
+        self.is_stale = false;
+
+        Ok(())
+    }
+
+    fn destroy(&mut self) -> Result<(), AssetError> {
+        // Dropping the handles lets the program and textures get unloaded once no other asset
+        // uses them.
+        self.program = None;
+        self.textures.clear();
+        self.reloaded_description = None;
+
+        self.id.clear();
+        self.src_file_path.clear();
+        self.is_loaded = false;
+
+        Ok(())
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.is_loaded
+    }
+
+    fn is_stale(&self) -> bool {
+        self.is_stale
+    }
+
+    fn mark_stale(&mut self) {
+        self.is_stale = true;
+    }
+
+    fn get_src_file_path(&self) -> &Path {
+        self.src_file_path.as_path()
+    }
+
+    fn get_asset_dependencies(&self) -> Vec<AssetKey> {
+        self.program
+            .iter()
+            .map(AssetKey::of)
+            .chain(
+                self.textures
+                    .iter()
+                    .map(|(_, texture)| AssetKey::of(texture)),
+            )
+            .collect()
+    }
+
+    fn load_asset_dependencies(&mut self, assets: &mut AssetRegistry) -> Result<(), AssetError> {
+        let description = match self.reloaded_description.take() {
+            Some(description) => description,
+            None => MaterialDescription {
+                program_file_path: self.program_file_path.clone(),
+                texture_file_paths: self.texture_file_paths.clone(),
+                uniforms: self.uniforms.clone(),
+            },
+        };
+
+        let program_file_path = &description.program_file_path;
+        let program = match assets.get_or_load_asset_file::<Program>(program_file_path) {
+            Ok(program) => program,
+            Err(error) => {
+                return Err(AssetError::new(
+                    format!(
+                        "unable to load program, {}, of material, \"{}\"",
+                        program_file_path.to_string_lossy(),
+                        self.id
+                    ),
+                    AssetErrorKind::LoadingFailed,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        let mut textures: Vec<(String, Handle<Texture>)> = vec![];
+        for (sampler_name, texture_file_path) in &description.texture_file_paths {
+            match assets.get_or_load_asset_file::<Texture>(texture_file_path) {
+                Ok(texture) => textures.push((sampler_name.clone(), texture)),
+                Err(error) => {
+                    return Err(AssetError::new(
+                        format!(
+                            "unable to load texture, {}, of material, \"{}\"",
+                            texture_file_path.to_string_lossy(),
+                            self.id
+                        ),
+                        AssetErrorKind::LoadingFailed,
+                        Some(Box::new(error)),
+                    ))
+                }
+            }
+        }
+
+        if let Some(program) = assets.get(&program) {
+            if let Err(error) = self.check_against_program(program, &description) {
+                return Err(AssetError::new(
+                    format!("material, \"{}\", does not fit its program", self.id),
+                    AssetErrorKind::LoadingFailed,
+                    Some(Box::new(error)),
+                ));
+            }
+        }
+
+        self.program_file_path = description.program_file_path;
+        self.texture_file_paths = description.texture_file_paths;
+        self.uniforms = description.uniforms;
+        self.program = Some(program);
+        self.textures = textures;
+
+        Ok(())
+    }
+
+    // The program of the material got reloaded, and may no longer have the uniforms of the
+    // material, or may have them with other types.
+    fn rebuild(&mut self, assets: &AssetRegistry) -> Result<(), AssetError> {
+        let program = match self
+            .program
+            .as_ref()
+            .and_then(|program| assets.get(program))
+        {
+            Some(program) => program,
+            None => return Ok(()),
+        };
+
+        let description = MaterialDescription {
+            program_file_path: self.program_file_path.clone(),
+            texture_file_paths: self.texture_file_paths.clone(),
+            uniforms: self.uniforms.clone(),
+        };
+        match self.check_against_program(program, &description) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(AssetError::new(
+                format!(
+                    "material, \"{}\", does not fit its reloaded program",
+                    self.id
+                ),
+                AssetErrorKind::ReloadingFailed,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+}
+
+// What a material file is turned into, before the assets it refers to are loaded.
+struct MaterialDescription {
+    program_file_path: PathBuf,
+    texture_file_paths: Vec<(String, PathBuf)>,
+    uniforms: Vec<(String, UniformConstant)>,
+}
+
+impl Material {
+    pub fn program(&self) -> Option<&Handle<Program>> {
+        self.program.as_ref()
+    }
+
+    pub fn textures(&self) -> &[(String, Handle<Texture>)] {
+        self.textures.as_slice()
+    }
+
+    pub fn uniforms(&self) -> &[(String, UniformConstant)] {
+        self.uniforms.as_slice()
+    }
+
+    // Uses the program of the material, binds its textures to consecutive texture units
+    // starting from 0, and sets its uniforms. Samplers and uniforms the program does not use
+    // are skipped, since the driver optimizes them out. The program is returned so that
+    // per-draw uniforms can be set on top.
+    pub fn bind<'a>(&self, assets: &'a AssetRegistry) -> Result<&'a Program, MaterialError> {
+        let program = match self
+            .program
+            .as_ref()
+            .and_then(|program| assets.get(program))
+        {
+            Some(program) => program,
+            None => {
+                return Err(MaterialError::new(
+                    format!(
+                        "program, {}, of material, \"{}\", is not loaded",
+                        self.program_file_path.to_string_lossy(),
+                        self.id
+                    ),
+                    MaterialErrorKind::UnresolvedAsset,
+                    None,
+                ))
+            }
+        };
+
+        unsafe {
+            gl::UseProgram(program.id());
+        };
+
+        for (texture_unit, (sampler_name, texture)) in self.textures.iter().enumerate() {
+            let texture = match assets.get(texture) {
+                Some(texture) => texture,
+                None => {
+                    return Err(MaterialError::new(
+                        format!(
+                            "texture bound to sampler, \"{}\", of material, \"{}\", is not loaded",
+                            sampler_name, self.id
+                        ),
+                        MaterialErrorKind::UnresolvedAsset,
+                        None,
+                    ))
+                }
+            };
+
+            texture.bind(texture_unit as u32);
+            let result = program.set_uniform(sampler_name, Sampler(texture_unit as u32));
+            self.check_uniform_result(sampler_name, result)?;
+        }
+
+        for (name, value) in &self.uniforms {
+            let result = program.set_uniform_constant(name, value);
+            self.check_uniform_result(name, result)?;
+        }
+
+        Ok(program)
+    }
+
+    // Checks that bind() can set every uniform of the material, and bind every texture to a
+    // sampler. Uniforms and samplers the program does not use are skipped, like in bind().
+    fn check_against_program(
+        &self,
+        program: &Program,
+        description: &MaterialDescription,
+    ) -> Result<(), MaterialError> {
+        for (sampler_name, _) in &description.texture_file_paths {
+            let uniform = match program.get_uniform_info(sampler_name) {
+                Some(uniform) => uniform,
+                None => continue,
+            };
+            if !is_sampler_type(uniform.gl_type()) {
+                let error = UniformError::new(
+                    format!(
+                        "uniform, \"{}\", is declared as a {}, and not as a sampler",
+                        sampler_name,
+                        uniform_type_name(uniform.gl_type())
+                    ),
+                    UniformErrorKind::TypeMismatch,
+                    None,
+                );
+                self.check_uniform_result(sampler_name, Err(error))?;
+            }
+        }
+
+        for (name, value) in &description.uniforms {
+            let result = program.check_uniform_constant(name, value);
+            self.check_uniform_result(name, result)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_uniform_result(
+        &self,
+        name: &str,
+        result: Result<(), UniformError>,
+    ) -> Result<(), MaterialError> {
+        match result {
+            Ok(_) => Ok(()),
+            Err(error) if error.kind() == UniformErrorKind::NotFound => Ok(()),
+            Err(error) => Err(MaterialError::new(
+                format!(
+                    "unable to set uniform, \"{}\", of material, \"{}\"",
+                    name, self.id
+                ),
+                MaterialErrorKind::UniformError,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+
+    fn read_file(file_path: &Path) -> Result<MaterialDescription, MaterialError> {
+        let contents = match fs::read_to_string(file_path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                return Err(MaterialError::new(
+                    format!(
+                        "unable to read material from {}",
+                        file_path.to_string_lossy()
+                    ),
+                    MaterialErrorKind::ReadError,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        let raw_material: RawMaterial = match toml::from_str(contents.as_str()) {
+            Ok(raw_material) => raw_material,
+            Err(error) => {
+                return Err(MaterialError::new(
+                    format!("material from {} is not valid", file_path.to_string_lossy()),
+                    MaterialErrorKind::ParseError,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        let mut uniforms: Vec<(String, UniformConstant)> = vec![];
+        for (name, value) in raw_material.uniforms {
+            match UniformConstant::from_toml_value(&value) {
+                Some(constant) => uniforms.push((name, constant)),
+                None => {
+                    return Err(MaterialError::new(
+                        format!(
+                            "value of uniform, \"{}\", of material from {} must be a boolean, a \
+                            number, an array of numbers, or an array of columns of numbers, but \
+                            is {}",
+                            name,
+                            file_path.to_string_lossy(),
+                            value
+                        ),
+                        MaterialErrorKind::InvalidUniformValue,
+                        None,
+                    ))
+                }
+            }
+        }
+
+        let base_dir_path: &Path = file_path.parent().unwrap_or(Path::new(""));
+        Ok(MaterialDescription {
+            program_file_path: normalize_path(&base_dir_path.join(raw_material.program)),
+            texture_file_paths: raw_material
+                .textures
+                .into_iter()
+                .map(|(sampler_name, texture_file_path)| {
+                    let texture_file_path = normalize_path(&base_dir_path.join(texture_file_path));
+                    (sampler_name, texture_file_path)
+                })
+                .collect(),
+            uniforms,
+        })
+    }
+}
@@ -0,0 +1,15 @@
+pub mod buffer;
+pub mod material;
+pub mod program;
+pub mod program_manifest;
+pub mod uniform;
+pub mod uniform_buffer;
+pub mod vertex_array;
+
+pub use buffer::*;
+pub use material::*;
+pub use program::*;
+pub use program_manifest::*;
+pub use uniform::*;
+pub use uniform_buffer::*;
+pub use vertex_array::*;
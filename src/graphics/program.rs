@@ -0,0 +1,752 @@
+// Based on:
+// http://nercury.github.io/rust/opengl/tutorial/2018/02/10
+//       /opengl-in-rust-from-scratch-03-compiling-shaders.html
+extern crate gl;
+
+use crate::assets::{
+    Asset, AssetError, AssetErrorKind, AssetKey, AssetManager, AssetRegistry, Handle, Shader,
+};
+use crate::c_bridge;
+use crate::graphics::{
+    bind_active_uniform_blocks, is_sampler_type, reflect_active_attributes,
+    reflect_active_uniforms, uniform_type_name, AttributeInfo, ProgramManifest, Sampler,
+    Std140Block, UniformBlockInfo, UniformBuffer, UniformBufferError, UniformConstant,
+    UniformError, UniformErrorKind, UniformInfo, UniformValue,
+};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::ffi::CString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ProgramErrorKind {
+    LinkError,
+    StaleShaderHandle,
+    UniformBlockBindingError,
+    ManifestError,
+    ShaderCompileError,
+    DefaultUniformError,
+}
+
+#[derive(Debug)]
+pub struct ProgramError {
+    source: Option<Box<dyn Error + 'static>>,
+    message: String,
+    kind: ProgramErrorKind,
+    diagnostics: Vec<String>,
+}
+
+impl ProgramError {
+    pub fn new(
+        message: impl AsRef<str>,
+        kind: ProgramErrorKind,
+        source: Option<Box<dyn Error + 'static>>,
+    ) -> ProgramError {
+        ProgramError {
+            source,
+            message: message.as_ref().into(),
+            kind,
+            diagnostics: vec![],
+        }
+    }
+
+    // Attaches the per-line diagnostics reported by the driver (e.g. the lines of a
+    // program info log) to the error.
+    pub fn with_diagnostics(mut self, diagnostics: Vec<String>) -> ProgramError {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    pub fn kind(&self) -> ProgramErrorKind {
+        self.kind
+    }
+
+    pub fn diagnostics(&self) -> &[String] {
+        self.diagnostics.as_slice()
+    }
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ProgramError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref()
+    }
+}
+
+pub struct Program {
+    id: gl::types::GLuint,
+    shaders: Vec<Handle<Shader>>,
+    // Only set for programs loaded from a .program manifest. Their stages are shader assets,
+    // one per permutation, so the program gets relinked whenever one of them is reloaded.
+    manifest_file_path: Option<PathBuf>,
+    // What the manifest was last (re)loaded into. It only replaces the current program once
+    // load_asset_dependencies() has loaded its stages and linked them.
+    pending_manifest: Option<ProgramManifest>,
+    default_uniforms: Vec<(String, UniformConstant)>,
+    attributes: HashMap<String, AttributeInfo>,
+    uniforms: HashMap<String, UniformInfo>,
+    uniform_blocks: HashMap<String, UniformBlockInfo>,
+    is_stale: bool,
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteProgram(self.id) }
+    }
+}
+
+impl Program {
+    pub fn new(
+        shader_manager: &AssetManager<Shader>,
+        shaders: Vec<Handle<Shader>>,
+    ) -> Result<Self, ProgramError> {
+        let program_id: gl::types::GLuint = Self::link(shader_manager, &shaders)?;
+        Self::from_linked_program(program_id, shaders, None, vec![])
+    }
+
+    // Reads a .program manifest (see ProgramManifest). The program is only linked once
+    // load_asset_dependencies() loads its stages through the asset registry.
+    fn from_manifest_file(file_path: &Path) -> Result<Self, ProgramError> {
+        let manifest = match ProgramManifest::from_file(file_path) {
+            Ok(manifest) => manifest,
+            Err(error) => {
+                return Err(ProgramError::new(
+                    format!(
+                        "unable to load program manifest from {}",
+                        file_path.to_string_lossy()
+                    ),
+                    ProgramErrorKind::ManifestError,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        Ok(Self {
+            id: 0,
+            shaders: vec![],
+            manifest_file_path: Some(file_path.to_path_buf()),
+            pending_manifest: Some(manifest),
+            default_uniforms: vec![],
+            attributes: HashMap::new(),
+            uniforms: HashMap::new(),
+            uniform_blocks: HashMap::new(),
+            is_stale: false,
+        })
+    }
+
+    // Loads the stages of the manifest as shader assets, compiled with the defines of the
+    // manifest, and links them.
+    fn link_manifest(
+        shader_manager: &mut AssetManager<Shader>,
+        manifest_file_path: &Path,
+        manifest: &ProgramManifest,
+    ) -> Result<Self, ProgramError> {
+        let mut shaders: Vec<Handle<Shader>> = vec![];
+        for stage_file_path in manifest.stage_file_paths() {
+            match shader_manager.load_shader_permutation(stage_file_path, manifest.defines()) {
+                Ok(shader) => shaders.push(shader),
+                Err(error) => {
+                    return Err(ProgramError::new(
+                        format!(
+                            "unable to load shader stage, {}, of program from {}",
+                            stage_file_path.to_string_lossy(),
+                            manifest_file_path.to_string_lossy()
+                        ),
+                        ProgramErrorKind::ShaderCompileError,
+                        Some(Box::new(error)),
+                    ))
+                }
+            }
+        }
+
+        let program_id: gl::types::GLuint = Self::link(shader_manager, &shaders)?;
+        Self::from_linked_program(
+            program_id,
+            shaders,
+            Some(manifest_file_path.to_path_buf()),
+            manifest.uniforms().to_vec(),
+        )
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    // The replacement program is linked before the current one is deleted, so that a failed
+    // reload leaves the last working program in place.
+    pub fn relink(&mut self, shader_manager: &AssetManager<Shader>) -> Result<(), ProgramError> {
+        let new_program_id: gl::types::GLuint = Self::link(shader_manager, &self.shaders)?;
+        let new_program = Self::from_linked_program(
+            new_program_id,
+            self.shaders.clone(),
+            self.manifest_file_path.clone(),
+            self.default_uniforms.clone(),
+        )?;
+
+        // The current program gets deleted as it is dropped.
+        *self = new_program;
+
+        Ok(())
+    }
+
+    pub fn shaders(&self) -> &[Handle<Shader>] {
+        self.shaders.as_slice()
+    }
+
+    // Values set to uniforms every time the program is linked.
+    pub fn default_uniforms(&self) -> &[(String, UniformConstant)] {
+        self.default_uniforms.as_slice()
+    }
+
+    pub fn attributes(&self) -> impl Iterator<Item = &AttributeInfo> {
+        self.attributes.values()
+    }
+
+    pub fn get_attribute_info<S: AsRef<str>>(&self, name: S) -> Option<&AttributeInfo> {
+        self.attributes.get(name.as_ref())
+    }
+
+    pub fn uniforms(&self) -> impl Iterator<Item = &UniformInfo> {
+        self.uniforms.values()
+    }
+
+    pub fn get_uniform_info<S: AsRef<str>>(&self, name: S) -> Option<&UniformInfo> {
+        self.uniforms.get(name.as_ref())
+    }
+
+    pub fn uniform_blocks(&self) -> impl Iterator<Item = &UniformBlockInfo> {
+        self.uniform_blocks.values()
+    }
+
+    pub fn get_uniform_block_info<S: AsRef<str>>(&self, name: S) -> Option<&UniformBlockInfo> {
+        self.uniform_blocks.get(name.as_ref())
+    }
+
+    // Binds the buffer for the block of the program with the same name, once it has checked
+    // that the buffer matches the block. Programs that do not use the block are left alone.
+    pub fn bind_uniform_buffer<T: Std140Block>(
+        &self,
+        uniform_buffer: &UniformBuffer<T>,
+    ) -> Result<(), UniformBufferError> {
+        if let Some(block) = self.uniform_blocks.get(uniform_buffer.block_name()) {
+            uniform_buffer.check_block(block)?;
+            uniform_buffer.bind();
+        }
+
+        Ok(())
+    }
+
+    // Sets a uniform of the program. The program must be currently in use.
+    pub fn set_uniform<S: AsRef<str>, T: UniformValue>(
+        &self,
+        name: S,
+        value: T,
+    ) -> Result<(), UniformError> {
+        self.set_uniform_array(name, &[value])
+    }
+
+    // Sets consecutive elements of an array uniform, starting from the element named by `name`
+    // (e.g. "lights" or "lights[2]"). The program must be currently in use.
+    pub fn set_uniform_array<S: AsRef<str>, T: UniformValue>(
+        &self,
+        name: S,
+        values: &[T],
+    ) -> Result<(), UniformError> {
+        let uniform = match self.uniforms.get(name.as_ref()) {
+            Some(uniform) => uniform,
+            None => {
+                return Err(UniformError::new(
+                    format!(
+                        "uniform, \"{}\", is not an active uniform of the program",
+                        name.as_ref()
+                    ),
+                    UniformErrorKind::NotFound,
+                    None,
+                ))
+            }
+        };
+
+        if !T::is_compatible_with(uniform.gl_type()) {
+            return Err(UniformError::new(
+                format!(
+                    "uniform, \"{}\", is declared as a {} and cannot be set to a {}",
+                    name.as_ref(),
+                    uniform_type_name(uniform.gl_type()),
+                    std::any::type_name::<T>()
+                ),
+                UniformErrorKind::TypeMismatch,
+                None,
+            ));
+        }
+
+        if values.len() > uniform.size() as usize {
+            return Err(UniformError::new(
+                format!(
+                    "uniform, \"{}\", can only hold {} element(s), but {} were given",
+                    name.as_ref(),
+                    uniform.size(),
+                    values.len()
+                ),
+                UniformErrorKind::TooManyElements,
+                None,
+            ));
+        }
+
+        unsafe {
+            T::upload(uniform.location(), values);
+        };
+
+        Ok(())
+    }
+
+    // Sets a uniform to a value whose type is only known at runtime, converting it to the type
+    // of the uniform. The program must be currently in use.
+    pub fn set_uniform_constant<S: AsRef<str>>(
+        &self,
+        name: S,
+        value: &UniformConstant,
+    ) -> Result<(), UniformError> {
+        let name = name.as_ref();
+        let uniform = match self.uniforms.get(name) {
+            Some(uniform) => uniform,
+            None => {
+                return Err(UniformError::new(
+                    format!(
+                        "uniform, \"{}\", is not an active uniform of the program",
+                        name
+                    ),
+                    UniformErrorKind::NotFound,
+                    None,
+                ))
+            }
+        };
+
+        let result = match uniform.gl_type() {
+            gl::FLOAT => value
+                .as_scalar()
+                .map(|value| self.set_uniform(name, value as f32)),
+            gl::FLOAT_VEC2 => value
+                .as_vector::<2>()
+                .map(|value| self.set_uniform(name, value.map(|c| c as f32))),
+            gl::FLOAT_VEC3 => value
+                .as_vector::<3>()
+                .map(|value| self.set_uniform(name, value.map(|c| c as f32))),
+            gl::FLOAT_VEC4 => value
+                .as_vector::<4>()
+                .map(|value| self.set_uniform(name, value.map(|c| c as f32))),
+            gl::INT => value
+                .as_scalar()
+                .map(|value| self.set_uniform(name, value as i32)),
+            gl::INT_VEC2 => value
+                .as_vector::<2>()
+                .map(|value| self.set_uniform(name, value.map(|c| c as i32))),
+            gl::INT_VEC3 => value
+                .as_vector::<3>()
+                .map(|value| self.set_uniform(name, value.map(|c| c as i32))),
+            gl::INT_VEC4 => value
+                .as_vector::<4>()
+                .map(|value| self.set_uniform(name, value.map(|c| c as i32))),
+            gl::UNSIGNED_INT => value
+                .as_scalar()
+                .map(|value| self.set_uniform(name, value as u32)),
+            gl::UNSIGNED_INT_VEC2 => value
+                .as_vector::<2>()
+                .map(|value| self.set_uniform(name, value.map(|c| c as u32))),
+            gl::UNSIGNED_INT_VEC3 => value
+                .as_vector::<3>()
+                .map(|value| self.set_uniform(name, value.map(|c| c as u32))),
+            gl::UNSIGNED_INT_VEC4 => value
+                .as_vector::<4>()
+                .map(|value| self.set_uniform(name, value.map(|c| c as u32))),
+            gl::BOOL => value.as_bool().map(|value| self.set_uniform(name, value)),
+            gl::BOOL_VEC2 => value
+                .as_bool_vector::<2>()
+                .map(|value| self.set_uniform(name, value)),
+            gl::BOOL_VEC3 => value
+                .as_bool_vector::<3>()
+                .map(|value| self.set_uniform(name, value)),
+            gl::BOOL_VEC4 => value
+                .as_bool_vector::<4>()
+                .map(|value| self.set_uniform(name, value)),
+            gl::FLOAT_MAT2 => value
+                .as_matrix::<2>()
+                .map(|value| self.set_uniform(name, value.map(|c| c.map(|v| v as f32)))),
+            gl::FLOAT_MAT3 => value
+                .as_matrix::<3>()
+                .map(|value| self.set_uniform(name, value.map(|c| c.map(|v| v as f32)))),
+            gl::FLOAT_MAT4 => value
+                .as_matrix::<4>()
+                .map(|value| self.set_uniform(name, value.map(|c| c.map(|v| v as f32)))),
+            gl_type if is_sampler_type(gl_type) => value
+                .as_scalar()
+                .map(|value| self.set_uniform(name, Sampler(value as u32))),
+            _ => None,
+        };
+
+        match result {
+            Some(result) => result,
+            None => Err(UniformError::new(
+                format!(
+                    "uniform, \"{}\", is declared as a {} and cannot be set to {:?}",
+                    name,
+                    uniform_type_name(uniform.gl_type()),
+                    value
+                ),
+                UniformErrorKind::TypeMismatch,
+                None,
+            )),
+        }
+    }
+
+    // Checks that set_uniform_constant() would accept the value, without setting it, e.g. to
+    // validate the uniforms of a material before it is used with the program. The program does
+    // not need to be in use.
+    pub fn check_uniform_constant<S: AsRef<str>>(
+        &self,
+        name: S,
+        value: &UniformConstant,
+    ) -> Result<(), UniformError> {
+        let name = name.as_ref();
+        match self.uniforms.get(name) {
+            Some(uniform) if value.is_compatible_with(uniform.gl_type()) => Ok(()),
+            Some(uniform) => Err(UniformError::new(
+                format!(
+                    "uniform, \"{}\", is declared as a {} and cannot be set to {:?}",
+                    name,
+                    uniform_type_name(uniform.gl_type()),
+                    value
+                ),
+                UniformErrorKind::TypeMismatch,
+                None,
+            )),
+            None => Err(UniformError::new(
+                format!(
+                    "uniform, \"{}\", is not an active uniform of the program",
+                    name
+                ),
+                UniformErrorKind::NotFound,
+                None,
+            )),
+        }
+    }
+
+    // Dropping the program on failure takes care of deleting it.
+    fn from_linked_program(
+        program_id: gl::types::GLuint,
+        shaders: Vec<Handle<Shader>>,
+        manifest_file_path: Option<PathBuf>,
+        default_uniforms: Vec<(String, UniformConstant)>,
+    ) -> Result<Self, ProgramError> {
+        let mut program: Self = Self {
+            id: program_id,
+            shaders,
+            manifest_file_path,
+            pending_manifest: None,
+            default_uniforms,
+            attributes: reflect_active_attributes(program_id),
+            uniforms: reflect_active_uniforms(program_id),
+            uniform_blocks: HashMap::new(),
+            is_stale: false,
+        };
+
+        program.uniform_blocks = Self::bind_uniform_blocks(program_id)?;
+        program.set_default_uniforms()?;
+
+        Ok(program)
+    }
+
+    // Uniforms can only be set on the program in use, so the program is used for a moment and
+    // the previous one is restored afterwards.
+    fn set_default_uniforms(&self) -> Result<(), ProgramError> {
+        if self.default_uniforms.is_empty() {
+            return Ok(());
+        }
+
+        let mut prev_program_id: gl::types::GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut prev_program_id);
+            gl::UseProgram(self.id);
+        };
+
+        let mut result: Result<(), ProgramError> = Ok(());
+        for (name, value) in &self.default_uniforms {
+            match self.set_uniform_constant(name, value) {
+                Ok(_) => {}
+                // Uniforms the shaders do not use get optimized out by the driver.
+                Err(error) if error.kind() == UniformErrorKind::NotFound => {}
+                Err(error) => {
+                    result = Err(ProgramError::new(
+                        format!("unable to set default value of uniform, \"{}\"", name),
+                        ProgramErrorKind::DefaultUniformError,
+                        Some(Box::new(error)),
+                    ));
+                    break;
+                }
+            }
+        }
+
+        unsafe {
+            gl::UseProgram(prev_program_id as gl::types::GLuint);
+        };
+
+        result
+    }
+
+    fn bind_uniform_blocks(
+        program_id: gl::types::GLuint,
+    ) -> Result<HashMap<String, UniformBlockInfo>, ProgramError> {
+        match bind_active_uniform_blocks(program_id) {
+            Ok(uniform_blocks) => Ok(uniform_blocks),
+            Err(error) => Err(ProgramError::new(
+                "unable to bind the uniform blocks of the program",
+                ProgramErrorKind::UniformBlockBindingError,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+
+    fn link(
+        shader_manager: &AssetManager<Shader>,
+        shader_handles: &[Handle<Shader>],
+    ) -> Result<gl::types::GLuint, ProgramError> {
+        let mut shaders: Vec<&Shader> = vec![];
+        for shader_handle in shader_handles {
+            match shader_manager.get(shader_handle) {
+                Some(shader) => shaders.push(shader),
+                None => {
+                    return Err(ProgramError::new(
+                        format!(
+                            "shader handle, {:?}, is stale, since its shader has been destroyed",
+                            shader_handle
+                        ),
+                        ProgramErrorKind::StaleShaderHandle,
+                        None,
+                    ))
+                }
+            }
+        }
+
+        Self::link_shaders(&shaders)
+    }
+
+    fn link_shaders(shaders: &[&Shader]) -> Result<gl::types::GLuint, ProgramError> {
+        let program_id: gl::types::GLuint = unsafe { gl::CreateProgram() };
+        for shader in shaders {
+            for stage in shader.get_stages() {
+                unsafe {
+                    gl::AttachShader(program_id, stage.get_shader_id());
+                };
+            }
+        }
+
+        unsafe {
+            gl::LinkProgram(program_id);
+        };
+
+        for shader in shaders {
+            for stage in shader.get_stages() {
+                unsafe {
+                    gl::DetachShader(program_id, stage.get_shader_id());
+                };
+            }
+        }
+
+        let mut success: gl::types::GLint = 1;
+        unsafe {
+            gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
+        };
+
+        if success == 0 {
+            let mut error_msg_length: gl::types::GLint = 0;
+            unsafe {
+                gl::GetProgramiv(program_id, gl::INFO_LOG_LENGTH, &mut error_msg_length);
+            }
+
+            let error_msg: CString = c_bridge::create_sized_cstring(error_msg_length as usize);
+            unsafe {
+                gl::GetProgramInfoLog(
+                    program_id,
+                    error_msg_length,
+                    ptr::null_mut(),
+                    error_msg.as_ptr() as *mut gl::types::GLchar,
+                );
+                gl::DeleteProgram(program_id);
+            };
+
+            let info_log: String = error_msg
+                .to_string_lossy()
+                .trim_end_matches('\0')
+                .to_string();
+            let diagnostics: Vec<String> = info_log
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .map(String::from)
+                .collect();
+
+            return Err(ProgramError::new(
+                format!("unable to link program:\n{}", info_log),
+                ProgramErrorKind::LinkError,
+                None,
+            )
+            .with_diagnostics(diagnostics));
+        }
+
+        Ok(program_id)
+    }
+}
+
+// Programs are either loaded from .program manifests, or linked from shader assets and added to
+// their asset manager with AssetRegistry::add_asset(). Either way, they are relinked whenever
+// one of their shaders is reloaded. The stages of manifest programs are loaded as shader assets
+// through the asset registry, which also reloads the program when its manifest changes.
+impl Asset for Program {
+    fn new<S: AsRef<str>>(id: S, file_path: &Path) -> Result<Self, AssetError> {
+        match Self::from_manifest_file(file_path) {
+            Ok(program) => Ok(program),
+            Err(error) => Err(AssetError::new(
+                format!("unable to load program, \"{}\"", id.as_ref()),
+                AssetErrorKind::LoadingFailed,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+
+    fn supported_file_extensions() -> &'static [&'static str] {
+        &["program"]
+    }
+
+    fn reload(&mut self) -> Result<(), AssetError> {
+        let manifest_file_path = match &self.manifest_file_path {
+            Some(manifest_file_path) => manifest_file_path,
+            None => {
+                return Err(AssetError::new(
+                    "programs linked from shader assets can only be relinked, with rebuild()",
+                    AssetErrorKind::ReloadingFailed,
+                    None,
+                ))
+            }
+        };
+
+        // The new manifest is only linked by load_asset_dependencies(), so a broken manifest, or
+        // stage, leaves the current program in place.
+        match ProgramManifest::from_file(manifest_file_path) {
+            Ok(manifest) => {
+                self.pending_manifest = Some(manifest);
+                self.is_stale = false;
+                Ok(())
+            }
+            Err(error) => Err(AssetError::new(
+                format!(
+                    "unable to hot-reload program from {}",
+                    manifest_file_path.to_string_lossy()
+                ),
+                AssetErrorKind::ReloadingFailed,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+
+    fn destroy(&mut self) -> Result<(), AssetError> {
+        unsafe {
+            gl::DeleteProgram(self.id);
+        };
+
+        self.id = 0;
+        self.attributes.clear();
+        self.uniforms.clear();
+        self.uniform_blocks.clear();
+        self.shaders.clear();
+        self.pending_manifest = None;
+
+        Ok(())
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.id != 0
+    }
+
+    fn is_stale(&self) -> bool {
+        self.is_stale
+    }
+
+    fn mark_stale(&mut self) {
+        self.is_stale = true;
+    }
+
+    fn get_src_file_path(&self) -> &Path {
+        match &self.manifest_file_path {
+            Some(manifest_file_path) => manifest_file_path.as_path(),
+            None => Path::new(""),
+        }
+    }
+
+    fn get_asset_dependencies(&self) -> Vec<AssetKey> {
+        self.shaders.iter().map(AssetKey::of).collect()
+    }
+
+    fn load_asset_dependencies(&mut self, assets: &mut AssetRegistry) -> Result<(), AssetError> {
+        let (manifest_file_path, manifest) =
+            match (&self.manifest_file_path, self.pending_manifest.take()) {
+                (Some(manifest_file_path), Some(manifest)) => (manifest_file_path, manifest),
+                _ => return Ok(()),
+            };
+
+        let shader_manager = match assets.manager_mut::<Shader>() {
+            Some(shader_manager) => shader_manager,
+            None => {
+                return Err(AssetError::new(
+                    "no shader asset manager is registered",
+                    AssetErrorKind::LoadingFailed,
+                    None,
+                ))
+            }
+        };
+
+        // The current program gets deleted as it is dropped, only once the new one is ready.
+        match Self::link_manifest(shader_manager, manifest_file_path, &manifest) {
+            Ok(program) => {
+                *self = program;
+                Ok(())
+            }
+            Err(error) => Err(AssetError::new(
+                format!(
+                    "unable to link program from {}",
+                    manifest_file_path.to_string_lossy()
+                ),
+                AssetErrorKind::LoadingFailed,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+
+    fn rebuild(&mut self, assets: &AssetRegistry) -> Result<(), AssetError> {
+        let shader_manager = match assets.manager::<Shader>() {
+            Some(shader_manager) => shader_manager,
+            None => {
+                return Err(AssetError::new(
+                    "no shader asset manager is registered",
+                    AssetErrorKind::ReloadingFailed,
+                    None,
+                ))
+            }
+        };
+
+        match self.relink(shader_manager) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(AssetError::new(
+                "unable to relink program",
+                AssetErrorKind::ReloadingFailed,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::assets::{normalize_path, ShaderDefine};
+use crate::graphics::UniformConstant;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ProgramManifestErrorKind {
+    ReadError,
+    ParseError,
+    NoStages,
+    InvalidUniformValue,
+}
+
+#[derive(Debug)]
+pub struct ProgramManifestError {
+    source: Option<Box<dyn Error + 'static>>,
+    message: String,
+    kind: ProgramManifestErrorKind,
+}
+
+impl ProgramManifestError {
+    pub fn new(
+        message: impl AsRef<str>,
+        kind: ProgramManifestErrorKind,
+        source: Option<Box<dyn Error + 'static>>,
+    ) -> ProgramManifestError {
+        ProgramManifestError {
+            source,
+            message: message.as_ref().into(),
+            kind,
+        }
+    }
+
+    pub fn kind(&self) -> ProgramManifestErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for ProgramManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ProgramManifestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref()
+    }
+}
+
+// The contents of a .program file, written in TOML:
+//
+//     stages = ["triangle.vert", "triangle.frag"]
+//     defines = ["USE_FOG", "NUM_LIGHTS=4"]
+//
+//     [uniforms]
+//     tint = [1.0, 0.5, 0.5]
+//     elapsedTime = 0.0
+//
+// Stage paths are relative to the directory of the .program file. Every stage is compiled with
+// the defines, and the uniforms are set right after the program is linked. Matrices are given
+// as arrays of columns.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawProgramManifest {
+    stages: Vec<PathBuf>,
+    #[serde(default)]
+    defines: Vec<String>,
+    #[serde(default)]
+    uniforms: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProgramManifest {
+    stage_file_paths: Vec<PathBuf>,
+    defines: Vec<ShaderDefine>,
+    uniforms: Vec<(String, UniformConstant)>,
+}
+
+impl ProgramManifest {
+    pub fn from_file(file_path: &Path) -> Result<Self, ProgramManifestError> {
+        let contents = match fs::read_to_string(file_path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                return Err(ProgramManifestError::new(
+                    format!(
+                        "unable to read program manifest from {}",
+                        file_path.to_string_lossy()
+                    ),
+                    ProgramManifestErrorKind::ReadError,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        let base_dir_path: &Path = file_path.parent().unwrap_or(Path::new(""));
+        Self::parse(contents.as_str(), base_dir_path).map_err(|error| {
+            ProgramManifestError::new(
+                format!(
+                    "unable to parse program manifest from {}",
+                    file_path.to_string_lossy()
+                ),
+                error.kind(),
+                Some(Box::new(error)),
+            )
+        })
+    }
+
+    // Relative stage paths are resolved against `base_dir_path`.
+    pub fn parse(contents: &str, base_dir_path: &Path) -> Result<Self, ProgramManifestError> {
+        let raw_manifest: RawProgramManifest = match toml::from_str(contents) {
+            Ok(raw_manifest) => raw_manifest,
+            Err(error) => {
+                return Err(ProgramManifestError::new(
+                    "program manifest is not valid",
+                    ProgramManifestErrorKind::ParseError,
+                    Some(Box::new(error)),
+                ))
+            }
+        };
+
+        if raw_manifest.stages.is_empty() {
+            return Err(ProgramManifestError::new(
+                "program manifest does not list any shader stages",
+                ProgramManifestErrorKind::NoStages,
+                None,
+            ));
+        }
+
+        let mut uniforms: Vec<(String, UniformConstant)> = vec![];
+        for (name, value) in raw_manifest.uniforms {
+            match UniformConstant::from_toml_value(&value) {
+                Some(constant) => uniforms.push((name, constant)),
+                None => {
+                    return Err(ProgramManifestError::new(
+                        format!(
+                            "value of uniform, \"{}\", must be a boolean, a number, an array of \
+                            numbers, or an array of columns of numbers, but is {}",
+                            name, value
+                        ),
+                        ProgramManifestErrorKind::InvalidUniformValue,
+                        None,
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            stage_file_paths: raw_manifest
+                .stages
+                .iter()
+                .map(|stage| normalize_path(&base_dir_path.join(stage)))
+                .collect(),
+            defines: raw_manifest
+                .defines
+                .iter()
+                .map(|define| ShaderDefine::from(define.as_str()))
+                .collect(),
+            uniforms,
+        })
+    }
+
+    pub fn stage_file_paths(&self) -> &[PathBuf] {
+        self.stage_file_paths.as_slice()
+    }
+
+    pub fn defines(&self) -> &[ShaderDefine] {
+        self.defines.as_slice()
+    }
+
+    pub fn uniforms(&self) -> &[(String, UniformConstant)] {
+        self.uniforms.as_slice()
+    }
+}
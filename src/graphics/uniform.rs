@@ -0,0 +1,500 @@
+extern crate gl;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::ffi::CString;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum UniformErrorKind {
+    NotFound,
+    TypeMismatch,
+    TooManyElements,
+}
+
+#[derive(Debug)]
+pub struct UniformError {
+    source: Option<Box<dyn Error + 'static>>,
+    message: String,
+    kind: UniformErrorKind,
+}
+
+impl UniformError {
+    pub fn new(
+        message: impl AsRef<str>,
+        kind: UniformErrorKind,
+        source: Option<Box<dyn Error + 'static>>,
+    ) -> UniformError {
+        UniformError {
+            source,
+            message: message.as_ref().into(),
+            kind,
+        }
+    }
+
+    pub fn kind(&self) -> UniformErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for UniformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for UniformError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref()
+    }
+}
+
+// An active uniform of a linked program, as reported by glGetActiveUniform().
+#[derive(Clone, Debug)]
+pub struct UniformInfo {
+    name: String,
+    location: gl::types::GLint,
+    gl_type: gl::types::GLenum,
+    size: gl::types::GLint,
+}
+
+impl UniformInfo {
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn location(&self) -> gl::types::GLint {
+        self.location
+    }
+
+    pub fn gl_type(&self) -> gl::types::GLenum {
+        self.gl_type
+    }
+
+    // Number of array elements, starting from this uniform, that can be written. This is 1 for
+    // non-array uniforms.
+    pub fn size(&self) -> gl::types::GLint {
+        self.size
+    }
+}
+
+// A texture unit to be assigned to a sampler uniform.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Sampler(pub u32);
+
+pub trait UniformValue: Sized {
+    fn is_compatible_with(gl_type: gl::types::GLenum) -> bool;
+
+    // Writes the values to the uniform at the given location of the program currently in use.
+    unsafe fn upload(location: gl::types::GLint, values: &[Self]);
+}
+
+macro_rules! impl_uniform_value {
+    ($value_type:ty, $gl_type:expr, $component_type:ty, $gl_func:ident) => {
+        impl UniformValue for $value_type {
+            fn is_compatible_with(gl_type: gl::types::GLenum) -> bool {
+                gl_type == $gl_type
+            }
+
+            unsafe fn upload(location: gl::types::GLint, values: &[Self]) {
+                gl::$gl_func(
+                    location,
+                    values.len() as gl::types::GLsizei,
+                    values.as_ptr() as *const $component_type,
+                );
+            }
+        }
+    };
+}
+
+macro_rules! impl_uniform_matrix_value {
+    ($value_type:ty, $gl_type:expr, $gl_func:ident) => {
+        impl UniformValue for $value_type {
+            fn is_compatible_with(gl_type: gl::types::GLenum) -> bool {
+                gl_type == $gl_type
+            }
+
+            // Matrices are expected to be in column-major order, like GLSL.
+            unsafe fn upload(location: gl::types::GLint, values: &[Self]) {
+                gl::$gl_func(
+                    location,
+                    values.len() as gl::types::GLsizei,
+                    gl::FALSE,
+                    values.as_ptr() as *const f32,
+                );
+            }
+        }
+    };
+}
+
+impl_uniform_value!(f32, gl::FLOAT, f32, Uniform1fv);
+impl_uniform_value!([f32; 2], gl::FLOAT_VEC2, f32, Uniform2fv);
+impl_uniform_value!([f32; 3], gl::FLOAT_VEC3, f32, Uniform3fv);
+impl_uniform_value!([f32; 4], gl::FLOAT_VEC4, f32, Uniform4fv);
+impl_uniform_value!(i32, gl::INT, i32, Uniform1iv);
+impl_uniform_value!([i32; 2], gl::INT_VEC2, i32, Uniform2iv);
+impl_uniform_value!([i32; 3], gl::INT_VEC3, i32, Uniform3iv);
+impl_uniform_value!([i32; 4], gl::INT_VEC4, i32, Uniform4iv);
+impl_uniform_value!(u32, gl::UNSIGNED_INT, u32, Uniform1uiv);
+impl_uniform_value!([u32; 2], gl::UNSIGNED_INT_VEC2, u32, Uniform2uiv);
+impl_uniform_value!([u32; 3], gl::UNSIGNED_INT_VEC3, u32, Uniform3uiv);
+impl_uniform_value!([u32; 4], gl::UNSIGNED_INT_VEC4, u32, Uniform4uiv);
+impl_uniform_matrix_value!([[f32; 2]; 2], gl::FLOAT_MAT2, UniformMatrix2fv);
+impl_uniform_matrix_value!([[f32; 3]; 3], gl::FLOAT_MAT3, UniformMatrix3fv);
+impl_uniform_matrix_value!([[f32; 4]; 4], gl::FLOAT_MAT4, UniformMatrix4fv);
+
+impl UniformValue for bool {
+    fn is_compatible_with(gl_type: gl::types::GLenum) -> bool {
+        gl_type == gl::BOOL
+    }
+
+    unsafe fn upload(location: gl::types::GLint, values: &[Self]) {
+        let converted_values: Vec<gl::types::GLint> = values
+            .iter()
+            .map(|value| *value as gl::types::GLint)
+            .collect();
+        gl::Uniform1iv(
+            location,
+            converted_values.len() as gl::types::GLsizei,
+            converted_values.as_ptr(),
+        );
+    }
+}
+
+// Boolean vectors are uploaded as integer vectors, the same way as booleans.
+macro_rules! impl_uniform_bool_vector_value {
+    ($value_type:ty, $gl_type:expr, $gl_func:ident) => {
+        impl UniformValue for $value_type {
+            fn is_compatible_with(gl_type: gl::types::GLenum) -> bool {
+                gl_type == $gl_type
+            }
+
+            unsafe fn upload(location: gl::types::GLint, values: &[Self]) {
+                let converted_values: Vec<gl::types::GLint> = values
+                    .iter()
+                    .flatten()
+                    .map(|value| *value as gl::types::GLint)
+                    .collect();
+                gl::$gl_func(
+                    location,
+                    values.len() as gl::types::GLsizei,
+                    converted_values.as_ptr(),
+                );
+            }
+        }
+    };
+}
+
+impl_uniform_bool_vector_value!([bool; 2], gl::BOOL_VEC2, Uniform2iv);
+impl_uniform_bool_vector_value!([bool; 3], gl::BOOL_VEC3, Uniform3iv);
+impl_uniform_bool_vector_value!([bool; 4], gl::BOOL_VEC4, Uniform4iv);
+
+impl UniformValue for Sampler {
+    fn is_compatible_with(gl_type: gl::types::GLenum) -> bool {
+        is_sampler_type(gl_type)
+    }
+
+    unsafe fn upload(location: gl::types::GLint, values: &[Self]) {
+        let texture_units: Vec<gl::types::GLint> = values
+            .iter()
+            .map(|sampler| sampler.0 as gl::types::GLint)
+            .collect();
+        gl::Uniform1iv(
+            location,
+            texture_units.len() as gl::types::GLsizei,
+            texture_units.as_ptr(),
+        );
+    }
+}
+
+// A uniform value whose type is only known at runtime, such as a value read from a file. It is
+// converted to the type of the uniform it gets set to (see Program::set_uniform_constant()), so
+// an integer can be set to a float uniform, for example, or to a sampler as a texture unit.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UniformConstant {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Vector(Vec<f64>),
+    BoolVector(Vec<bool>),
+    // Column-major, like GLSL.
+    Matrix(Vec<Vec<f64>>),
+}
+
+impl UniformConstant {
+    // Converts a value read from a TOML file (e.g. a .program manifest). Matrices are given as
+    // arrays of columns. Returns None for anything that is not a boolean, a number, an array of
+    // booleans or numbers, or an array of arrays of numbers.
+    pub fn from_toml_value(value: &toml::Value) -> Option<Self> {
+        match value {
+            toml::Value::Boolean(value) => Some(Self::Bool(*value)),
+            toml::Value::Integer(value) => Some(Self::Int(*value)),
+            toml::Value::Float(value) => Some(Self::Float(*value)),
+            toml::Value::Array(values) if values.iter().all(|value| value.is_array()) => {
+                let columns: Option<Vec<Vec<f64>>> = values
+                    .iter()
+                    .map(|column| match column {
+                        toml::Value::Array(column) => Self::toml_values_to_numbers(column),
+                        _ => None,
+                    })
+                    .collect();
+                columns.map(Self::Matrix)
+            }
+            toml::Value::Array(values) if values.iter().all(|value| value.is_bool()) => values
+                .iter()
+                .map(toml::Value::as_bool)
+                .collect::<Option<Vec<bool>>>()
+                .map(Self::BoolVector),
+            toml::Value::Array(values) => Self::toml_values_to_numbers(values).map(Self::Vector),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_scalar(&self) -> Option<f64> {
+        match self {
+            Self::Int(value) => Some(*value as f64),
+            Self::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_vector<const N: usize>(&self) -> Option<[f64; N]> {
+        match self {
+            Self::Vector(values) => values.as_slice().try_into().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool_vector<const N: usize>(&self) -> Option<[bool; N]> {
+        match self {
+            Self::BoolVector(values) => values.as_slice().try_into().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_matrix<const N: usize>(&self) -> Option<[[f64; N]; N]> {
+        let columns = match self {
+            Self::Matrix(columns) if columns.len() == N => columns,
+            _ => return None,
+        };
+
+        let mut matrix = [[0.0f64; N]; N];
+        for (column_index, column) in columns.iter().enumerate() {
+            matrix[column_index] = column.as_slice().try_into().ok()?;
+        }
+
+        Some(matrix)
+    }
+
+    // Whether the value can be set to a uniform of the given type, converting it the same way
+    // Program::set_uniform_constant() does.
+    pub fn is_compatible_with(&self, gl_type: gl::types::GLenum) -> bool {
+        match gl_type {
+            gl::FLOAT | gl::INT | gl::UNSIGNED_INT => self.as_scalar().is_some(),
+            gl::FLOAT_VEC2 | gl::INT_VEC2 | gl::UNSIGNED_INT_VEC2 => {
+                self.as_vector::<2>().is_some()
+            }
+            gl::FLOAT_VEC3 | gl::INT_VEC3 | gl::UNSIGNED_INT_VEC3 => {
+                self.as_vector::<3>().is_some()
+            }
+            gl::FLOAT_VEC4 | gl::INT_VEC4 | gl::UNSIGNED_INT_VEC4 => {
+                self.as_vector::<4>().is_some()
+            }
+            gl::BOOL => self.as_bool().is_some(),
+            gl::BOOL_VEC2 => self.as_bool_vector::<2>().is_some(),
+            gl::BOOL_VEC3 => self.as_bool_vector::<3>().is_some(),
+            gl::BOOL_VEC4 => self.as_bool_vector::<4>().is_some(),
+            gl::FLOAT_MAT2 => self.as_matrix::<2>().is_some(),
+            gl::FLOAT_MAT3 => self.as_matrix::<3>().is_some(),
+            gl::FLOAT_MAT4 => self.as_matrix::<4>().is_some(),
+            gl_type if is_sampler_type(gl_type) => self.as_scalar().is_some(),
+            _ => false,
+        }
+    }
+
+    fn toml_values_to_numbers(values: &[toml::Value]) -> Option<Vec<f64>> {
+        values
+            .iter()
+            .map(|value| match value {
+                toml::Value::Integer(value) => Some(*value as f64),
+                toml::Value::Float(value) => Some(*value),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+pub fn is_sampler_type(gl_type: gl::types::GLenum) -> bool {
+    matches!(
+        gl_type,
+        gl::SAMPLER_1D
+            | gl::SAMPLER_2D
+            | gl::SAMPLER_3D
+            | gl::SAMPLER_CUBE
+            | gl::SAMPLER_1D_SHADOW
+            | gl::SAMPLER_2D_SHADOW
+            | gl::SAMPLER_1D_ARRAY
+            | gl::SAMPLER_2D_ARRAY
+            | gl::SAMPLER_1D_ARRAY_SHADOW
+            | gl::SAMPLER_2D_ARRAY_SHADOW
+            | gl::SAMPLER_2D_MULTISAMPLE
+            | gl::SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::SAMPLER_CUBE_SHADOW
+            | gl::SAMPLER_BUFFER
+            | gl::SAMPLER_2D_RECT
+            | gl::SAMPLER_2D_RECT_SHADOW
+            | gl::INT_SAMPLER_1D
+            | gl::INT_SAMPLER_2D
+            | gl::INT_SAMPLER_3D
+            | gl::INT_SAMPLER_CUBE
+            | gl::INT_SAMPLER_1D_ARRAY
+            | gl::INT_SAMPLER_2D_ARRAY
+            | gl::INT_SAMPLER_2D_MULTISAMPLE
+            | gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::INT_SAMPLER_BUFFER
+            | gl::INT_SAMPLER_2D_RECT
+            | gl::UNSIGNED_INT_SAMPLER_1D
+            | gl::UNSIGNED_INT_SAMPLER_2D
+            | gl::UNSIGNED_INT_SAMPLER_3D
+            | gl::UNSIGNED_INT_SAMPLER_CUBE
+            | gl::UNSIGNED_INT_SAMPLER_1D_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_2D_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE
+            | gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_BUFFER
+            | gl::UNSIGNED_INT_SAMPLER_2D_RECT
+    )
+}
+
+// Returns the GLSL name of a uniform type, for use in error messages.
+pub fn uniform_type_name(gl_type: gl::types::GLenum) -> &'static str {
+    match gl_type {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::BOOL => "bool",
+        gl::BOOL_VEC2 => "bvec2",
+        gl::BOOL_VEC3 => "bvec3",
+        gl::BOOL_VEC4 => "bvec4",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::FLOAT_MAT2x3 => "mat2x3",
+        gl::FLOAT_MAT2x4 => "mat2x4",
+        gl::FLOAT_MAT3x2 => "mat3x2",
+        gl::FLOAT_MAT3x4 => "mat3x4",
+        gl::FLOAT_MAT4x2 => "mat4x2",
+        gl::FLOAT_MAT4x3 => "mat4x3",
+        gl::SAMPLER_1D => "sampler1D",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl_type if is_sampler_type(gl_type) => "sampler",
+        _ => "unknown type",
+    }
+}
+
+// Queries the active uniforms of a linked program. Array uniforms are registered under their
+// base name (e.g. "lights") as well as under each of their elements (e.g. "lights[2]"). Uniforms
+// that are part of a uniform block have no location and are not included.
+pub fn reflect_active_uniforms(program_id: gl::types::GLuint) -> HashMap<String, UniformInfo> {
+    let mut uniforms: HashMap<String, UniformInfo> = HashMap::new();
+
+    let mut num_uniforms: gl::types::GLint = 0;
+    let mut max_name_length: gl::types::GLint = 0;
+    unsafe {
+        gl::GetProgramiv(program_id, gl::ACTIVE_UNIFORMS, &mut num_uniforms);
+        gl::GetProgramiv(
+            program_id,
+            gl::ACTIVE_UNIFORM_MAX_LENGTH,
+            &mut max_name_length,
+        );
+    };
+
+    let mut name_buffer: Vec<u8> = vec![0; max_name_length.max(1) as usize];
+    for index in 0..num_uniforms {
+        let mut name_length: gl::types::GLsizei = 0;
+        let mut size: gl::types::GLint = 0;
+        let mut gl_type: gl::types::GLenum = 0;
+        unsafe {
+            gl::GetActiveUniform(
+                program_id,
+                index as gl::types::GLuint,
+                name_buffer.len() as gl::types::GLsizei,
+                &mut name_length,
+                &mut size,
+                &mut gl_type,
+                name_buffer.as_mut_ptr() as *mut gl::types::GLchar,
+            );
+        };
+
+        let name = String::from_utf8_lossy(&name_buffer[..name_length as usize]).into_owned();
+        let location = match get_uniform_location(program_id, name.as_str()) {
+            Some(location) if location >= 0 => location,
+            _ => continue,
+        };
+
+        // Array uniforms are reported with a "[0]" suffix.
+        let base_name = match name.strip_suffix("[0]") {
+            Some(base_name) => String::from(base_name),
+            None => name.clone(),
+        };
+
+        for element in 1..size {
+            let element_name = format!("{}[{}]", base_name, element);
+            if let Some(element_location) = get_uniform_location(program_id, &element_name) {
+                if element_location < 0 {
+                    continue;
+                }
+
+                uniforms.insert(
+                    element_name.clone(),
+                    UniformInfo {
+                        name: element_name,
+                        location: element_location,
+                        gl_type,
+                        size: size - element,
+                    },
+                );
+            }
+        }
+
+        let info = UniformInfo {
+            name: base_name.clone(),
+            location,
+            gl_type,
+            size,
+        };
+        if base_name != name {
+            uniforms.insert(name, info.clone());
+        }
+        uniforms.insert(base_name, info);
+    }
+
+    uniforms
+}
+
+fn get_uniform_location(program_id: gl::types::GLuint, name: &str) -> Option<gl::types::GLint> {
+    let name_cstring = CString::new(name).ok()?;
+    let location = unsafe { gl::GetUniformLocation(program_id, name_cstring.as_ptr()) };
+
+    Some(location)
+}
@@ -0,0 +1,632 @@
+extern crate gl;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::ffi::CString;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::{Mutex, OnceLock};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum UniformBufferErrorKind {
+    InvalidBlockName,
+    LayoutMismatch,
+    OutOfBindingPoints,
+    BindingRegistryPoisoned,
+}
+
+#[derive(Debug)]
+pub struct UniformBufferError {
+    source: Option<Box<dyn Error + 'static>>,
+    message: String,
+    kind: UniformBufferErrorKind,
+}
+
+impl UniformBufferError {
+    pub fn new(
+        message: impl AsRef<str>,
+        kind: UniformBufferErrorKind,
+        source: Option<Box<dyn Error + 'static>>,
+    ) -> UniformBufferError {
+        UniformBufferError {
+            source,
+            message: message.as_ref().into(),
+            kind,
+        }
+    }
+
+    pub fn kind(&self) -> UniformBufferErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for UniformBufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for UniformBufferError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Std140Type {
+    Float,
+    Int,
+    UInt,
+    Bool,
+    Vec2,
+    Vec3,
+    Vec4,
+    IVec2,
+    IVec3,
+    IVec4,
+    UVec2,
+    UVec3,
+    UVec4,
+    Mat2,
+    Mat3,
+    Mat4,
+}
+
+impl Std140Type {
+    // Base alignment and size of the type, following the std140 rules in section 7.6.2.2 of
+    // the OpenGL 4.6 specification. Matrices are stored as arrays of column vectors.
+    pub fn alignment_and_size(&self) -> (usize, usize) {
+        match self {
+            Std140Type::Float | Std140Type::Int | Std140Type::UInt | Std140Type::Bool => (4, 4),
+            Std140Type::Vec2 | Std140Type::IVec2 | Std140Type::UVec2 => (8, 8),
+            Std140Type::Vec3 | Std140Type::IVec3 | Std140Type::UVec3 => (16, 12),
+            Std140Type::Vec4 | Std140Type::IVec4 | Std140Type::UVec4 => (16, 16),
+            Std140Type::Mat2 => (16, 32),
+            Std140Type::Mat3 => (16, 48),
+            Std140Type::Mat4 => (16, 64),
+        }
+    }
+
+    // The type of a uniform block member as reported by glGetActiveUniformsiv, if it is one
+    // that can be mirrored in a Std140Block.
+    pub fn from_gl_type(gl_type: gl::types::GLenum) -> Option<Std140Type> {
+        match gl_type {
+            gl::FLOAT => Some(Std140Type::Float),
+            gl::INT => Some(Std140Type::Int),
+            gl::UNSIGNED_INT => Some(Std140Type::UInt),
+            gl::BOOL => Some(Std140Type::Bool),
+            gl::FLOAT_VEC2 => Some(Std140Type::Vec2),
+            gl::FLOAT_VEC3 => Some(Std140Type::Vec3),
+            gl::FLOAT_VEC4 => Some(Std140Type::Vec4),
+            gl::INT_VEC2 => Some(Std140Type::IVec2),
+            gl::INT_VEC3 => Some(Std140Type::IVec3),
+            gl::INT_VEC4 => Some(Std140Type::IVec4),
+            gl::UNSIGNED_INT_VEC2 => Some(Std140Type::UVec2),
+            gl::UNSIGNED_INT_VEC3 => Some(Std140Type::UVec3),
+            gl::UNSIGNED_INT_VEC4 => Some(Std140Type::UVec4),
+            gl::FLOAT_MAT2 => Some(Std140Type::Mat2),
+            gl::FLOAT_MAT3 => Some(Std140Type::Mat3),
+            gl::FLOAT_MAT4 => Some(Std140Type::Mat4),
+            _ => None,
+        }
+    }
+}
+
+// A member of a uniform block, along with the offset of its Rust counterpart.
+#[derive(Clone, Debug)]
+pub struct Std140Field {
+    name: &'static str,
+    kind: Std140Type,
+    array_length: Option<usize>,
+    offset: usize,
+}
+
+impl Std140Field {
+    pub fn new(
+        name: &'static str,
+        kind: Std140Type,
+        array_length: Option<usize>,
+        offset: usize,
+    ) -> Std140Field {
+        Std140Field {
+            name,
+            kind,
+            array_length,
+            offset,
+        }
+    }
+}
+
+// Describes a #[repr(C)] struct that mirrors a std140 uniform block. Use the `std140_block!`
+// macro to implement this.
+pub trait Std140Block: Copy {
+    fn std140_fields() -> Vec<Std140Field>;
+}
+
+// Implements Std140Block for a struct, e.g.:
+//
+//     std140_block!(CameraBlock {
+//         view: Mat4,
+//         light_positions: Vec4[4],
+//         time: Float,
+//     });
+macro_rules! std140_block {
+    (@array_length) => { None };
+    (@array_length $length:expr) => { Some($length) };
+    ($block_type:ty { $($field:ident : $kind:ident $([$length:expr])?),* $(,)? }) => {
+        impl $crate::graphics::Std140Block for $block_type {
+            fn std140_fields() -> Vec<$crate::graphics::Std140Field> {
+                vec![
+                    $(
+                        $crate::graphics::Std140Field::new(
+                            stringify!($field),
+                            $crate::graphics::Std140Type::$kind,
+                            $crate::graphics::std140_block!(@array_length $($length)?),
+                            std::mem::offset_of!($block_type, $field),
+                        )
+                    ),*
+                ]
+            }
+        }
+    };
+}
+
+pub(crate) use std140_block;
+
+fn round_up(value: usize, alignment: usize) -> usize {
+    value.div_ceil(alignment) * alignment
+}
+
+// Checks that the fields of the Rust struct are placed where std140 expects them to be, and
+// that the struct is large enough to cover the whole block.
+pub fn validate_std140_layout<T: Std140Block>() -> Result<(), UniformBufferError> {
+    let mut cursor: usize = 0;
+    for field in T::std140_fields() {
+        let (alignment, size) = field.kind.alignment_and_size();
+        let (alignment, size) = match field.array_length {
+            // Array elements are aligned and padded to the size of a vec4.
+            Some(length) => (16, round_up(size, 16) * length),
+            None => (alignment, size),
+        };
+
+        let expected_offset = round_up(cursor, alignment);
+        if field.offset != expected_offset {
+            return Err(UniformBufferError::new(
+                format!(
+                    "field, \"{}\", of {} is at offset {}, but std140 places it at offset {}",
+                    field.name,
+                    std::any::type_name::<T>(),
+                    field.offset,
+                    expected_offset
+                ),
+                UniformBufferErrorKind::LayoutMismatch,
+                None,
+            ));
+        }
+
+        cursor = expected_offset + size;
+    }
+
+    let block_size = round_up(cursor, 16);
+    if mem::size_of::<T>() < block_size {
+        return Err(UniformBufferError::new(
+            format!(
+                "{} is {} bytes, but its std140 block is {} bytes (add padding at the end)",
+                std::any::type_name::<T>(),
+                mem::size_of::<T>(),
+                block_size
+            ),
+            UniformBufferErrorKind::LayoutMismatch,
+            None,
+        ));
+    }
+
+    Ok(())
+}
+
+// Binding points are assigned per block name and kept for the lifetime of the application, so
+// that programs and buffers agree on them regardless of which one gets created first.
+fn binding_registry() -> &'static Mutex<HashMap<String, gl::types::GLuint>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, gl::types::GLuint>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub fn uniform_block_binding_point<S: AsRef<str>>(
+    block_name: S,
+) -> Result<gl::types::GLuint, UniformBufferError> {
+    let mut registry = match binding_registry().lock() {
+        Ok(registry) => registry,
+        Err(_) => {
+            return Err(UniformBufferError::new(
+                "uniform block binding registry is poisoned",
+                UniformBufferErrorKind::BindingRegistryPoisoned,
+                None,
+            ))
+        }
+    };
+
+    if let Some(binding_point) = registry.get(block_name.as_ref()) {
+        return Ok(*binding_point);
+    }
+
+    let mut max_bindings: gl::types::GLint = 0;
+    unsafe {
+        gl::GetIntegerv(gl::MAX_UNIFORM_BUFFER_BINDINGS, &mut max_bindings);
+    };
+
+    let binding_point = registry.len() as gl::types::GLuint;
+    if binding_point >= max_bindings as gl::types::GLuint {
+        return Err(UniformBufferError::new(
+            format!(
+                "no binding point left for uniform block, \"{}\" (maximum is {})",
+                block_name.as_ref(),
+                max_bindings
+            ),
+            UniformBufferErrorKind::OutOfBindingPoints,
+            None,
+        ));
+    }
+
+    registry.insert(block_name.as_ref().into(), binding_point);
+
+    Ok(binding_point)
+}
+
+// A member of an active uniform block, as placed by the program.
+#[derive(Clone, Debug)]
+pub struct UniformBlockMember {
+    name: String,
+    gl_type: gl::types::GLenum,
+    offset: gl::types::GLint,
+    array_size: gl::types::GLint,
+}
+
+impl UniformBlockMember {
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn gl_type(&self) -> gl::types::GLenum {
+        self.gl_type
+    }
+
+    pub fn offset(&self) -> gl::types::GLint {
+        self.offset
+    }
+
+    pub fn array_size(&self) -> gl::types::GLint {
+        self.array_size
+    }
+}
+
+// An active uniform block of a linked program.
+#[derive(Clone, Debug)]
+pub struct UniformBlockInfo {
+    name: String,
+    index: gl::types::GLuint,
+    data_size: gl::types::GLint,
+    binding_point: gl::types::GLuint,
+    members: Vec<UniformBlockMember>,
+}
+
+impl UniformBlockInfo {
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn index(&self) -> gl::types::GLuint {
+        self.index
+    }
+
+    pub fn data_size(&self) -> gl::types::GLint {
+        self.data_size
+    }
+
+    pub fn binding_point(&self) -> gl::types::GLuint {
+        self.binding_point
+    }
+
+    // Members of the block, in the order of their offsets.
+    pub fn members(&self) -> &[UniformBlockMember] {
+        self.members.as_slice()
+    }
+}
+
+// Queries the members of an active uniform block, sorted by offset.
+fn reflect_uniform_block_members(
+    program_id: gl::types::GLuint,
+    block_index: gl::types::GLuint,
+) -> Vec<UniformBlockMember> {
+    let mut num_members: gl::types::GLint = 0;
+    let mut max_name_length: gl::types::GLint = 0;
+    unsafe {
+        gl::GetActiveUniformBlockiv(
+            program_id,
+            block_index,
+            gl::UNIFORM_BLOCK_ACTIVE_UNIFORMS,
+            &mut num_members,
+        );
+        gl::GetProgramiv(
+            program_id,
+            gl::ACTIVE_UNIFORM_MAX_LENGTH,
+            &mut max_name_length,
+        );
+    };
+
+    if num_members <= 0 {
+        return vec![];
+    }
+
+    let mut indices: Vec<gl::types::GLint> = vec![0; num_members as usize];
+    unsafe {
+        gl::GetActiveUniformBlockiv(
+            program_id,
+            block_index,
+            gl::UNIFORM_BLOCK_ACTIVE_UNIFORM_INDICES,
+            indices.as_mut_ptr(),
+        );
+    };
+    let indices: Vec<gl::types::GLuint> = indices
+        .into_iter()
+        .map(|index| index as gl::types::GLuint)
+        .collect();
+
+    let mut gl_types: Vec<gl::types::GLint> = vec![0; indices.len()];
+    let mut offsets: Vec<gl::types::GLint> = vec![0; indices.len()];
+    let mut array_sizes: Vec<gl::types::GLint> = vec![0; indices.len()];
+    for (pname, values) in [
+        (gl::UNIFORM_TYPE, &mut gl_types),
+        (gl::UNIFORM_OFFSET, &mut offsets),
+        (gl::UNIFORM_SIZE, &mut array_sizes),
+    ] {
+        unsafe {
+            gl::GetActiveUniformsiv(
+                program_id,
+                indices.len() as gl::types::GLsizei,
+                indices.as_ptr(),
+                pname,
+                values.as_mut_ptr(),
+            );
+        };
+    }
+
+    let mut name_buffer: Vec<u8> = vec![0; max_name_length.max(1) as usize];
+    let mut members: Vec<UniformBlockMember> = indices
+        .iter()
+        .enumerate()
+        .map(|(i, index)| {
+            let mut name_length: gl::types::GLsizei = 0;
+            unsafe {
+                gl::GetActiveUniformName(
+                    program_id,
+                    *index,
+                    name_buffer.len() as gl::types::GLsizei,
+                    &mut name_length,
+                    name_buffer.as_mut_ptr() as *mut gl::types::GLchar,
+                );
+            };
+
+            UniformBlockMember {
+                name: String::from_utf8_lossy(&name_buffer[..name_length as usize]).into_owned(),
+                gl_type: gl_types[i] as gl::types::GLenum,
+                offset: offsets[i],
+                array_size: array_sizes[i],
+            }
+        })
+        .collect();
+    members.sort_by_key(|member| member.offset);
+
+    members
+}
+
+// Queries the active uniform blocks of a linked program and binds each of them to the binding
+// point registered for its name.
+pub fn bind_active_uniform_blocks(
+    program_id: gl::types::GLuint,
+) -> Result<HashMap<String, UniformBlockInfo>, UniformBufferError> {
+    let mut blocks: HashMap<String, UniformBlockInfo> = HashMap::new();
+
+    let mut num_blocks: gl::types::GLint = 0;
+    let mut max_name_length: gl::types::GLint = 0;
+    unsafe {
+        gl::GetProgramiv(program_id, gl::ACTIVE_UNIFORM_BLOCKS, &mut num_blocks);
+        gl::GetProgramiv(
+            program_id,
+            gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH,
+            &mut max_name_length,
+        );
+    };
+
+    let mut name_buffer: Vec<u8> = vec![0; max_name_length.max(1) as usize];
+    for index in 0..num_blocks as gl::types::GLuint {
+        let mut name_length: gl::types::GLsizei = 0;
+        let mut data_size: gl::types::GLint = 0;
+        unsafe {
+            gl::GetActiveUniformBlockName(
+                program_id,
+                index,
+                name_buffer.len() as gl::types::GLsizei,
+                &mut name_length,
+                name_buffer.as_mut_ptr() as *mut gl::types::GLchar,
+            );
+            gl::GetActiveUniformBlockiv(
+                program_id,
+                index,
+                gl::UNIFORM_BLOCK_DATA_SIZE,
+                &mut data_size,
+            );
+        };
+
+        let name = String::from_utf8_lossy(&name_buffer[..name_length as usize]).into_owned();
+        let binding_point = uniform_block_binding_point(name.as_str())?;
+        unsafe {
+            gl::UniformBlockBinding(program_id, index, binding_point);
+        };
+
+        blocks.insert(
+            name.clone(),
+            UniformBlockInfo {
+                name,
+                index,
+                data_size,
+                binding_point,
+                members: reflect_uniform_block_members(program_id, index),
+            },
+        );
+    }
+
+    Ok(blocks)
+}
+
+// A uniform buffer object holding a single std140 block that can be shared between programs.
+pub struct UniformBuffer<T: Std140Block> {
+    id: gl::types::GLuint,
+    block_name: String,
+    binding_point: gl::types::GLuint,
+    phantom: PhantomData<T>,
+}
+
+impl<T: Std140Block> Drop for UniformBuffer<T> {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteBuffers(1, &self.id) }
+    }
+}
+
+impl<T: Std140Block> UniformBuffer<T> {
+    pub fn new<S: AsRef<str>>(block_name: S, data: &T) -> Result<Self, UniformBufferError> {
+        if CString::new(block_name.as_ref()).is_err() {
+            return Err(UniformBufferError::new(
+                format!("invalid uniform block name, \"{}\"", block_name.as_ref()),
+                UniformBufferErrorKind::InvalidBlockName,
+                None,
+            ));
+        }
+
+        validate_std140_layout::<T>()?;
+
+        let binding_point = uniform_block_binding_point(block_name.as_ref())?;
+
+        let mut buffer_id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut buffer_id);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, buffer_id);
+            gl::BufferData(
+                gl::UNIFORM_BUFFER,
+                mem::size_of::<T>() as gl::types::GLsizeiptr,
+                data as *const T as *const gl::types::GLvoid,
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, binding_point, buffer_id);
+        };
+
+        Ok(Self {
+            id: buffer_id,
+            block_name: block_name.as_ref().into(),
+            binding_point,
+            phantom: PhantomData,
+        })
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    pub fn block_name(&self) -> &str {
+        self.block_name.as_str()
+    }
+
+    pub fn binding_point(&self) -> gl::types::GLuint {
+        self.binding_point
+    }
+
+    // Checks that T matches the block as a program sees it: the fields must line up with the
+    // members of the block, in type and offset, and T must cover the whole block, allowing for
+    // the padding at its end.
+    pub fn check_block(&self, block: &UniformBlockInfo) -> Result<(), UniformBufferError> {
+        if block.name() != self.block_name {
+            return Err(UniformBufferError::new(
+                format!(
+                    "uniform buffer of block, \"{}\", cannot be bound to block, \"{}\"",
+                    self.block_name,
+                    block.name()
+                ),
+                UniformBufferErrorKind::InvalidBlockName,
+                None,
+            ));
+        }
+
+        let fields = T::std140_fields();
+        if fields.len() != block.members().len() {
+            return Err(UniformBufferError::new(
+                format!(
+                    "{} has {} fields, but uniform block, \"{}\", of the program has {} members",
+                    std::any::type_name::<T>(),
+                    fields.len(),
+                    block.name(),
+                    block.members().len()
+                ),
+                UniformBufferErrorKind::LayoutMismatch,
+                None,
+            ));
+        }
+
+        for (field, member) in fields.iter().zip(block.members()) {
+            let matches_member = Std140Type::from_gl_type(member.gl_type()) == Some(field.kind)
+                && field.offset as gl::types::GLint == member.offset()
+                && field.array_length.unwrap_or(1) as gl::types::GLint == member.array_size();
+            if !matches_member {
+                return Err(UniformBufferError::new(
+                    format!(
+                        "field, \"{}\", of {} does not match member, \"{}\", of uniform block, \"{}\"",
+                        field.name,
+                        std::any::type_name::<T>(),
+                        member.name(),
+                        block.name()
+                    ),
+                    UniformBufferErrorKind::LayoutMismatch,
+                    None,
+                ));
+            }
+        }
+
+        let block_size = block.data_size().max(0) as usize;
+        let struct_size = mem::size_of::<T>();
+        if struct_size < block_size || struct_size > round_up(block_size, 16) {
+            return Err(UniformBufferError::new(
+                format!(
+                    "{} is {} bytes, but uniform block, \"{}\", of the program is {} bytes",
+                    std::any::type_name::<T>(),
+                    struct_size,
+                    block.name(),
+                    block_size
+                ),
+                UniformBufferErrorKind::LayoutMismatch,
+                None,
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn update(&self, data: &T) {
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, self.id);
+            gl::BufferSubData(
+                gl::UNIFORM_BUFFER,
+                0,
+                mem::size_of::<T>() as gl::types::GLsizeiptr,
+                data as *const T as *const gl::types::GLvoid,
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        };
+    }
+
+    // Re-attaches the buffer to its binding point, in case another buffer took it over.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, self.binding_point, self.id);
+        };
+    }
+}
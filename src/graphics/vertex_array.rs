@@ -0,0 +1,572 @@
+extern crate gl;
+
+use crate::graphics::{Buffer, BufferKind, Program};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::ffi::CString;
+use std::fmt;
+use std::ptr;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum VertexArrayErrorKind {
+    AttributeNotFound,
+    AttributeTypeMismatch,
+    InvalidLayout,
+    InvalidBuffer,
+}
+
+#[derive(Debug)]
+pub struct VertexArrayError {
+    source: Option<Box<dyn Error + 'static>>,
+    message: String,
+    kind: VertexArrayErrorKind,
+}
+
+impl VertexArrayError {
+    pub fn new(
+        message: impl AsRef<str>,
+        kind: VertexArrayErrorKind,
+        source: Option<Box<dyn Error + 'static>>,
+    ) -> VertexArrayError {
+        VertexArrayError {
+            source,
+            message: message.as_ref().into(),
+            kind,
+        }
+    }
+
+    pub fn kind(&self) -> VertexArrayErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for VertexArrayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for VertexArrayError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref()
+    }
+}
+
+// An active vertex attribute of a linked program, as reported by glGetActiveAttrib().
+#[derive(Clone, Debug)]
+pub struct AttributeInfo {
+    name: String,
+    location: gl::types::GLint,
+    gl_type: gl::types::GLenum,
+}
+
+impl AttributeInfo {
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn location(&self) -> gl::types::GLint {
+        self.location
+    }
+
+    pub fn gl_type(&self) -> gl::types::GLenum {
+        self.gl_type
+    }
+}
+
+// Queries the active vertex attributes of a linked program. Built-in attributes, such as
+// gl_VertexID, have no location and are not included.
+pub fn reflect_active_attributes(program_id: gl::types::GLuint) -> HashMap<String, AttributeInfo> {
+    let mut attributes: HashMap<String, AttributeInfo> = HashMap::new();
+
+    let mut num_attributes: gl::types::GLint = 0;
+    let mut max_name_length: gl::types::GLint = 0;
+    unsafe {
+        gl::GetProgramiv(program_id, gl::ACTIVE_ATTRIBUTES, &mut num_attributes);
+        gl::GetProgramiv(
+            program_id,
+            gl::ACTIVE_ATTRIBUTE_MAX_LENGTH,
+            &mut max_name_length,
+        );
+    };
+
+    let mut name_buffer: Vec<u8> = vec![0; max_name_length.max(1) as usize];
+    for index in 0..num_attributes as gl::types::GLuint {
+        let mut name_length: gl::types::GLsizei = 0;
+        let mut size: gl::types::GLint = 0;
+        let mut gl_type: gl::types::GLenum = 0;
+        unsafe {
+            gl::GetActiveAttrib(
+                program_id,
+                index,
+                name_buffer.len() as gl::types::GLsizei,
+                &mut name_length,
+                &mut size,
+                &mut gl_type,
+                name_buffer.as_mut_ptr() as *mut gl::types::GLchar,
+            );
+        };
+
+        let name = String::from_utf8_lossy(&name_buffer[..name_length as usize]).into_owned();
+        let name_cstring = match CString::new(name.as_str()) {
+            Ok(name_cstring) => name_cstring,
+            Err(_) => continue,
+        };
+        let location = unsafe { gl::GetAttribLocation(program_id, name_cstring.as_ptr()) };
+        if location < 0 {
+            continue;
+        }
+
+        attributes.insert(
+            name.clone(),
+            AttributeInfo {
+                name,
+                location,
+                gl_type,
+            },
+        );
+    }
+
+    attributes
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ComponentType {
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+    HalfFloat,
+    Float,
+}
+
+impl ComponentType {
+    pub fn gl_type(&self) -> gl::types::GLenum {
+        match self {
+            ComponentType::Byte => gl::BYTE,
+            ComponentType::UnsignedByte => gl::UNSIGNED_BYTE,
+            ComponentType::Short => gl::SHORT,
+            ComponentType::UnsignedShort => gl::UNSIGNED_SHORT,
+            ComponentType::Int => gl::INT,
+            ComponentType::UnsignedInt => gl::UNSIGNED_INT,
+            ComponentType::HalfFloat => gl::HALF_FLOAT,
+            ComponentType::Float => gl::FLOAT,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            ComponentType::Byte | ComponentType::UnsignedByte => 1,
+            ComponentType::Short | ComponentType::UnsignedShort | ComponentType::HalfFloat => 2,
+            ComponentType::Int | ComponentType::UnsignedInt | ComponentType::Float => 4,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !matches!(self, ComponentType::HalfFloat | ComponentType::Float)
+    }
+}
+
+// Describes where a single vertex attribute lives within a vertex buffer.
+#[derive(Clone, Debug)]
+pub struct VertexAttribute {
+    name: String,
+    component_type: ComponentType,
+    num_components: usize,
+    offset: usize,
+    normalized: bool,
+    divisor: u32,
+    location: Option<gl::types::GLuint>,
+}
+
+impl VertexAttribute {
+    pub fn new<S: AsRef<str>>(
+        name: S,
+        component_type: ComponentType,
+        num_components: usize,
+        offset: usize,
+    ) -> VertexAttribute {
+        VertexAttribute {
+            name: name.as_ref().into(),
+            component_type,
+            num_components,
+            offset,
+            normalized: false,
+            divisor: 0,
+            location: None,
+        }
+    }
+
+    // Maps integer components to [0, 1] (or [-1, 1] for signed types) in the shader.
+    pub fn normalized(mut self) -> VertexAttribute {
+        self.normalized = true;
+        self
+    }
+
+    // Advances the attribute once every `divisor` instances instead of once per vertex.
+    pub fn divisor(mut self, divisor: u32) -> VertexAttribute {
+        self.divisor = divisor;
+        self
+    }
+
+    // Binds the attribute to a fixed location, for use with VertexArray::with_fixed_locations().
+    pub fn at_location(mut self, location: gl::types::GLuint) -> VertexAttribute {
+        self.location = Some(location);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn size(&self) -> usize {
+        self.component_type.size() * self.num_components
+    }
+}
+
+// Describes the layout of the vertices in a single vertex buffer.
+#[derive(Clone, Debug)]
+pub struct VertexLayout {
+    stride: usize,
+    attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    pub fn new(stride: usize) -> VertexLayout {
+        VertexLayout {
+            stride,
+            attributes: vec![],
+        }
+    }
+
+    pub fn attribute(mut self, attribute: VertexAttribute) -> VertexLayout {
+        self.attributes.push(attribute);
+        self
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        self.attributes.as_slice()
+    }
+}
+
+// Returns whether the shader input is an integer type and the number of components it has.
+fn attribute_type_components(gl_type: gl::types::GLenum) -> Option<(bool, usize)> {
+    match gl_type {
+        gl::FLOAT => Some((false, 1)),
+        gl::FLOAT_VEC2 => Some((false, 2)),
+        gl::FLOAT_VEC3 => Some((false, 3)),
+        gl::FLOAT_VEC4 => Some((false, 4)),
+        gl::INT | gl::UNSIGNED_INT => Some((true, 1)),
+        gl::INT_VEC2 | gl::UNSIGNED_INT_VEC2 => Some((true, 2)),
+        gl::INT_VEC3 | gl::UNSIGNED_INT_VEC3 => Some((true, 3)),
+        gl::INT_VEC4 | gl::UNSIGNED_INT_VEC4 => Some((true, 4)),
+        _ => None,
+    }
+}
+
+fn validate_layout_attribute(
+    layout: &VertexLayout,
+    attribute: &VertexAttribute,
+) -> Result<(), VertexArrayError> {
+    if attribute.num_components == 0 || attribute.num_components > 4 {
+        return Err(VertexArrayError::new(
+            format!(
+                "vertex attribute, \"{}\", must have between 1 and 4 components",
+                attribute.name()
+            ),
+            VertexArrayErrorKind::InvalidLayout,
+            None,
+        ));
+    }
+
+    if layout.stride > 0 && attribute.offset + attribute.size() > layout.stride {
+        return Err(VertexArrayError::new(
+            format!(
+                "vertex attribute, \"{}\", does not fit within the vertex stride of {} bytes",
+                attribute.name(),
+                layout.stride
+            ),
+            VertexArrayErrorKind::InvalidLayout,
+            None,
+        ));
+    }
+
+    Ok(())
+}
+
+// Checks the attribute against the matching active attribute of the program, and returns the
+// location to bind it to and whether it feeds an integer input.
+fn validate_program_attribute(
+    program: &Program,
+    attribute: &VertexAttribute,
+) -> Result<(gl::types::GLuint, bool), VertexArrayError> {
+    let active_attribute = match program.get_attribute_info(attribute.name()) {
+        Some(active_attribute) => active_attribute,
+        None => {
+            return Err(VertexArrayError::new(
+                format!(
+                    "vertex attribute, \"{}\", is not an active attribute of the program",
+                    attribute.name()
+                ),
+                VertexArrayErrorKind::AttributeNotFound,
+                None,
+            ))
+        }
+    };
+
+    let (is_integer_input, num_input_components) =
+        match attribute_type_components(active_attribute.gl_type()) {
+            Some(type_components) => type_components,
+            None => {
+                return Err(VertexArrayError::new(
+                    format!(
+                        "vertex attribute, \"{}\", has a type that is not supported",
+                        attribute.name()
+                    ),
+                    VertexArrayErrorKind::AttributeTypeMismatch,
+                    None,
+                ))
+            }
+        };
+
+    if is_integer_input && (!attribute.component_type.is_integer() || attribute.normalized) {
+        return Err(VertexArrayError::new(
+            format!(
+                "vertex attribute, \"{}\", is an integer input, but its data is not plain \
+                integers",
+                attribute.name()
+            ),
+            VertexArrayErrorKind::AttributeTypeMismatch,
+            None,
+        ));
+    }
+
+    if attribute.num_components > num_input_components {
+        return Err(VertexArrayError::new(
+            format!(
+                "vertex attribute, \"{}\", has {} components in the shader, but {} were given",
+                attribute.name(),
+                num_input_components,
+                attribute.num_components
+            ),
+            VertexArrayErrorKind::AttributeTypeMismatch,
+            None,
+        ));
+    }
+
+    Ok((
+        active_attribute.location() as gl::types::GLuint,
+        is_integer_input,
+    ))
+}
+
+pub struct VertexArray {
+    id: gl::types::GLuint,
+    vertex_buffers: Vec<(Buffer, VertexLayout)>,
+    index_buffer: Option<Buffer>,
+    num_vertices: usize,
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteVertexArrays(1, &self.id) }
+    }
+}
+
+impl VertexArray {
+    // Attribute locations are taken from the program, so the vertex array must be rebuilt if
+    // the program gets relinked with different locations.
+    pub fn new(
+        program: &Program,
+        vertex_buffers: Vec<(Buffer, VertexLayout)>,
+        index_buffer: Option<Buffer>,
+    ) -> Result<Self, VertexArrayError> {
+        Self::build(vertex_buffers, index_buffer, |attribute| {
+            validate_program_attribute(program, attribute)
+        })
+    }
+
+    // Creates a vertex array without a program to validate against, binding every attribute to
+    // the location given to VertexAttribute::at_location(). Attributes are assumed to feed
+    // floating-point shader inputs.
+    pub fn with_fixed_locations(
+        vertex_buffers: Vec<(Buffer, VertexLayout)>,
+        index_buffer: Option<Buffer>,
+    ) -> Result<Self, VertexArrayError> {
+        Self::build(vertex_buffers, index_buffer, |attribute| {
+            match attribute.location {
+                Some(location) => Ok((location, false)),
+                None => Err(VertexArrayError::new(
+                    format!(
+                        "vertex attribute, \"{}\", has no fixed location",
+                        attribute.name()
+                    ),
+                    VertexArrayErrorKind::InvalidLayout,
+                    None,
+                )),
+            }
+        })
+    }
+
+    fn build<F>(
+        vertex_buffers: Vec<(Buffer, VertexLayout)>,
+        index_buffer: Option<Buffer>,
+        resolve_attribute: F,
+    ) -> Result<Self, VertexArrayError>
+    where
+        F: Fn(&VertexAttribute) -> Result<(gl::types::GLuint, bool), VertexArrayError>,
+    {
+        let mut num_vertices: Option<usize> = None;
+        let mut attribute_bindings: Vec<(usize, VertexAttribute, gl::types::GLuint, bool)> = vec![];
+        for (buffer_index, (buffer, layout)) in vertex_buffers.iter().enumerate() {
+            if buffer.kind() != BufferKind::Vertex {
+                return Err(VertexArrayError::new(
+                    "only vertex buffers can be used as vertex attribute sources",
+                    VertexArrayErrorKind::InvalidBuffer,
+                    None,
+                ));
+            }
+
+            for attribute in layout.attributes() {
+                validate_layout_attribute(layout, attribute)?;
+                let (location, is_integer_input) = resolve_attribute(attribute)?;
+                attribute_bindings.push((
+                    buffer_index,
+                    attribute.clone(),
+                    location,
+                    is_integer_input,
+                ));
+            }
+
+            let is_per_vertex = layout.attributes().iter().any(|a| a.divisor == 0);
+            let stride = match layout.stride() {
+                0 => layout.attributes().iter().map(|a| a.size()).sum(),
+                stride => stride,
+            };
+            if is_per_vertex && stride > 0 {
+                let buffer_vertices = buffer.size_in_bytes() / stride;
+                num_vertices =
+                    Some(num_vertices.map_or(buffer_vertices, |n| n.min(buffer_vertices)));
+            }
+        }
+
+        if let Some(index_buffer) = &index_buffer {
+            if index_buffer.kind() != BufferKind::Index || index_type(index_buffer).is_none() {
+                return Err(VertexArrayError::new(
+                    "index buffer must be an index buffer of u8, u16, or u32 indices",
+                    VertexArrayErrorKind::InvalidBuffer,
+                    None,
+                ));
+            }
+        }
+
+        let mut vertex_array_id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vertex_array_id);
+            gl::BindVertexArray(vertex_array_id);
+
+            for (buffer_index, attribute, location, is_integer_input) in &attribute_bindings {
+                let (buffer, layout) = &vertex_buffers[*buffer_index];
+                let offset = attribute.offset as *const gl::types::GLvoid;
+
+                buffer.bind();
+                if *is_integer_input {
+                    gl::VertexAttribIPointer(
+                        *location,
+                        attribute.num_components as gl::types::GLint,
+                        attribute.component_type.gl_type(),
+                        layout.stride() as gl::types::GLsizei,
+                        offset,
+                    );
+                } else {
+                    gl::VertexAttribPointer(
+                        *location,
+                        attribute.num_components as gl::types::GLint,
+                        attribute.component_type.gl_type(),
+                        attribute.normalized as gl::types::GLboolean,
+                        layout.stride() as gl::types::GLsizei,
+                        offset,
+                    );
+                }
+                gl::VertexAttribDivisor(*location, attribute.divisor);
+                gl::EnableVertexAttribArray(*location);
+            }
+
+            if let Some(index_buffer) = &index_buffer {
+                index_buffer.bind();
+            }
+
+            gl::BindVertexArray(0);
+        };
+
+        Ok(Self {
+            id: vertex_array_id,
+            vertex_buffers,
+            index_buffer,
+            num_vertices: num_vertices.unwrap_or(0),
+        })
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    pub fn vertex_buffers(&self) -> &[(Buffer, VertexLayout)] {
+        self.vertex_buffers.as_slice()
+    }
+
+    pub fn index_buffer(&self) -> Option<&Buffer> {
+        self.index_buffer.as_ref()
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindVertexArray(self.id);
+        };
+    }
+
+    // Draws every vertex, or every index if the vertex array has an index buffer.
+    pub fn draw(&self, mode: gl::types::GLenum) {
+        self.draw_instanced(mode, 1);
+    }
+
+    // The vertex array is unbound afterwards, so that buffers bound later on cannot end up in
+    // it.
+    pub fn draw_instanced(&self, mode: gl::types::GLenum, num_instances: usize) {
+        self.bind();
+        unsafe {
+            match &self.index_buffer {
+                Some(index_buffer) => gl::DrawElementsInstanced(
+                    mode,
+                    index_buffer.len() as gl::types::GLsizei,
+                    index_type(index_buffer).unwrap_or(gl::UNSIGNED_INT),
+                    ptr::null(),
+                    num_instances as gl::types::GLsizei,
+                ),
+                None => gl::DrawArraysInstanced(
+                    mode,
+                    0,
+                    self.num_vertices as gl::types::GLsizei,
+                    num_instances as gl::types::GLsizei,
+                ),
+            }
+            gl::BindVertexArray(0);
+        };
+    }
+}
+
+fn index_type(index_buffer: &Buffer) -> Option<gl::types::GLenum> {
+    match index_buffer.element_size() {
+        1 => Some(gl::UNSIGNED_BYTE),
+        2 => Some(gl::UNSIGNED_SHORT),
+        4 => Some(gl::UNSIGNED_INT),
+        _ => None,
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+
+use log::{Level, LevelFilter};
+
+use crate::logging::LogEntry;
+
+// Decides which log entries get displayed. Targets can be given their own level, which then
+// applies to their submodules as well, e.g. the level of "mulay_gfx::assets" applies to
+// "mulay_gfx::assets::watcher" unless the latter has its own.
+#[derive(Clone, Debug)]
+pub struct LogFilter {
+    default_level: LevelFilter,
+    target_levels: BTreeMap<String, LevelFilter>,
+    search_text: String,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            default_level: LevelFilter::Trace,
+            target_levels: BTreeMap::new(),
+            search_text: String::new(),
+        }
+    }
+}
+
+impl LogFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn default_level(&self) -> LevelFilter {
+        self.default_level
+    }
+
+    pub fn set_default_level(&mut self, level: LevelFilter) {
+        self.default_level = level;
+    }
+
+    // The level set for the target itself, if any. Levels set for its parents are not included.
+    pub fn get_target_level(&self, target: &str) -> Option<LevelFilter> {
+        self.target_levels.get(target).copied()
+    }
+
+    pub fn set_target_level<S: AsRef<str>>(&mut self, target: S, level: LevelFilter) {
+        self.target_levels
+            .insert(String::from(target.as_ref()), level);
+    }
+
+    pub fn clear_target_level(&mut self, target: &str) {
+        self.target_levels.remove(target);
+    }
+
+    pub fn target_levels(&self) -> &BTreeMap<String, LevelFilter> {
+        &self.target_levels
+    }
+
+    pub fn search_text(&self) -> &str {
+        self.search_text.as_str()
+    }
+
+    // Entries are only displayed if their target or message contain the text, ignoring case.
+    pub fn set_search_text<S: AsRef<str>>(&mut self, search_text: S) {
+        self.search_text = search_text.as_ref().to_lowercase();
+    }
+
+    // The level of the target, or of its closest parent with a level of its own.
+    pub fn get_effective_level(&self, target: &str) -> LevelFilter {
+        let mut module_path = target;
+        loop {
+            if let Some(level) = self.target_levels.get(module_path) {
+                return *level;
+            }
+
+            match module_path.rfind("::") {
+                Some(separator_index) => module_path = &module_path[..separator_index],
+                None => return self.default_level,
+            }
+        }
+    }
+
+    pub fn is_level_enabled(&self, target: &str, level: Level) -> bool {
+        level <= self.get_effective_level(target)
+    }
+
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        if !self.is_level_enabled(entry.target(), entry.level()) {
+            return false;
+        }
+
+        self.search_text.is_empty()
+            || entry
+                .message()
+                .to_lowercase()
+                .contains(self.search_text.as_str())
+            || entry
+                .target()
+                .to_lowercase()
+                .contains(self.search_text.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::logging::LogBuffer;
+
+    fn entry(level: Level, target: &str, message: &str) -> LogEntry {
+        let mut buffer = LogBuffer::new(1);
+        buffer.push(level, target, String::from(message), Duration::ZERO);
+        let entry = buffer.entries().next().unwrap().clone();
+        entry
+    }
+
+    #[test]
+    fn submodules_inherit_parent_level() {
+        let mut filter = LogFilter::new();
+        filter.set_default_level(LevelFilter::Warn);
+        filter.set_target_level("a", LevelFilter::Debug);
+
+        assert_eq!(filter.get_effective_level("a"), LevelFilter::Debug);
+        assert_eq!(filter.get_effective_level("a::b"), LevelFilter::Debug);
+        assert_eq!(filter.get_effective_level("a::b::c"), LevelFilter::Debug);
+        assert_eq!(filter.get_effective_level("ab"), LevelFilter::Warn);
+        assert!(filter.matches(&entry(Level::Debug, "a::b", "message")));
+        assert!(!filter.matches(&entry(Level::Debug, "ab", "message")));
+    }
+
+    #[test]
+    fn submodule_level_overrides_parent_level() {
+        let mut filter = LogFilter::new();
+        filter.set_target_level("a", LevelFilter::Debug);
+        filter.set_target_level("a::b", LevelFilter::Error);
+
+        assert_eq!(filter.get_effective_level("a::b"), LevelFilter::Error);
+        assert_eq!(filter.get_effective_level("a::b::c"), LevelFilter::Error);
+        assert_eq!(filter.get_effective_level("a::c"), LevelFilter::Debug);
+        assert!(!filter.matches(&entry(Level::Warn, "a::b::c", "message")));
+        assert!(filter.matches(&entry(Level::Warn, "a::c", "message")));
+    }
+
+    #[test]
+    fn searches_message_and_target_ignoring_case() {
+        let mut filter = LogFilter::new();
+        filter.set_search_text("Shader");
+
+        assert!(filter.matches(&entry(Level::Info, "a", "compiled SHADER")));
+        assert!(filter.matches(&entry(Level::Info, "assets::shader", "compiled")));
+        assert!(!filter.matches(&entry(Level::Info, "assets::mesh", "loaded")));
+    }
+}
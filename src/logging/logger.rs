@@ -0,0 +1,466 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+// Maximum level of the messages that get logged, e.g. "debug". Defaults to DEFAULT_LOG_LEVEL.
+pub const LOG_LEVEL_ENV_VAR: &str = "MULAY_GFX_LOG";
+// Path of a file that messages are mirrored to. Messages are appended to it.
+pub const LOG_FILE_ENV_VAR: &str = "MULAY_GFX_LOG_FILE";
+// Set to "1" to mirror messages to stderr.
+pub const LOG_STDERR_ENV_VAR: &str = "MULAY_GFX_LOG_STDERR";
+
+pub const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+pub const DEFAULT_LOG_CAPACITY: usize = 1000;
+
+// Target of the messages printed by the console itself, e.g. the output of commands. They are
+// shown without their level and target.
+pub const CONSOLE_LOG_TARGET: &str = "console";
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LoggerErrorKind {
+    InvalidSettings,
+    LogFileError,
+    AlreadyInstalled,
+}
+
+#[derive(Debug)]
+pub struct LoggerError {
+    source: Option<Box<dyn Error + 'static>>,
+    message: String,
+    kind: LoggerErrorKind,
+}
+
+impl LoggerError {
+    pub fn new(
+        message: impl AsRef<str>,
+        kind: LoggerErrorKind,
+        source: Option<Box<dyn Error + 'static>>,
+    ) -> LoggerError {
+        LoggerError {
+            source,
+            message: message.as_ref().into(),
+            kind,
+        }
+    }
+
+    pub fn kind(&self) -> LoggerErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for LoggerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for LoggerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    // Increases by one with every entry, so that entries can be told apart once older ones
+    // are dropped from the buffer.
+    sequence: u64,
+    // Time since the log was created.
+    elapsed: Duration,
+    level: Level,
+    target: String,
+    message: String,
+}
+
+impl LogEntry {
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    // The module the message was logged from, unless it was given another target.
+    pub fn target(&self) -> &str {
+        self.target.as_str()
+    }
+
+    pub fn message(&self) -> &str {
+        self.message.as_str()
+    }
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>9.3} [{:<5}] {}: {}",
+            self.elapsed.as_secs_f64(),
+            self.level,
+            self.target,
+            self.message
+        )
+    }
+}
+
+// Keeps the latest entries. The oldest entry is dropped for every entry pushed past the
+// capacity.
+#[derive(Clone, Debug)]
+pub struct LogBuffer {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    next_sequence: u64,
+    targets: BTreeSet<String>,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            next_sequence: 0,
+            targets: BTreeSet::new(),
+        }
+    }
+
+    pub fn push(&mut self, level: Level, target: &str, message: String, elapsed: Duration) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+
+        if !self.targets.contains(target) {
+            self.targets.insert(String::from(target));
+        }
+
+        self.entries.push_back(LogEntry {
+            sequence: self.next_sequence,
+            elapsed,
+            level,
+            target: String::from(target),
+            message,
+        });
+        self.next_sequence += 1;
+    }
+
+    // Oldest first.
+    pub fn entries(&self) -> impl Iterator<Item = &LogEntry> {
+        self.entries.iter()
+    }
+
+    // Every target logged to so far, including the ones whose entries have been dropped.
+    pub fn targets(&self) -> &BTreeSet<String> {
+        &self.targets
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+// A shared reference to the log buffer, for the logger to write to and the UI to read from.
+#[derive(Clone, Debug)]
+pub struct LogHandle {
+    buffer: Arc<Mutex<LogBuffer>>,
+    start_time: Instant,
+}
+
+impl LogHandle {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buffer: Arc::new(Mutex::new(LogBuffer::new(capacity))),
+            start_time: Instant::now(),
+        }
+    }
+
+    // Adds an entry to the buffer directly, without mirroring it or going through the log
+    // facade. Used by the console to print the output of commands.
+    pub fn push<S: Into<String>>(&self, level: Level, target: &str, message: S) {
+        let elapsed = self.start_time.elapsed();
+        self.lock_buffer()
+            .push(level, target, message.into(), elapsed);
+    }
+
+    pub fn read<R, F: FnOnce(&LogBuffer) -> R>(&self, f: F) -> R {
+        f(&self.lock_buffer())
+    }
+
+    pub fn clear(&self) {
+        self.lock_buffer().clear();
+    }
+
+    // A panic while the buffer is locked leaves it in a usable state, so poisoning is ignored.
+    // Logging must keep working while the application is going down.
+    fn lock_buffer(&self) -> MutexGuard<'_, LogBuffer> {
+        match self.buffer.lock() {
+            Ok(buffer) => buffer,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LoggerSettings {
+    level: LevelFilter,
+    capacity: usize,
+    mirror_to_stderr: bool,
+    log_file_path: Option<PathBuf>,
+}
+
+impl Default for LoggerSettings {
+    fn default() -> Self {
+        Self {
+            level: DEFAULT_LOG_LEVEL,
+            capacity: DEFAULT_LOG_CAPACITY,
+            mirror_to_stderr: false,
+            log_file_path: None,
+        }
+    }
+}
+
+impl LoggerSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Reads the settings from LOG_LEVEL_ENV_VAR, LOG_FILE_ENV_VAR and LOG_STDERR_ENV_VAR.
+    pub fn from_env() -> Result<Self, LoggerError> {
+        let mut settings = Self::default();
+
+        if let Some(level) = Self::read_env_var(LOG_LEVEL_ENV_VAR)? {
+            settings.level = match level.trim().parse::<LevelFilter>() {
+                Ok(level) => level,
+                Err(error) => {
+                    return Err(LoggerError::new(
+                        format!(
+                            "value of {}, \"{}\", is not one of \"off\", \"error\", \"warn\", \
+                            \"info\", \"debug\", or \"trace\"",
+                            LOG_LEVEL_ENV_VAR, level
+                        ),
+                        LoggerErrorKind::InvalidSettings,
+                        Some(Box::new(error)),
+                    ))
+                }
+            };
+        }
+
+        if let Some(log_file_path) = Self::read_env_var(LOG_FILE_ENV_VAR)? {
+            settings.log_file_path = Some(PathBuf::from(log_file_path));
+        }
+
+        if let Some(mirror_to_stderr) = Self::read_env_var(LOG_STDERR_ENV_VAR)? {
+            settings.mirror_to_stderr = matches!(mirror_to_stderr.trim(), "1" | "true");
+        }
+
+        Ok(settings)
+    }
+
+    pub fn level(mut self, level: LevelFilter) -> Self {
+        self.level = level;
+        self
+    }
+
+    // The number of entries kept in the log buffer.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn mirror_to_stderr(mut self, mirror_to_stderr: bool) -> Self {
+        self.mirror_to_stderr = mirror_to_stderr;
+        self
+    }
+
+    pub fn log_file<P: Into<PathBuf>>(mut self, log_file_path: P) -> Self {
+        self.log_file_path = Some(log_file_path.into());
+        self
+    }
+
+    fn read_env_var(name: &str) -> Result<Option<String>, LoggerError> {
+        match env::var(name) {
+            Ok(value) => Ok(Some(value)),
+            Err(env::VarError::NotPresent) => Ok(None),
+            Err(error) => Err(LoggerError::new(
+                format!("value of {} is not valid unicode", name),
+                LoggerErrorKind::InvalidSettings,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+}
+
+// A backend for the log facade that writes to a log buffer, which the UI console displays, and
+// optionally to stderr and a log file.
+pub struct ConsoleLogger {
+    handle: LogHandle,
+    level: LevelFilter,
+    mirror_to_stderr: bool,
+    log_file: Option<Mutex<File>>,
+}
+
+impl ConsoleLogger {
+    pub fn new(settings: LoggerSettings) -> Result<Self, LoggerError> {
+        let log_file = match &settings.log_file_path {
+            Some(log_file_path) => {
+                match OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(log_file_path)
+                {
+                    Ok(log_file) => Some(Mutex::new(log_file)),
+                    Err(error) => {
+                        return Err(LoggerError::new(
+                            format!(
+                                "unable to open log file, {}",
+                                log_file_path.to_string_lossy()
+                            ),
+                            LoggerErrorKind::LogFileError,
+                            Some(Box::new(error)),
+                        ))
+                    }
+                }
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            handle: LogHandle::new(settings.capacity),
+            level: settings.level,
+            mirror_to_stderr: settings.mirror_to_stderr,
+            log_file,
+        })
+    }
+
+    pub fn handle(&self) -> LogHandle {
+        self.handle.clone()
+    }
+
+    // Makes the logger the backend of the log facade. Only one logger can ever be installed.
+    pub fn install(self) -> Result<LogHandle, LoggerError> {
+        let handle = self.handle();
+        let level = self.level;
+        match log::set_boxed_logger(Box::new(self)) {
+            Ok(_) => {
+                log::set_max_level(level);
+                Ok(handle)
+            }
+            Err(error) => Err(LoggerError::new(
+                "a logger has already been installed",
+                LoggerErrorKind::AlreadyInstalled,
+                Some(Box::new(error)),
+            )),
+        }
+    }
+}
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let elapsed = self.handle.start_time.elapsed();
+        let mut buffer = self.handle.lock_buffer();
+        buffer.push(
+            record.level(),
+            record.target(),
+            record.args().to_string(),
+            elapsed,
+        );
+
+        // Mirrored while the buffer is still locked, so that the mirrors get the entries in the
+        // same order as the buffer.
+        if self.mirror_to_stderr || self.log_file.is_some() {
+            let entry = match buffer.entries().last() {
+                Some(entry) => entry.to_string(),
+                None => return,
+            };
+
+            if self.mirror_to_stderr {
+                eprintln!("{}", entry);
+            }
+
+            if let Some(log_file) = &self.log_file {
+                if let Ok(mut log_file) = log_file.lock() {
+                    // There is nowhere left to report a failed write to.
+                    let _ = writeln!(log_file, "{}", entry);
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(log_file) = &self.log_file {
+            if let Ok(mut log_file) = log_file.lock() {
+                let _ = log_file.flush();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push(buffer: &mut LogBuffer, target: &str, message: &str) {
+        buffer.push(Level::Info, target, String::from(message), Duration::ZERO);
+    }
+
+    #[test]
+    fn drops_oldest_entry_when_full() {
+        let mut buffer = LogBuffer::new(2);
+        push(&mut buffer, "a", "first");
+        push(&mut buffer, "a", "second");
+        push(&mut buffer, "a", "third");
+
+        let messages: Vec<&str> = buffer.entries().map(|entry| entry.message()).collect();
+        assert_eq!(messages, ["second", "third"]);
+        assert_eq!(buffer.len(), 2);
+    }
+
+    #[test]
+    fn keeps_increasing_sequence_after_eviction() {
+        let mut buffer = LogBuffer::new(2);
+        for message in ["first", "second", "third", "fourth"] {
+            push(&mut buffer, "a", message);
+        }
+
+        let sequences: Vec<u64> = buffer.entries().map(|entry| entry.sequence()).collect();
+        assert_eq!(sequences, [2, 3]);
+    }
+
+    #[test]
+    fn keeps_targets_of_dropped_entries() {
+        let mut buffer = LogBuffer::new(1);
+        push(&mut buffer, "a", "first");
+        push(&mut buffer, "b", "second");
+
+        assert!(buffer.entries().all(|entry| entry.target() == "b"));
+        let targets: Vec<&str> = buffer.targets().iter().map(String::as_str).collect();
+        assert_eq!(targets, ["a", "b"]);
+    }
+}
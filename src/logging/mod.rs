@@ -0,0 +1,5 @@
+pub mod filter;
+pub mod logger;
+
+pub use filter::*;
+pub use logger::*;
@@ -3,22 +3,179 @@ extern crate sdl2;
 
 mod assets;
 mod c_bridge;
+mod console;
+mod cvars;
 mod graphics;
+mod logging;
 mod ui;
 
+use std::cell::Cell;
+use std::env;
+use std::error::Error;
 use std::mem;
 use std::os;
-use std::ptr;
-use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::video::GLProfile;
 
-use graphics::Program;
+use console::{
+    ArgKind, Command, CommandArg, CommandArgs, CommandError, CommandErrorKind, CommandRegistry,
+    ConsoleRequest,
+};
+use cvars::{Cvar, CvarDefinition, CvarRegistry};
+use graphics::{
+    Buffer, BufferKind, BufferUsage, ComponentType, Material, MaterialErrorKind, Program,
+    UniformBuffer, UniformConstant, VertexArray, VertexArrayError, VertexAttribute, VertexLayout,
+};
+use ui::AssetBrowserRequest;
+
+// Time each asset manager may spend per frame uploading assets loaded in the background.
+const ASSET_UPLOAD_TIME_BUDGET: Duration = Duration::from_millis(4);
+
+// Values that change every frame, shared with every program through a uniform block.
+const FRAME_BLOCK_NAME: &str = "Frame";
+
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+struct FrameBlock {
+    elapsed_time: f32,
+    time_since_last_frame: f32,
+    _padding: [f32; 2],
+}
+
+graphics::std140_block!(FrameBlock {
+    elapsed_time: Float,
+    time_since_last_frame: Float,
+});
+
+const MATERIAL_ASSET_ID: &str = "triangle-material";
+const MATERIAL_FILE_PATH: &str = "assets/materials/triangle.material";
+
+// What console commands act on.
+struct AppState {
+    asset_registry: assets::AssetRegistry,
+    cvars: CvarRegistry,
+    // None until the material, and the assets it refers to, load successfully.
+    material: Option<assets::Handle<Material>>,
+    // Uniforms set with the "set" command, which override the ones of the material.
+    uniform_overrides: Vec<(String, UniformConstant)>,
+}
+
+impl AsRef<assets::AssetRegistry> for AppState {
+    fn as_ref(&self) -> &assets::AssetRegistry {
+        &self.asset_registry
+    }
+}
+
+impl AsMut<assets::AssetRegistry> for AppState {
+    fn as_mut(&mut self) -> &mut assets::AssetRegistry {
+        &mut self.asset_registry
+    }
+}
+
+impl AsRef<CvarRegistry> for AppState {
+    fn as_ref(&self) -> &CvarRegistry {
+        &self.cvars
+    }
+}
+
+impl AsMut<CvarRegistry> for AppState {
+    fn as_mut(&mut self) -> &mut CvarRegistry {
+        &mut self.cvars
+    }
+}
+
+impl AppState {
+    fn get_program(&self) -> Option<&Program> {
+        let material = self.asset_registry.get(self.material.as_ref()?)?;
+        self.asset_registry.get(material.program()?)
+    }
+}
 
 fn main() {
+    // Invalid settings should not keep the application from starting, so they are reported
+    // once the logger is up.
+    let (logger_settings, logger_settings_error) = match logging::LoggerSettings::from_env() {
+        Ok(settings) => (settings, None),
+        Err(error) => (logging::LoggerSettings::new(), Some(error)),
+    };
+    let console_log = match logging::ConsoleLogger::new(logger_settings) {
+        Ok(logger) => logger.install(),
+        Err(error) => Err(error),
+    };
+    let console_log = match console_log {
+        Ok(console_log) => console_log,
+        Err(error) => panic!("{:?}", error), // For now. Maybe.
+    };
+    if let Some(error) = logger_settings_error {
+        report_error(&error);
+    }
+
+    // Values from the config file are applied first, so that the command line overrides them.
+    let mut cvars = CvarRegistry::from_env();
+    let is_window_resized = Rc::new(Cell::new(false));
+    let window_width = {
+        let is_window_resized = is_window_resized.clone();
+        match cvars.register(
+            CvarDefinition::new("window.width", "Width of the window, in pixels.", 640u32)
+                .range(320.0, 7680.0)
+                .on_change(move |_| is_window_resized.set(true)),
+        ) {
+            Ok(cvar) => cvar,
+            Err(error) => panic!("{:?}", error), // For now. Maybe.
+        }
+    };
+    let window_height = {
+        let is_window_resized = is_window_resized.clone();
+        match cvars.register(
+            CvarDefinition::new("window.height", "Height of the window, in pixels.", 480u32)
+                .range(240.0, 4320.0)
+                .on_change(move |_| is_window_resized.set(true)),
+        ) {
+            Ok(cvar) => cvar,
+            Err(error) => panic!("{:?}", error), // For now. Maybe.
+        }
+    };
+    let clear_color = match cvars.register(
+        CvarDefinition::new(
+            "render.clear_color",
+            "Colour the screen is cleared with, as red, green, and blue.",
+            [0.14f32, 0.14f32, 0.14f32],
+        )
+        .range(0.0, 1.0),
+    ) {
+        Ok(cvar) => cvar,
+        Err(error) => panic!("{:?}", error), // For now. Maybe.
+    };
+    let is_unload_policy_changed = Rc::new(Cell::new(false));
+    let unload_policy = {
+        let is_unload_policy_changed = is_unload_policy_changed.clone();
+        match cvars.register(
+            CvarDefinition::new(
+                "assets.unload_policy",
+                "When unused assets are unloaded: \"immediate\", \"manual\" (with the \
+                collect-garbage command), or after a grace period in seconds, e.g. \"5\".",
+                String::from("immediate"),
+            )
+            .on_change(move |_| is_unload_policy_changed.set(true)),
+        ) {
+            Ok(cvar) => cvar,
+            Err(error) => panic!("{:?}", error), // For now. Maybe.
+        }
+    };
+    for error in cvars.load_config_file() {
+        report_error(&error);
+    }
+    for error in cvars.set_values_from_args(env::args().skip(1)) {
+        report_error(&error);
+    }
+    // The window gets created with the size from the cvars.
+    is_window_resized.set(false);
+
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
 
@@ -26,8 +183,8 @@ fn main() {
     gl_attr.set_context_profile(GLProfile::Core);
     gl_attr.set_context_version(3, 3);
 
-    let window = video_subsystem
-        .window("MulayGFX", 640, 480)
+    let mut window = video_subsystem
+        .window("MulayGFX", window_width.get(), window_height.get())
         .opengl()
         .build()
         .unwrap();
@@ -39,7 +196,7 @@ fn main() {
     debug_assert_eq!(gl_attr.context_profile(), GLProfile::Core);
     debug_assert_eq!(gl_attr.context_version(), (3, 3));
 
-    let mut app_ui = ui::UI::new(&window);
+    let mut app_ui = ui::UI::new(&window, console_log);
 
     // Set up data.
     let vertices = vec![
@@ -50,67 +207,116 @@ fn main() {
         Ok(manager) => manager,
         Err(error) => panic!("{:?}", error), // For now. Maybe.
     };
-    let vertex_shader =
-        match shader_asset_manager.load_asset("vertex-shader", "assets/shaders/triangle.vert") {
-            Ok(ptr) => ptr,
-            Err(err) => panic!("{:?}", err), // For now. Maybe.
-        };
-    let fragment_shader =
-        match shader_asset_manager.load_asset("fragment-shader", "assets/shaders/triangle.frag") {
-            Ok(ptr) => ptr,
-            Err(err) => panic!("{:?}", err), // For now. Maybe.
-        };
+    let mut texture_asset_manager = match assets::AssetManager::<assets::Texture>::new() {
+        Ok(manager) => manager,
+        Err(error) => panic!("{:?}", error), // For now. Maybe.
+    };
+    let mut mesh_asset_manager = match assets::AssetManager::<assets::Mesh>::new() {
+        Ok(manager) => manager,
+        Err(error) => panic!("{:?}", error), // For now. Maybe.
+    };
+
+    let worker_pool = match assets::WorkerPool::new(assets::WorkerPool::default_num_workers()) {
+        Ok(worker_pool) => worker_pool,
+        Err(error) => panic!("{:?}", error), // For now. Maybe.
+    };
+    shader_asset_manager.set_worker_pool(worker_pool.clone());
+    texture_asset_manager.set_worker_pool(worker_pool.clone());
+    mesh_asset_manager.set_worker_pool(worker_pool);
+
+    // New and changed files of these get decoded in the background, and uploaded by pump().
+    shader_asset_manager.enable_async_loading();
+    texture_asset_manager.enable_async_loading();
+    mesh_asset_manager.enable_async_loading();
+
+    let program_asset_manager = match assets::AssetManager::<Program>::new() {
+        Ok(manager) => manager,
+        Err(error) => panic!("{:?}", error), // For now. Maybe.
+    };
+    let material_asset_manager = match assets::AssetManager::<Material>::new() {
+        Ok(manager) => manager,
+        Err(error) => panic!("{:?}", error), // For now. Maybe.
+    };
+
+    // The registry loads the assets that materials refer to, and rebuilds assets built from
+    // other assets whenever those get reloaded.
+    let mut asset_registry = assets::AssetRegistry::new();
+    asset_registry.register(shader_asset_manager);
+    asset_registry.register(texture_asset_manager);
+    asset_registry.register(mesh_asset_manager);
+    asset_registry.register(program_asset_manager);
+    asset_registry.register(material_asset_manager);
+    apply_unload_policy(&mut asset_registry, &unload_policy);
+
+    // The material gets reloaded whenever its file changes, and its program whenever the
+    // manifest, or one of the stages, of the program changes. A material that fails to load,
+    // e.g. because a stage does not compile, is loaded again once its files change.
+    let material = match asset_registry.load_asset(MATERIAL_ASSET_ID, MATERIAL_FILE_PATH) {
+        Ok(handle) => Some(handle),
+        Err(error) => {
+            report_asset_error(&mut app_ui, &error);
+            None
+        }
+    };
+
+    let mut app_state = AppState {
+        asset_registry,
+        cvars,
+        material,
+        uniform_overrides: vec![],
+    };
 
     let mut watcher = match assets::AssetsWatcher::new() {
         Ok(watcher) => watcher,
         Err(error) => panic!("{:?}", error), // For now. Maybe.
     };
-    watcher.add_paths_to_watchlist(&vec![
-        "assets/shaders/triangle.vert",
-        "assets/shaders/triangle.frag",
-    ]);
-
-    let shader_program: Arc<Mutex<Program>> = match Program::new(vec![
-        Arc::clone(&vertex_shader),
-        Arc::clone(&fragment_shader),
-    ]) {
-        Ok(program) => Arc::new(Mutex::new(program)),
-        Err(err) => panic!("{:?}", err),
-    };
-
-    let shader_program_ptr1 = Arc::clone(&shader_program);
-    let shader_program_ptr2 = Arc::clone(&shader_program);
-    shader_asset_manager.register_asset_reload_callback("vertex-shader", move || {
-        shader_program_ptr1.lock().unwrap().reload().unwrap();
-    });
-    shader_asset_manager.register_asset_reload_callback("fragment-shader", move || {
-        shader_program_ptr2.lock().unwrap().reload().unwrap();
-    });
-
-    let mut vao_id: u32 = 0;
-    let mut vbo_id: u32 = 0;
-    unsafe {
-        gl::GenVertexArrays(1, &mut vao_id);
-        gl::GenBuffers(1, &mut vbo_id);
-
-        gl::BindVertexArray(vao_id);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo_id);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            (vertices.len() * mem::size_of::<f32>()) as gl::types::GLsizeiptr,
-            vertices.as_ptr() as *const gl::types::GLvoid,
-            gl::STATIC_DRAW,
-        );
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            (mem::size_of::<f32>() * 3) as i32,
-            ptr::null(),
-        );
-        gl::EnableVertexAttribArray(0);
-    }
+    // Watch the whole directory so that files included by shaders are watched too, and new
+    // files get loaded as they are added.
+    let watch_filter = match assets::WatchFilter::new(&["**/*"], &["**/.*", "**/*~", "**/*.swp"]) {
+        Ok(filter) => filter,
+        Err(error) => panic!("{:?}", error), // For now. Maybe.
+    };
+    match watcher.watch_directory("assets", watch_filter) {
+        Ok(_) => {}
+        Err(error) => panic!("{:?}", error), // For now. Maybe.
+    };
+
+    let frame_uniform_buffer = match UniformBuffer::new(FRAME_BLOCK_NAME, &FrameBlock::default()) {
+        Ok(uniform_buffer) => uniform_buffer,
+        Err(error) => panic!("{:?}", error), // For now. Maybe.
+    };
+    // The last error that kept the frame from being drawn, so that it is not reported every
+    // frame.
+    let mut last_draw_error: Option<String> = None;
+
+    // Created for the program that the triangle gets drawn with, and again whenever the program
+    // is relinked into a new one, since its attributes may have moved. The ID of the program it
+    // was last created for is kept, so that a program it fails for is reported only once.
+    let mut vertex_array: Option<VertexArray> = None;
+    let mut vertex_array_program_id: Option<gl::types::GLuint> = None;
+
+    let mut commands: CommandRegistry<AppState> = CommandRegistry::new();
+    assets::register_asset_commands(&mut commands);
+    cvars::register_cvar_commands(&mut commands);
+    commands.register(
+        Command::new(
+            "set",
+            "Sets a uniform of the program of the material, overriding the material.",
+            set_uniform_override,
+        )
+        .arg(
+            CommandArg::new("uniform", ArgKind::String).completer(|app_state: &AppState| {
+                match app_state.get_program() {
+                    Some(program) => program
+                        .uniforms()
+                        .map(|uniform| String::from(uniform.name()))
+                        .collect(),
+                    None => vec![],
+                }
+            }),
+        )
+        .arg(CommandArg::new("value", ArgKind::Float).variadic()),
+    );
 
     let mut app_time_start = Instant::now();
     let mut frame_time_start = Instant::now();
@@ -142,41 +348,314 @@ fn main() {
             break;
         }
 
+        for request in app_ui.take_console_requests() {
+            match request {
+                ConsoleRequest::Execute(line) => {
+                    app_ui.print_to_console(format!("> {}", line));
+                    match commands.execute(&mut app_state, line.as_str()) {
+                        Ok(output) => {
+                            for output_line in output {
+                                app_ui.print_to_console(output_line);
+                            }
+                        }
+                        Err(error) => report_error(&error),
+                    }
+                }
+                ConsoleRequest::Complete(line) => {
+                    let completion = commands.complete(&app_state, line.as_str());
+                    app_ui.complete_console_command(completion);
+                }
+            }
+        }
+
+        for request in app_ui.take_asset_browser_requests() {
+            match request {
+                AssetBrowserRequest::Reload(key) => {
+                    for error in app_state.asset_registry.reload_assets(&[key]) {
+                        report_asset_error(&mut app_ui, &error);
+                    }
+                }
+                AssetBrowserRequest::Destroy(key) => {
+                    if let Err(error) = app_state.asset_registry.destroy_asset(key) {
+                        report_error(&error);
+                    }
+                }
+            }
+        }
+
+        if is_window_resized.replace(false) {
+            let (width, height) = (window_width.get(), window_height.get());
+            if let Err(error) = window.set_size(width, height) {
+                report_error(&error);
+            }
+            unsafe {
+                gl::Viewport(0, 0, width as i32, height as i32);
+            }
+        }
+
+        if is_unload_policy_changed.replace(false) {
+            apply_unload_policy(&mut app_state.asset_registry, &unload_policy);
+        }
+
+        // Finish background loads and reloads.
+        let (new_asset_ids, pump_errors) = app_state.asset_registry.pump(ASSET_UPLOAD_TIME_BUDGET);
+        for asset_id in new_asset_ids {
+            log::info!("Loaded new asset, \"{}\"", asset_id);
+        }
+        for error in pump_errors {
+            report_asset_error(&mut app_ui, &error);
+        }
+
         // Hot-reload.
-        let stale_paths = watcher.get_stale_paths();
-        let asset_ids = shader_asset_manager.file_paths_to_asset_ids(&stale_paths);
-        match shader_asset_manager.reload_assets_by_id(&asset_ids) {
-            Ok(_) => {}
-            Err(error) => panic!("{:?}", error),
+        let asset_events = match watcher.poll_events() {
+            Ok(asset_events) => asset_events,
+            Err(error) => {
+                report_error(&error);
+                vec![]
+            }
         };
+        // Assets of removed files keep their last working version until the files reappear.
+        // A renamed file no longer exists at its old path either.
+        let removed_paths: Vec<PathBuf> = asset_events
+            .iter()
+            .filter_map(|asset_event| match asset_event {
+                assets::AssetEvent::Removed(path) => Some(path.clone()),
+                assets::AssetEvent::Renamed { from, .. } => Some(from.clone()),
+                _ => None,
+            })
+            .collect();
+        for asset_id in app_state
+            .asset_registry
+            .mark_assets_missing_by_file_paths(&removed_paths)
+        {
+            log::warn!(
+                "Source file of asset, \"{}\", was removed. Its last loaded version will be used \
+                until the file is restored.",
+                asset_id
+            );
+        }
 
-        if !stale_paths.is_empty() {
-            watcher.clear_stale_paths();
+        let stale_paths: Vec<PathBuf> = asset_events
+            .iter()
+            .filter(|asset_event| !matches!(asset_event, assets::AssetEvent::Removed(_)))
+            .map(|asset_event| asset_event.path().to_path_buf())
+            .collect();
+        // Shader errors about the changed files are dismissed, since the files get compiled
+        // again. The ones that still do not compile are shown again.
+        app_ui.dismiss_shader_errors(&stale_paths);
+
+        // Assets built from the reloaded ones, such as the program, get rebuilt as well.
+        for error in app_state.asset_registry.reload_file_paths(&stale_paths) {
+            report_asset_error(&mut app_ui, &error);
         }
 
-        unsafe {
-            gl::ClearColor(0.14f32, 0.14f32, 0.14f32, 1.0f32);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+        // New files are loaded after the reloads, so that a new file that a reloaded shader
+        // now includes is known to be an include rather than a shader of its own.
+        let new_paths: Vec<PathBuf> = asset_events
+            .iter()
+            .filter(|asset_event| {
+                matches!(
+                    asset_event,
+                    assets::AssetEvent::Created(_) | assets::AssetEvent::Renamed { .. }
+                )
+            })
+            .map(|asset_event| asset_event.path().to_path_buf())
+            .collect();
+        let (new_asset_ids, load_errors) =
+            app_state.asset_registry.load_new_asset_files(&new_paths);
+        for asset_id in new_asset_ids {
+            log::info!("Loaded new asset, \"{}\"", asset_id);
+        }
+        for error in load_errors {
+            report_asset_error(&mut app_ui, &error);
+        }
 
-            gl::UseProgram(shader_program.lock().unwrap().id());
-            match shader_program
-                .lock()
-                .unwrap()
-                .add_uniform1f("elapsedTime", app_time_start.elapsed().as_secs_f32())
+        if app_state.material.is_none() && !stale_paths.is_empty() {
+            match app_state
+                .asset_registry
+                .load_asset(MATERIAL_ASSET_ID, MATERIAL_FILE_PATH)
             {
-                Ok(_) => {}
-                Err(error) => panic!("{:?}", error),
-            };
+                Ok(handle) => app_state.material = Some(handle),
+                Err(error) => report_asset_error(&mut app_ui, &error),
+            }
+        }
+
+        frame_uniform_buffer.update(&FrameBlock {
+            elapsed_time: app_time_start.elapsed().as_secs_f32(),
+            time_since_last_frame: time_since_last_frame.as_secs_f32(),
+            ..FrameBlock::default()
+        });
+
+        unsafe {
+            let [red, green, blue] = clear_color.get();
+            gl::ClearColor(red, green, blue, 1.0f32);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
 
-            gl::BindVertexArray(vao_id);
+        // The material, or its program, may have been destroyed from the asset browser, in
+        // which case there is nothing to draw with. A reloaded program may also no longer fit
+        // the material, e.g. if a uniform changed type, or the frame block. The draw is skipped
+        // until either gets fixed, and the error is only reported once.
+        let bind_result: Result<Option<&Program>, Box<dyn Error>> = match app_state
+            .material
+            .as_ref()
+            .and_then(|material| app_state.asset_registry.get(material))
+            .map(|material| material.bind(&app_state.asset_registry))
+        {
+            Some(Ok(program)) => match program.bind_uniform_buffer(&frame_uniform_buffer) {
+                Ok(_) => Ok(Some(program)),
+                Err(error) => Err(Box::new(error)),
+            },
+            Some(Err(error)) if error.kind() == MaterialErrorKind::UnresolvedAsset => Ok(None),
+            Some(Err(error)) => Err(Box::new(error)),
+            None => Ok(None),
+        };
+        let program = match bind_result {
+            Ok(program) => {
+                last_draw_error = None;
+                program
+            }
+            Err(error) => {
+                let message = error.to_string();
+                if last_draw_error.as_ref() != Some(&message) {
+                    report_error(error.as_ref());
+                    last_draw_error = Some(message);
+                }
+                None
+            }
+        };
 
-            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        if let Some(program) = program {
+            // Overrides of uniforms that a reloaded program no longer has, or that changed
+            // type, are dropped.
+            let mut failed_overrides: Vec<String> = vec![];
+            for (name, value) in &app_state.uniform_overrides {
+                if let Err(error) = program.set_uniform_constant(name, value) {
+                    report_error(&error);
+                    failed_overrides.push(name.clone());
+                }
+            }
+            app_state
+                .uniform_overrides
+                .retain(|(name, _)| !failed_overrides.contains(name));
+
+            if vertex_array_program_id != Some(program.id()) {
+                vertex_array_program_id = Some(program.id());
+                vertex_array = match create_vertex_array(program, &vertices) {
+                    Ok(vertex_array) => Some(vertex_array),
+                    Err(error) => {
+                        report_asset_error(&mut app_ui, &error);
+                        None
+                    }
+                };
+            }
+
+            if let Some(vertex_array) = &vertex_array {
+                vertex_array.draw(gl::TRIANGLES);
+            }
         }
 
+        app_ui.set_asset_infos(app_state.asset_registry.asset_infos());
         app_ui.draw_frames(&window, app_time_start.elapsed().as_secs_f64());
 
         window.gl_swap_window();
 
         frame_time_end = Instant::now();
     }
+
+    if let Err(error) = app_state.cvars.save_config_file() {
+        report_error(&error);
+    }
+}
+
+// Logs an error, along with the chain of errors that caused it.
+fn report_error(error: &dyn Error) {
+    let mut message = error.to_string();
+
+    let mut source = error.source();
+    while let Some(error) = source {
+        message.push_str(format!("\n    caused by: {}", error).as_str());
+        source = error.source();
+    }
+
+    log::error!("{}", message);
+}
+
+// Reports an error, and shows the diagnostics of the shader that caused it, if any.
+fn report_asset_error(app_ui: &mut ui::UI, error: &(dyn Error + 'static)) {
+    if let Some(shader_error) = assets::find_shader_error(error) {
+        app_ui.show_shader_error(shader_error);
+    }
+
+    report_error(error);
+}
+
+fn apply_unload_policy(asset_registry: &mut assets::AssetRegistry, unload_policy: &Cvar<String>) {
+    match assets::UnloadPolicy::parse(unload_policy.get().as_str()) {
+        Some(policy) => asset_registry.set_unload_policy(policy),
+        None => log::error!(
+            "Unload policy, \"{}\", is not \"immediate\", \"manual\", or a number of seconds. \
+            The current policy is kept.",
+            unload_policy.get()
+        ),
+    }
+}
+
+fn create_vertex_array(
+    program: &Program,
+    vertices: &[f32],
+) -> Result<VertexArray, VertexArrayError> {
+    let vertex_buffer = Buffer::new(BufferKind::Vertex, BufferUsage::Static, vertices);
+    let vertex_layout = VertexLayout::new(mem::size_of::<f32>() * 3)
+        .attribute(VertexAttribute::new("pos", ComponentType::Float, 3, 0));
+
+    VertexArray::new(program, vec![(vertex_buffer, vertex_layout)], None)
+}
+
+// Handler of the "set" command. The uniform is set right away, so that values that the uniform
+// cannot take are reported, and then again every frame.
+fn set_uniform_override(
+    app_state: &mut AppState,
+    args: &CommandArgs,
+) -> Result<Vec<String>, CommandError> {
+    let name = String::from(args.get_str("uniform").unwrap_or_default());
+    let components: Vec<f64> = args
+        .get_all("value")
+        .iter()
+        .filter_map(|value| value.as_float())
+        .collect();
+    let value = match components.as_slice() {
+        [component] => UniformConstant::Float(*component),
+        _ => UniformConstant::Vector(components),
+    };
+
+    let program = match app_state.get_program() {
+        Some(program) => program,
+        None => {
+            return Err(CommandError::new(
+                "the program of the material is not loaded",
+                CommandErrorKind::ExecutionFailed,
+                None,
+            ))
+        }
+    };
+
+    unsafe {
+        gl::UseProgram(program.id());
+    };
+    if let Err(error) = program.set_uniform_constant(name.as_str(), &value) {
+        return Err(CommandError::new(
+            format!("unable to set uniform, \"{}\"", name),
+            CommandErrorKind::ExecutionFailed,
+            Some(Box::new(error)),
+        ));
+    }
+
+    let output = vec![format!("Set uniform, \"{}\", to {:?}", name, value)];
+    app_state
+        .uniform_overrides
+        .retain(|(override_name, _)| *override_name != name);
+    app_state.uniform_overrides.push((name, value));
+
+    Ok(output)
 }
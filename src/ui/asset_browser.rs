@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use egui;
+
+use crate::assets::{AssetInfo, AssetKey, AssetThumbnail};
+
+// Maximum width and height at which thumbnails are shown.
+const THUMBNAIL_DISPLAY_SIZE: f32 = 32f32;
+
+// What the asset browser asks of whoever owns the assets.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AssetBrowserRequest {
+    Reload(AssetKey),
+    Destroy(AssetKey),
+}
+
+struct Thumbnail {
+    // Compared with the pixels of the asset to tell when the thumbnail has to be uploaded again,
+    // e.g. after the asset is reloaded.
+    pixels: Arc<Vec<u8>>,
+    texture: egui::TextureHandle,
+}
+
+// The "Assets" window, which lists the assets along with their state, and lets them be
+// reloaded or destroyed.
+pub struct AssetBrowser {
+    asset_infos: Vec<AssetInfo>,
+    requests: Vec<AssetBrowserRequest>,
+    filter_contents: String,
+    thumbnails: HashMap<AssetKey, Thumbnail>,
+}
+
+impl AssetBrowser {
+    pub fn new() -> Self {
+        Self {
+            asset_infos: vec![],
+            requests: vec![],
+            filter_contents: String::from(""),
+            thumbnails: HashMap::new(),
+        }
+    }
+
+    // The assets to list, sorted by type (see AssetRegistry::asset_infos()).
+    pub fn set_asset_infos(&mut self, asset_infos: Vec<AssetInfo>) {
+        self.thumbnails.retain(|key, _| {
+            asset_infos
+                .iter()
+                .any(|info| info.key() == *key && info.thumbnail().is_some())
+        });
+        self.asset_infos = asset_infos;
+    }
+
+    // Reloads and destroys requested since the last call.
+    pub fn take_requests(&mut self) -> Vec<AssetBrowserRequest> {
+        std::mem::take(&mut self.requests)
+    }
+
+    pub fn draw(&mut self, egui_ctx: &egui::Context) {
+        egui::Window::new("Assets")
+            .default_open(false)
+            .show(egui_ctx, |ui| {
+                let total_memory_usage: usize = self
+                    .asset_infos
+                    .iter()
+                    .filter_map(AssetInfo::memory_usage)
+                    .sum();
+                ui.horizontal(|ui| {
+                    ui.label("Filter:");
+                    ui.text_edit_singleline(&mut self.filter_contents);
+                    ui.label(format!(
+                        "{} asset(s), {}",
+                        self.asset_infos.len(),
+                        Self::format_memory_usage(total_memory_usage)
+                    ));
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(320f32)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| self.draw_asset_groups(ui));
+            });
+    }
+
+    fn draw_asset_groups(&mut self, ui: &mut egui::Ui) {
+        let filter = self.filter_contents.to_lowercase();
+        let now = Instant::now();
+
+        let asset_infos = std::mem::take(&mut self.asset_infos);
+        let matching_infos: Vec<&AssetInfo> = asset_infos
+            .iter()
+            .filter(|info| {
+                info.id().to_lowercase().contains(filter.as_str())
+                    || info
+                        .src_file_path()
+                        .to_string_lossy()
+                        .to_lowercase()
+                        .contains(filter.as_str())
+            })
+            .collect();
+
+        // The infos are sorted by type, so each type is a run of them.
+        for type_infos in matching_infos.chunk_by(|info, other_info| {
+            info.key().short_type_name() == other_info.key().short_type_name()
+        }) {
+            let type_name = type_infos[0].key().short_type_name();
+            egui::CollapsingHeader::new(format!("{} ({})", type_name, type_infos.len()))
+                .id_source(("asset-browser-type", type_name))
+                .default_open(true)
+                .show(ui, |ui| {
+                    egui::Grid::new(("asset-browser-grid", type_name))
+                        .striped(true)
+                        .num_columns(7)
+                        .show(ui, |ui| {
+                            for header in ["", "ID", "Path", "State", "Reloaded", "Memory", ""] {
+                                ui.strong(header);
+                            }
+                            ui.end_row();
+
+                            for info in type_infos {
+                                self.draw_asset_row(ui, info, now);
+                                ui.end_row();
+                            }
+                        });
+                });
+        }
+
+        self.asset_infos = asset_infos;
+    }
+
+    fn draw_asset_row(&mut self, ui: &mut egui::Ui, info: &AssetInfo, now: Instant) {
+        match info.thumbnail() {
+            Some(thumbnail) => {
+                let texture_id = self.get_thumbnail_texture(ui.ctx(), info.key(), thumbnail);
+                let scale = THUMBNAIL_DISPLAY_SIZE / thumbnail.width.max(thumbnail.height) as f32;
+                let size = egui::vec2(
+                    thumbnail.width as f32 * scale,
+                    thumbnail.height as f32 * scale,
+                );
+                ui.image(texture_id, size);
+            }
+            None => {
+                ui.label("");
+            }
+        }
+
+        ui.label(info.id());
+
+        let src_file_path = info.src_file_path().to_string_lossy();
+        match src_file_path.is_empty() {
+            true => ui.weak("-"),
+            false => ui.label(src_file_path.as_ref()),
+        };
+
+        // An error is the most pressing state to show, then a missing file, then staleness.
+        let state_text = match (info.last_error(), info.is_loaded()) {
+            (Some(_), _) => egui::RichText::new("error").color(ui.visuals().error_fg_color),
+            (None, false) => egui::RichText::new("not loaded").color(ui.visuals().warn_fg_color),
+            (None, true) if info.is_missing() => {
+                egui::RichText::new("missing").color(ui.visuals().warn_fg_color)
+            }
+            (None, true) if info.is_stale() => {
+                egui::RichText::new("stale").color(ui.visuals().warn_fg_color)
+            }
+            (None, true) => egui::RichText::new("loaded"),
+        };
+        let state_response = ui.label(state_text);
+        if let Some(error) = info.last_error() {
+            state_response.on_hover_text(error);
+        }
+
+        match info.last_reload_time() {
+            Some(last_reload_time) => ui.label(format!(
+                "{:.0} s ago",
+                (now - last_reload_time).as_secs_f32()
+            )),
+            None => ui.weak("never"),
+        };
+
+        match info.memory_usage() {
+            Some(memory_usage) => ui.label(Self::format_memory_usage(memory_usage)),
+            None => ui.weak("unknown"),
+        };
+
+        ui.horizontal(|ui| {
+            // Assets that were not loaded from a file have nothing to be reloaded from.
+            let reload_response = ui.add_enabled(
+                !info.src_file_path().as_os_str().is_empty(),
+                egui::Button::new("Reload"),
+            );
+            if reload_response.clicked() {
+                self.requests.push(AssetBrowserRequest::Reload(info.key()));
+            }
+
+            let destroy_response = ui
+                .button("Destroy")
+                .on_hover_text("Destroys the asset, even if it is still in use.");
+            if destroy_response.clicked() {
+                self.requests.push(AssetBrowserRequest::Destroy(info.key()));
+            }
+        });
+    }
+
+    // Uploads the thumbnail to egui, unless it already has been.
+    fn get_thumbnail_texture(
+        &mut self,
+        egui_ctx: &egui::Context,
+        key: AssetKey,
+        thumbnail: &AssetThumbnail,
+    ) -> egui::TextureId {
+        if let Some(uploaded_thumbnail) = self.thumbnails.get(&key) {
+            if Arc::ptr_eq(&uploaded_thumbnail.pixels, &thumbnail.pixels) {
+                return uploaded_thumbnail.texture.id();
+            }
+        }
+
+        let image = egui::ColorImage::from_rgba_unmultiplied(
+            [thumbnail.width, thumbnail.height],
+            thumbnail.pixels.as_slice(),
+        );
+        let texture = egui_ctx.load_texture(
+            format!("asset-thumbnail-{:?}", key),
+            image,
+            egui::TextureOptions::LINEAR,
+        );
+        let texture_id = texture.id();
+        self.thumbnails.insert(
+            key,
+            Thumbnail {
+                pixels: thumbnail.pixels.clone(),
+                texture,
+            },
+        );
+
+        texture_id
+    }
+
+    // E.g. "1.5 MiB".
+    fn format_memory_usage(num_bytes: usize) -> String {
+        const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+        let mut size = num_bytes as f64;
+        let mut unit_index = 0;
+        while size >= 1024f64 && unit_index < UNITS.len() - 1 {
+            size /= 1024f64;
+            unit_index += 1;
+        }
+
+        match unit_index {
+            0 => format!("{} {}", num_bytes, UNITS[0]),
+            _ => format!("{:.1} {}", size, UNITS[unit_index]),
+        }
+    }
+}
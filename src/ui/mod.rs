@@ -1,3 +1,7 @@
+pub mod asset_browser;
+pub mod shader_error_overlay;
 pub mod ui;
 
+pub use asset_browser::*;
+pub use shader_error_overlay::*;
 pub use ui::*;
@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+
+use egui;
+
+use crate::assets::{ShaderDiagnostic, ShaderDiagnosticSeverity, ShaderError};
+
+// Tabs are expanded so that the column markers line up with the source.
+const TAB_WIDTH: usize = 4;
+
+struct ShaderErrorReport {
+    title: String,
+    source_files: Vec<PathBuf>,
+    diagnostics: Vec<ShaderDiagnostic>,
+}
+
+// Shows the diagnostics of shaders that failed to compile, along with the lines of source they
+// point at. A report stays up until one of the files it is about gets reloaded, or it is
+// dismissed by hand.
+pub struct ShaderErrorOverlay {
+    reports: Vec<ShaderErrorReport>,
+}
+
+impl ShaderErrorOverlay {
+    pub fn new() -> Self {
+        Self { reports: vec![] }
+    }
+
+    // Replaces the report of the same stage, if there is one.
+    pub fn show_error(&mut self, error: &ShaderError) {
+        let source_files = error.source_files().to_vec();
+        self.reports
+            .retain(|report| report.source_files.first() != source_files.first());
+
+        // The error message lists the source files too, which the diagnostics already show.
+        let message = error.to_string();
+        let message = message.lines().next().unwrap_or_default();
+        let title = match source_files.first() {
+            Some(file_path) => format!("{} ({})", message, file_path.to_string_lossy()),
+            None => String::from(message),
+        };
+        self.reports.push(ShaderErrorReport {
+            title,
+            source_files,
+            diagnostics: error.diagnostics().to_vec(),
+        });
+    }
+
+    // Dismisses the reports about any of the files, e.g. once they have been reloaded. A reload
+    // that fails again shows a new report.
+    pub fn dismiss_errors_in_files(&mut self, file_paths: &[PathBuf]) {
+        self.reports.retain(|report| {
+            !report
+                .source_files
+                .iter()
+                .any(|source_file| file_paths.contains(source_file))
+        });
+    }
+
+    pub fn draw(&mut self, egui_ctx: &egui::Context) {
+        if self.reports.is_empty() {
+            return;
+        }
+
+        let mut dismissed_report_index: Option<usize> = None;
+        egui::Window::new("Shader Errors")
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0f32, 16f32))
+            .default_width(640f32)
+            .show(egui_ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(480f32)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        for (index, report) in self.reports.iter().enumerate() {
+                            if index > 0 {
+                                ui.separator();
+                            }
+
+                            ui.horizontal(|ui| {
+                                if ui.button("Dismiss").clicked() {
+                                    dismissed_report_index = Some(index);
+                                }
+                                ui.label(
+                                    egui::RichText::new(report.title.as_str())
+                                        .strong()
+                                        .color(ui.visuals().error_fg_color),
+                                );
+                            });
+
+                            for diagnostic in &report.diagnostics {
+                                Self::draw_diagnostic(ui, diagnostic);
+                            }
+                        }
+                    });
+            });
+
+        if let Some(index) = dismissed_report_index {
+            self.reports.remove(index);
+        }
+    }
+
+    fn draw_diagnostic(ui: &mut egui::Ui, diagnostic: &ShaderDiagnostic) {
+        let color = match diagnostic.severity() {
+            ShaderDiagnosticSeverity::Error => ui.visuals().error_fg_color,
+            ShaderDiagnosticSeverity::Warning => ui.visuals().warn_fg_color,
+        };
+        ui.label(
+            egui::RichText::new(diagnostic.to_string())
+                .monospace()
+                .color(color),
+        );
+
+        if diagnostic.source_excerpt().is_empty() {
+            return;
+        }
+
+        let gutter_width = diagnostic
+            .source_excerpt()
+            .iter()
+            .map(|(line_number, _)| line_number.to_string().len())
+            .max()
+            .unwrap_or(0);
+        egui::Frame::none()
+            .fill(ui.visuals().extreme_bg_color)
+            .inner_margin(egui::Margin::same(4f32))
+            .show(ui, |ui| {
+                ui.spacing_mut().item_spacing.y = 0f32;
+                for (line_number, line) in diagnostic.source_excerpt() {
+                    let line_text = format!(
+                        "{:>width$} | {}",
+                        line_number,
+                        Self::expand_tabs(line),
+                        width = gutter_width
+                    );
+                    if Some(*line_number) != diagnostic.line() {
+                        ui.label(egui::RichText::new(line_text).monospace().weak());
+                        continue;
+                    }
+
+                    ui.label(
+                        egui::RichText::new(line_text)
+                            .monospace()
+                            .strong()
+                            .background_color(color.gamma_multiply(0.25f32)),
+                    );
+                    if let Some(column) = diagnostic.column() {
+                        let prefix: String = line.chars().take(column.saturating_sub(1)).collect();
+                        let marker_text = format!(
+                            "{:>width$} | {}^",
+                            "",
+                            " ".repeat(Self::expand_tabs(&prefix).chars().count()),
+                            width = gutter_width
+                        );
+                        ui.label(egui::RichText::new(marker_text).monospace().color(color));
+                    }
+                }
+            });
+    }
+
+    fn expand_tabs(line: &str) -> String {
+        line.replace('\t', " ".repeat(TAB_WIDTH).as_str())
+    }
+}
@@ -1,17 +1,46 @@
+use std::path::PathBuf;
+
 use egui;
 use egui_sdl2_gl;
 use sdl2;
 
+use log::{Level, LevelFilter};
+
+use crate::assets::{AssetInfo, ShaderError};
+use crate::console::{CommandHistory, Completion, ConsoleRequest};
+use crate::logging::{LogEntry, LogFilter, LogHandle, CONSOLE_LOG_TARGET};
+use crate::ui::{AssetBrowser, AssetBrowserRequest, ShaderErrorOverlay};
+
+const CONSOLE_HISTORY_LENGTH: usize = 100;
+const LOG_LEVEL_FILTERS: [LevelFilter; 6] = [
+    LevelFilter::Off,
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
 pub struct UI {
     egui_ctx: egui::Context,
     egui_painter: egui_sdl2_gl::painter::Painter,
     egui_state: egui_sdl2_gl::EguiStateHandler,
-    console_contents: String,
+    console_log: LogHandle,
+    console_log_filter: LogFilter,
+    console_search_contents: String,
     console_command_contents: String,
+    console_history: CommandHistory,
+    console_requests: Vec<ConsoleRequest>,
+    // Set when the command line is changed from code, e.g. by the history, so that the cursor
+    // gets moved to the end of the new line.
+    is_console_command_replaced: bool,
+    asset_browser: AssetBrowser,
+    shader_error_overlay: ShaderErrorOverlay,
 }
 
 impl UI {
-    pub fn new(window: &sdl2::video::Window) -> UI {
+    // The console displays the entries of the log, and prints to it too.
+    pub fn new(window: &sdl2::video::Window, console_log: LogHandle) -> UI {
         let (egui_painter, egui_state) = egui_sdl2_gl::with_sdl2(
             window,
             egui_sdl2_gl::ShaderVersion::Default,
@@ -23,8 +52,15 @@ impl UI {
             egui_ctx,
             egui_painter,
             egui_state,
-            console_contents: String::from(""),
+            console_log,
+            console_log_filter: LogFilter::new(),
+            console_search_contents: String::from(""),
             console_command_contents: String::from(""),
+            console_history: CommandHistory::new(CONSOLE_HISTORY_LENGTH),
+            console_requests: vec![],
+            is_console_command_replaced: false,
+            asset_browser: AssetBrowser::new(),
+            shader_error_overlay: ShaderErrorOverlay::new(),
         }
     }
 
@@ -32,35 +68,17 @@ impl UI {
         self.egui_state.input.time = Some(app_elapsed_time);
         self.egui_ctx.begin_frame(self.egui_state.input.take());
 
-        egui::Window::new("Console").show(&self.egui_ctx, |ui| {
-            egui::ScrollArea::vertical()
-                .max_width(f32::INFINITY)
-                .max_height(256f32)
-                .stick_to_bottom(true)
-                .show(ui, |ui| {
-                    ui.add(egui::TextEdit::multiline(
-                        &mut self.console_contents.as_str(),
-                    ));
-                });
-
-            let textedit_response = ui.add(egui::TextEdit::singleline(
-                &mut self.console_command_contents,
-            ));
-            if textedit_response.lost_focus()
-                && ui.input(|i| i.key_pressed(egui::Key::Enter))
-                && !self.console_command_contents.is_empty()
-            {
-                if !self.console_contents.is_empty() {
-                    self.console_contents.push_str("\n");
-                }
-                
-                self.console_contents
-                    .push_str(&self.console_command_contents.as_str());
-                self.console_command_contents.clear();
-
-                textedit_response.request_focus();
-            }
+        // egui::Context is reference-counted, so cloning it is cheap and lets the windows below
+        // borrow the rest of the UI mutably.
+        let egui_ctx = self.egui_ctx.clone();
+        egui::Window::new("Console").show(&egui_ctx, |ui| {
+            self.draw_console_toolbar(ui);
+            ui.separator();
+            self.draw_console_log(ui);
+            self.draw_console_command_line(ui);
         });
+        self.asset_browser.draw(&egui_ctx);
+        self.shader_error_overlay.draw(&egui_ctx);
 
         let egui::FullOutput {
             platform_output,
@@ -75,6 +93,240 @@ impl UI {
             .paint_jobs(None, textures_delta, paint_jobs);
     }
 
+    // Commands entered, and completions requested (with Tab), in the console since the last
+    // call.
+    pub fn take_console_requests(&mut self) -> Vec<ConsoleRequest> {
+        std::mem::take(&mut self.console_requests)
+    }
+
+    // Replaces the command line with a completed one, and prints the possible completions if
+    // there is more than one.
+    pub fn complete_console_command(&mut self, completion: Completion) {
+        if completion.line != self.console_command_contents {
+            self.console_command_contents = completion.line;
+            self.is_console_command_replaced = true;
+        }
+
+        if !completion.candidates.is_empty() {
+            self.print_to_console(completion.candidates.join("    "));
+        }
+    }
+
+    // The assets listed in the "Assets" window.
+    pub fn set_asset_infos(&mut self, asset_infos: Vec<AssetInfo>) {
+        self.asset_browser.set_asset_infos(asset_infos);
+    }
+
+    // Reloads and destroys requested in the "Assets" window since the last call.
+    pub fn take_asset_browser_requests(&mut self) -> Vec<AssetBrowserRequest> {
+        self.asset_browser.take_requests()
+    }
+
+    // Shows the diagnostics of a shader that failed to compile until the files it was compiled
+    // from are reloaded (see dismiss_shader_errors()).
+    pub fn show_shader_error(&mut self, error: &ShaderError) {
+        self.shader_error_overlay.show_error(error);
+    }
+
+    pub fn dismiss_shader_errors(&mut self, file_paths: &[PathBuf]) {
+        self.shader_error_overlay
+            .dismiss_errors_in_files(file_paths);
+    }
+
+    pub fn print_to_console<S: AsRef<str>>(&mut self, message: S) {
+        self.console_log
+            .push(Level::Info, CONSOLE_LOG_TARGET, message.as_ref());
+    }
+
+    fn draw_console_toolbar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Search:");
+            let search_response = ui.text_edit_singleline(&mut self.console_search_contents);
+            if search_response.changed() {
+                self.console_log_filter
+                    .set_search_text(self.console_search_contents.as_str());
+            }
+
+            if ui.button("Clear").clicked() {
+                self.console_log.clear();
+            }
+        });
+
+        egui::CollapsingHeader::new("Filters")
+            .id_source("console-log-filters")
+            .show(ui, |ui| {
+                let mut default_level = self.console_log_filter.default_level();
+                ui.horizontal(|ui| {
+                    Self::draw_level_filter_combo_box(
+                        ui,
+                        "console-log-default-level",
+                        &mut default_level,
+                    );
+                    ui.label("Everything else");
+                });
+                self.console_log_filter.set_default_level(default_level);
+
+                // The targets of the entries, e.g. "mulay_gfx::assets::watcher". Targets set to
+                // "Default" use the level of their closest parent that has one set.
+                let targets: Vec<String> = self
+                    .console_log
+                    .read(|log| log.targets().iter().cloned().collect());
+                for target in targets {
+                    let mut level = self.console_log_filter.get_target_level(target.as_str());
+                    let is_changed = ui
+                        .horizontal(|ui| {
+                            let is_changed = Self::draw_optional_level_filter_combo_box(
+                                ui,
+                                target.as_str(),
+                                &mut level,
+                            );
+                            ui.label(target.as_str());
+                            is_changed
+                        })
+                        .inner;
+
+                    if is_changed {
+                        match level {
+                            Some(level) => self
+                                .console_log_filter
+                                .set_target_level(target.as_str(), level),
+                            None => self.console_log_filter.clear_target_level(target.as_str()),
+                        }
+                    }
+                }
+            });
+    }
+
+    fn draw_console_log(&mut self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .max_width(f32::INFINITY)
+            .max_height(256f32)
+            .auto_shrink([false, true])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                let filter = &self.console_log_filter;
+                self.console_log.read(|log| {
+                    for entry in log.entries().filter(|entry| filter.matches(entry)) {
+                        ui.add(egui::Label::new(Self::format_log_entry(ui, entry)).wrap(true));
+                    }
+                });
+            });
+    }
+
+    fn format_log_entry(ui: &egui::Ui, entry: &LogEntry) -> egui::RichText {
+        // Output of the console, e.g. of commands, is shown as is.
+        if entry.target() == CONSOLE_LOG_TARGET && entry.level() == Level::Info {
+            return egui::RichText::new(entry.message()).monospace();
+        }
+
+        let text = egui::RichText::new(format!(
+            "[{}] {}: {}",
+            entry.level(),
+            entry.target(),
+            entry.message()
+        ))
+        .monospace();
+        match entry.level() {
+            Level::Error => text.color(ui.visuals().error_fg_color),
+            Level::Warn => text.color(ui.visuals().warn_fg_color),
+            Level::Info => text,
+            Level::Debug => text.color(egui::Color32::GRAY),
+            Level::Trace => text.color(egui::Color32::DARK_GRAY),
+        }
+    }
+
+    fn draw_level_filter_combo_box(ui: &mut egui::Ui, id_source: &str, level: &mut LevelFilter) {
+        egui::ComboBox::from_id_source(id_source)
+            .selected_text(level.as_str())
+            .width(80f32)
+            .show_ui(ui, |ui| {
+                for level_filter in LOG_LEVEL_FILTERS {
+                    ui.selectable_value(level, level_filter, level_filter.as_str());
+                }
+            });
+    }
+
+    // Returns whether the level was changed. None is shown as "Default".
+    fn draw_optional_level_filter_combo_box(
+        ui: &mut egui::Ui,
+        id_source: &str,
+        level: &mut Option<LevelFilter>,
+    ) -> bool {
+        let previous_level = *level;
+        egui::ComboBox::from_id_source(id_source)
+            .selected_text(level.map_or("Default", |level| level.as_str()))
+            .width(80f32)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(level, None, "Default");
+                for level_filter in LOG_LEVEL_FILTERS {
+                    ui.selectable_value(level, Some(level_filter), level_filter.as_str());
+                }
+            });
+
+        *level != previous_level
+    }
+
+    fn draw_console_command_line(&mut self, ui: &mut egui::Ui) {
+        let command_line_id = egui::Id::new("console-command-line");
+
+        // Tab and the up and down keys are taken before the text edit sees them, since they
+        // would move the focus or the cursor otherwise.
+        if ui.memory(|mem| mem.has_focus(command_line_id)) {
+            let (is_tab_pressed, is_up_pressed, is_down_pressed) = ui.input_mut(|i| {
+                (
+                    i.consume_key(egui::Modifiers::NONE, egui::Key::Tab),
+                    i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                    i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                )
+            });
+
+            if is_tab_pressed {
+                self.console_requests.push(ConsoleRequest::Complete(
+                    self.console_command_contents.clone(),
+                ));
+            }
+
+            let history_entry = if is_up_pressed {
+                self.console_history
+                    .previous_entry(self.console_command_contents.as_str())
+            } else if is_down_pressed {
+                self.console_history.next_entry()
+            } else {
+                None
+            };
+            if let Some(history_entry) = history_entry {
+                self.console_command_contents = String::from(history_entry);
+                self.is_console_command_replaced = true;
+            }
+        }
+
+        let mut textedit_output = egui::TextEdit::singleline(&mut self.console_command_contents)
+            .id(command_line_id)
+            .lock_focus(true)
+            .show(ui);
+
+        if self.is_console_command_replaced {
+            let cursor = egui::text::CCursor::new(self.console_command_contents.chars().count());
+            textedit_output
+                .state
+                .set_ccursor_range(Some(egui::text::CCursorRange::one(cursor)));
+            textedit_output.state.store(ui.ctx(), command_line_id);
+            self.is_console_command_replaced = false;
+        }
+
+        let textedit_response = textedit_output.response;
+        if textedit_response.lost_focus()
+            && ui.input(|i| i.key_pressed(egui::Key::Enter))
+            && !self.console_command_contents.is_empty()
+        {
+            let command = std::mem::take(&mut self.console_command_contents);
+            self.console_history.push(command.as_str());
+            self.console_requests.push(ConsoleRequest::Execute(command));
+
+            textedit_response.request_focus();
+        }
+    }
+
     pub fn process_input(&mut self, window: &sdl2::video::Window, event: sdl2::event::Event) {
         self.egui_state
             .process_input(window, event, &mut self.egui_painter);